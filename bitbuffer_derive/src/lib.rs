@@ -51,7 +51,10 @@
 //!
 //! # Enums
 //!
-//! The implementation can be derived for an enum as long as every variant of the enum either has no field, or an unnamed field that implements `BitRead` or `BitReadSized`
+//! The implementation can be derived for an enum as long as every variant of the enum either has no fields, or only has fields that implement `BitRead` or `BitReadSized`
+//!
+//! Variants with fields are read field by field like structs, for variants with a single unnamed field the
+//! size can also be set on the variant itself.
 //!
 //! The enum is read by first reading a set number of bits as the discriminant of the enum, then the variant for the read discriminant is read.
 //!
@@ -89,6 +92,21 @@
 //! ```
 //!
 //! ```
+//! # use bitbuffer::BitRead;
+//! #
+//! #[derive(BitRead)]
+//! #[discriminant_bits = 2]
+//! enum TestFieldsEnum {
+//!     Move {
+//!         x: i16,
+//!         #[size = 3]
+//!         dir: u8,
+//!     },
+//!     Pair(u8, #[size = 4] u8),
+//! }
+//! ```
+//!
+//! ```
 //! # use bitbuffer::BitReadSized;
 //! #
 //! #[derive(BitReadSized, PartialEq, Debug)]
@@ -103,6 +121,284 @@
 //! }
 //! ```
 //!
+//! A variant can be marked as wildcard using `#[discriminant = "_"]`, it will be used for any discriminant that doesn't match another variant.
+//! To preserve the read discriminant, a wildcard variant can capture it in a field marked with `#[discriminant]`,
+//! when writing the variant the captured discriminant is written back.
+//! The captured field has to be an integer type that can hold every value of `discriminant_bits`, and writing
+//! fails with `BitError::ValueTooLarge` if the captured value doesn't fit, or with `BitError::DiscriminantConflict`
+//! if it belongs to another variant.
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite, BitReadStream, Endianness};
+//! #
+//! #[derive(BitRead, BitWrite)]
+//! #[discriminant_bits = 4]
+//! #[endianness = "E"]
+//! enum Message<'a, E: Endianness> {
+//!     Ping,
+//!     Data(u8),
+//!     #[discriminant = "_"]
+//!     Unknown {
+//!         #[discriminant]
+//!         tag: u8,
+//!         #[size = 8]
+//!         body: BitReadStream<'a, E>,
+//!     },
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! # use bitbuffer::BitRead;
+//! #
+//! #[derive(BitRead)]
+//! #[discriminant_bits = 8]
+//! enum Narrow {
+//!     Ping,
+//!     #[discriminant = "_"]
+//!     Unknown(#[discriminant] i8),
+//! }
+//! ```
+//!
+//! # Alignment
+//!
+//! Fields, enum variants and types can be aligned using the `align` attribute, before reading or writing the
+//! field the stream is moved to the next position that is a multiple of the provided number of bits.
+//! When writing, the stream is padded with zero bits, when reading the padding bits are skipped.
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite};
+//! #
+//! #[derive(BitRead, BitWrite)]
+//! #[align = 8] // start the struct at a byte boundary
+//! struct AlignedStruct {
+//!     #[size = 3]
+//!     flags: u8,
+//!     #[align = 8] // skip the remaining 5 bits of the first byte
+//!     payload: u16,
+//! }
+//! ```
+//!
+//! # Conditional fields
+//!
+//! A field of type `Option<T>` can be made conditional using the `read_if` attribute, the field is only read when
+//! the expression evaluates to true and is `None` otherwise. The expression can use previously read fields or `input_size`.
+//! Unlike a plain `Option<T>` field, no bit is used to mark the presence of the field.
+//!
+//! When writing, the field is written when the condition is true, writing fails with `BitError::OptionalFieldMismatch`
+//! if the presence of the value doesn't match the condition.
+//! Only integer and boolean fields can be used in the condition when writing.
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite};
+//! #
+//! #[derive(BitRead, BitWrite)]
+//! struct ConditionalStruct {
+//!     has_timestamp: bool,
+//!     #[read_if = "has_timestamp"]
+//!     timestamp: Option<u32>,
+//!     #[size = 3]
+//!     kind: u8,
+//!     #[read_if = "kind == 2"]
+//!     #[size = 12]
+//!     extra: Option<u16>,
+//! }
+//! ```
+//!
+//! # Terminated lists
+//!
+//! Fields of type `Vec<T>` where the number of items isn't known up front can use one of the following attributes
+//! instead of a size:
+//!
+//! - `until`: items are read until an item equal to the expression is read, the terminator is not included in the list.
+//!   When writing, the terminator is written after the items and writing fails if one of the items equals the terminator.
+//! - `until_eof`: items are read until the end of the stream.
+//! - `continuation_bit`: every item is preceded by a set bit, the list ends at the first unset bit.
+//!   When writing, the continuation bits are written before the items and the list is terminated by an unset bit.
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite};
+//! #
+//! #[derive(BitRead, BitWrite)]
+//! struct ListStruct {
+//!     #[until = "0"]
+//!     ids: Vec<u16>,
+//!     #[continuation_bit]
+//!     flags: Vec<bool>,
+//!     #[until_eof]
+//!     rest: Vec<u8>,
+//! }
+//! ```
+//!
+//! Only one of the attributes can be set for a field.
+//!
+//! ```compile_fail
+//! # use bitbuffer::BitRead;
+//! #
+//! #[derive(BitRead)]
+//! struct ListStruct {
+//!     #[until_eof]
+//!     #[continuation_bit]
+//!     flags: Vec<bool>,
+//! }
+//! ```
+//!
+//! # Computed fields
+//!
+//! When writing, the value of a field marked with `computed` is taken from the expression instead of the field,
+//! the expression can use all fields of the struct or variant. The computed value is also used in the size and
+//! condition expressions of other fields. The computed value is converted to the type of the field using `TryFrom`,
+//! writing fails with `BitError::ValueTooLarge` if it doesn't fit.
+//!
+//! Fields marked with `bit_length_of` or `byte_length_of` contain the length of the section starting after the
+//! field and ending with the named field. When writing, the length is filled in after the section is written, for
+//! `byte_length_of` the section is padded with zero bits to a whole number of bytes.
+//! Length fields are at most 55 bits, fields wider than 32 bits need an explicit `size`.
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite, BitReadStream, Endianness};
+//! #
+//! #[derive(BitRead, BitWrite)]
+//! #[endianness = "E"]
+//! struct LengthStruct<'a, E: Endianness> {
+//!     #[computed = "items.len()"]
+//!     count: u8,
+//!     #[size = "count"]
+//!     items: Vec<u16>,
+//!     #[size = 12]
+//!     #[bit_length_of = "body"]
+//!     body_length: u16,
+//!     #[size = "body_length"]
+//!     body: BitReadStream<'a, E>,
+//! }
+//! ```
+//!
+//! # Custom read and write functions
+//!
+//! Fields that can't be read or written using their `BitRead` or `BitWrite` implementation can use custom functions
+//! using the `bitread_with` and `bitwrite_with` attributes, or `bit_with` to use the `read` and `write` functions from a module.
+//!
+//! The read function is called with the stream, the write function with a reference to the value and the stream.
+//! If a size is set for the field, it's passed as the next argument. Extra arguments, which can use previous fields,
+//! are passed after that when the function is written as a call: `#[bitread_with = "read_scaled(scale)"]`.
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite, BitReadStream, BitWriteStream, Endianness, Result};
+//! #
+//! mod timestamp {
+//!     # use bitbuffer::{BitReadStream, BitWriteStream, Endianness, Result};
+//!     pub fn read<E: Endianness>(stream: &mut BitReadStream<E>) -> Result<u32> {
+//!         stream.read_int(24)
+//!     }
+//!
+//!     pub fn write<E: Endianness>(value: &u32, stream: &mut BitWriteStream<E>) -> Result<()> {
+//!         stream.write_int(*value, 24)
+//!     }
+//! }
+//!
+//! fn read_scaled<E: Endianness>(stream: &mut BitReadStream<E>, size: usize, scale: u8) -> Result<f32> {
+//!     Ok(stream.read_int::<u16>(size)? as f32 / scale as f32)
+//! }
+//!
+//! fn write_scaled<E: Endianness>(value: &f32, stream: &mut BitWriteStream<E>, size: usize, scale: u8) -> Result<()> {
+//!     stream.write_int((value * scale as f32) as u16, size)
+//! }
+//!
+//! #[derive(BitRead, BitWrite)]
+//! struct CustomStruct {
+//!     #[bit_with = "timestamp"]
+//!     time: u32,
+//!     scale: u8,
+//!     #[size = 10]
+//!     #[bitread_with = "read_scaled(scale)"]
+//!     #[bitwrite_with = "write_scaled(scale)"]
+//!     value: f32,
+//! }
+//! ```
+//!
+//! # Magic values and assertions
+//!
+//! Types and fields can be prefixed by a constant magic value using the `magic` attribute, which is checked
+//! when reading and written automatically when writing. The magic value is either a byte string or an integer,
+//! the number of bits for an integer is taken from the number of digits of a hexadecimal or binary literal
+//! (`0x0001` is read as 16 bits). Reading fails with `BitError::InvalidMagic`
+//! if the read value doesn't match.
+//!
+//! The `assert` attribute checks an expression after the field is read, the expression can use the field itself and
+//! previously read fields. Reading fails with `BitError::AssertionFailed` if the expression evaluates to false.
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite};
+//! #
+//! #[derive(BitRead, BitWrite)]
+//! #[magic = b"RIFF"]
+//! struct Header {
+//!     size: u32,
+//!     #[magic = 0xCAFE]
+//!     #[assert = "version <= 2"]
+//!     version: u8,
+//! }
+//! ```
+//!
+//! # Compile time size
+//!
+//! Types deriving `BitRead` with the `bit_size` attribute also implement `BitSize`, which provides the number of bits
+//! of the type as an associated constant when all fields have a fixed size. The types of the fields are required to
+//! implement `BitSize`, or `BitSizeSized` for fields with a `size` attribute.
+//!
+//! The `assert_bit_size` attribute implies `bit_size` and fails compilation when the size of the type doesn't match
+//! the expected number of bits.
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitSize};
+//! #
+//! #[derive(BitRead)]
+//! #[assert_bit_size = 48]
+//! struct Coordinates {
+//!     #[size = 12]
+//!     x: u16,
+//!     #[size = 12]
+//!     y: u16,
+//!     z: i16,
+//!     #[size = 6]
+//!     flags: u8,
+//!     #[size = 2]
+//!     kind: u8,
+//! }
+//!
+//! const SIZE: usize = match Coordinates::BIT_SIZE {
+//!     Some(size) => size,
+//!     None => 0,
+//! };
+//! assert_eq!(SIZE, 48);
+//! ```
+//!
+//! ```compile_fail
+//! # use bitbuffer::BitRead;
+//! #
+//! #[derive(BitRead)]
+//! #[assert_bit_size = 16]
+//! struct TooLarge {
+//!     a: u8,
+//!     b: u16,
+//! }
+//! ```
+//!
+//! Deriving `BitWrite` or `BitWriteSized` implements `written_bit_size` or `written_bit_size_sized` as the sum of the sizes of the
+//! written fields. For types with alignment, custom write functions or `byte_length_of` fields the size is instead
+//! measured by writing the value.
+//!
+//! # Errors
+//!
+//! Errors that occur while reading a field are wrapped in `BitError::Context`, which contains the path to the
+//! field that failed to read (e.g. `Packet.entities[3].origin.x`) and the position in the stream the field started at.
+//! The underlying error can be retrieved using `BitError::root`.
+//!
+//! Before 0.11 the errors from derived implementations were returned unwrapped, code that matches on the error
+//! variant, like `Err(BitError::NotEnoughData { .. })`, should match on `err.root()` instead.
+//!
+//! The context is only added once a read fails, types with a fixed size are read without tracking the fields
+//! and read again field by field when an error occurs to find the failing field.
+//!
 //! # Endianness
 //!
 //! If the struct that `BitRead` or `BitReadSized` is derived for requires a Endianness type parameter, you need to tell the derive macro the name of the type parameter used
@@ -132,26 +428,70 @@
 //! }
 //! ```
 //!
+//! Fields and enum variants that use a different endianness than the rest of the type can be marked with
+//! the `le` or `be` attributes, the field or variant is then read and written in little or big endian byte order
+//! regardless of the endianness of the stream. Since the endianness also determines the order of the bits within a byte,
+//! these fields have to start at a byte boundary, reading or writing them at any other position
+//! returns a `BitError::UnalignedEndianness` error.
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite};
+//! #
+//! #[derive(BitRead, BitWrite)]
+//! struct MixedEndianStruct {
+//!     #[le]
+//!     length: u32,
+//!     #[be]
+//!     checksum: u16,
+//! }
+//! ```
+//!
 mod discriminant;
+mod magic;
 mod write;
 
 extern crate proc_macro;
 
 use crate::write::derive_bitwrite_trait;
 use discriminant::Discriminant;
+use magic::{add_magic_size, read_magic, Magic};
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, quote_spanned};
 use syn::spanned::Spanned;
 use syn::{
     parse_macro_input, parse_quote, parse_str, Attribute, Data, DataStruct, DeriveInput, Expr,
-    Fields, GenericParam, Ident, Lit, LitInt, LitStr, Path,
+    Fields, GenericArgument, GenericParam, Ident, Lit, LitInt, LitStr, Path, PathArguments, Type,
+    WherePredicate,
 };
 use syn_util::get_attribute_value;
 
 /// See the [crate documentation](index.html) for details
 #[proc_macro_derive(
     BitRead,
-    attributes(size, size_bits, discriminant_bits, discriminant, endianness)
+    attributes(
+        size,
+        size_bits,
+        discriminant_bits,
+        discriminant,
+        endianness,
+        align,
+        read_if,
+        bitread_with,
+        bitwrite_with,
+        bit_with,
+        magic,
+        assert,
+        until,
+        until_eof,
+        continuation_bit,
+        computed,
+        bit_length_of,
+        byte_length_of,
+        le,
+        be,
+        bit_size,
+        assert_bit_size
+    )
 )]
 pub fn derive_bitread(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     derive_bitread_trait(input, "BitRead".to_owned(), None)
@@ -161,7 +501,30 @@ pub fn derive_bitread(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 /// See the [crate documentation](index.html) for details
 #[proc_macro_derive(
     BitReadSized,
-    attributes(size, size_bits, discriminant_bits, discriminant, endianness)
+    attributes(
+        size,
+        size_bits,
+        discriminant_bits,
+        discriminant,
+        endianness,
+        align,
+        read_if,
+        bitread_with,
+        bitwrite_with,
+        bit_with,
+        magic,
+        assert,
+        until,
+        until_eof,
+        continuation_bit,
+        computed,
+        bit_length_of,
+        byte_length_of,
+        le,
+        be,
+        bit_size,
+        assert_bit_size
+    )
 )]
 pub fn derive_bitread_sized(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let extra_param = parse_str::<TokenStream>(", input_size: usize").unwrap();
@@ -171,7 +534,30 @@ pub fn derive_bitread_sized(input: proc_macro::TokenStream) -> proc_macro::Token
 /// See the [crate documentation](index.html) for details
 #[proc_macro_derive(
     BitWrite,
-    attributes(size, size_bits, discriminant_bits, discriminant, endianness)
+    attributes(
+        size,
+        size_bits,
+        discriminant_bits,
+        discriminant,
+        endianness,
+        align,
+        read_if,
+        bitread_with,
+        bitwrite_with,
+        bit_with,
+        magic,
+        assert,
+        until,
+        until_eof,
+        continuation_bit,
+        computed,
+        bit_length_of,
+        byte_length_of,
+        le,
+        be,
+        bit_size,
+        assert_bit_size
+    )
 )]
 pub fn derive_bitwrite(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     derive_bitwrite_trait(input, "BitWrite".into(), "write".into(), None)
@@ -181,7 +567,30 @@ pub fn derive_bitwrite(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
 /// See the [crate documentation](index.html) for details
 #[proc_macro_derive(
     BitWriteSized,
-    attributes(size, size_bits, discriminant_bits, discriminant, endianness)
+    attributes(
+        size,
+        size_bits,
+        discriminant_bits,
+        discriminant,
+        endianness,
+        align,
+        read_if,
+        bitread_with,
+        bitwrite_with,
+        bit_with,
+        magic,
+        assert,
+        until,
+        until_eof,
+        continuation_bit,
+        computed,
+        bit_length_of,
+        byte_length_of,
+        le,
+        be,
+        bit_size,
+        assert_bit_size
+    )
 )]
 pub fn derive_bitwrite_sized(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let extra_param = parse_str::<TokenStream>(", input_size: usize").unwrap();
@@ -233,6 +642,23 @@ fn derive_bitread_trait(
     );
     let parsed = parse(input.data.clone(), name, &input.attrs, false);
     let parsed_unchecked = parse(input.data.clone(), name, &input.attrs, true);
+    let size = add_magic_size(&input.attrs, size, span);
+    let bit_size_impl = if !input
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("bit_size"))
+        && get_attribute_value::<Lit>(&input.attrs, &["assert_bit_size"]).is_none()
+    {
+        quote! {}
+    } else if extra_param.is_none() {
+        bit_size_impl(&input)
+    } else {
+        quote_spanned! { span =>
+            compile_error!("#[bit_size] and #[assert_bit_size] are only supported when deriving `BitRead`");
+        }
+    };
+    let align = align(&input.attrs, quote!(stream), span);
+    let magic = read_magic(&input.attrs, quote!(stream), span);
 
     let endianness_placeholder = endianness.unwrap_or_else(|| "_E".to_owned());
     let trait_def_str = format!(
@@ -268,20 +694,27 @@ fn derive_bitread_trait(
         impl #impl_generics #trait_def for #name #ty_generics #where_clause {
             fn read(stream: &mut ::bitbuffer::BitReadStream<#lifetime, #endianness_ident>#extra_param) -> ::bitbuffer::Result<Self> {
                 // if the read has a predicable size, we can do the bounds check in one go
-                match <Self as #trait_def>::#size_method_name(#extra_param_call) {
-                    Some(size) => {
-                        let end = stream.check_read(size)?;
-                        unsafe {
+                if let Some(size) = <Self as #trait_def>::#size_method_name(#extra_param_call) {
+                    if let Ok(end) = stream.check_read(size) {
+                        let start = stream.pos();
+                        match unsafe {
                             <Self as #trait_def>::read_unchecked(stream, #extra_param_call end)
+                        } {
+                            Ok(value) => return Ok(value),
+                            // read again field by field to find the field that fails
+                            Err(_) => stream.set_pos(start)?,
                         }
-                    },
-                    None => {
-                        #parsed
                     }
                 }
+                // if there isn't enough data we read field by field to find the field that fails
+                #align
+                #magic
+                #parsed
             }
 
             unsafe fn read_unchecked(stream: &mut ::bitbuffer::BitReadStream<#lifetime, #endianness_ident>#extra_param, end: bool) -> ::bitbuffer::Result<Self> {
+                #align
+                #magic
                 #parsed_unchecked
             }
 
@@ -289,6 +722,8 @@ fn derive_bitread_trait(
                 #size
             }
         }
+
+        #bit_size_impl
     };
 
     // panic!("{}", TokenStream::to_string(&expanded));
@@ -299,80 +734,17 @@ fn derive_bitread_trait(
 fn parse(data: Data, struct_name: &Ident, attrs: &[Attribute], unchecked: bool) -> TokenStream {
     let span = struct_name.span();
 
-    match data {
-        Data::Struct(DataStruct { fields, .. }) => {
-            let values = fields.iter().map(|f| {
-                // Get attributes `#[..]` on each field
-                let size = get_field_size(&f.attrs, f.span());
-                let field_type = &f.ty;
-                let span = f.span();
-                if unchecked {
-                    match size {
-                        Some(size) => {
-                            quote_spanned! { span =>
-                                {
-                                    let _size: usize = #size;
-                                    stream.read_sized_unchecked::<#field_type>(_size, end)?
-                                }
-                            }
-                        }
-                        None => {
-                            quote_spanned! { span =>
-                                stream.read_unchecked::<#field_type>(end)?
-                            }
-                        }
-                    }
-                } else {
-                    match size {
-                        Some(size) => {
-                            quote_spanned! { span =>
-                                {
-                                    let _size: usize = #size;
-                                    stream.read_sized::<#field_type>(_size)?
-                                }
-                            }
-                        }
-                        None => {
-                            quote_spanned! { span =>
-                                stream.read::<#field_type>()?
-                            }
-                        }
-                    }
-                }
-            });
+    let type_name = LitStr::new(&struct_name.to_string(), span);
 
-            match &fields {
-                Fields::Named(fields) => {
-                    let definitions = fields.named.iter().zip(values).map(|(f, value)| {
-                        let name = &f.ident;
-                        quote_spanned! { f.span() =>
-                            let #name = #value;
-                        }
-                    });
-                    let struct_definition = fields.named.iter().map(|f| {
-                        let name = &f.ident;
-                        quote_spanned! { f.span() =>
-                            #name,
-                        }
-                    });
-                    quote_spanned! { span =>
-                        #(#definitions)*
-
-                        Ok(#struct_name {
-                            #(#struct_definition)*
-                        })
-                    }
-                }
-                Fields::Unnamed(_) => quote_spanned! { span =>
-                    Ok(#struct_name(
-                        #(#values ,)*
-                    ))
-                },
-                Fields::Unit => quote_spanned! {span=>
-                    Ok(#struct_name)
-                },
-            }
-        }
+    match data {
+        Data::Struct(DataStruct { fields, .. }) => read_fields(
+            &fields,
+            &type_name,
+            quote!(#struct_name),
+            unchecked,
+            !unchecked,
+            span,
+        ),
         Data::Enum(data) => {
             let discriminant_bits: u64 = match get_attribute_value(attrs, &["discriminant_bits"]) {
                 Some(attr) => attr,
@@ -387,29 +759,92 @@ fn parse(data: Data, struct_name: &Ident, attrs: &[Attribute], unchecked: bool)
             let match_arms = data.variants.iter().map(|variant| {
                 let span = variant.span();
                 let variant_name = &variant.ident;
+                let variant_lit = LitStr::new(&variant_name.to_string(), variant_name.span());
+                let has_discriminant_field = variant
+                    .fields
+                    .iter()
+                    .any(|f| is_discriminant_field(&f.attrs));
                 let read_fields = match &variant.fields {
                     Fields::Unit => quote_spanned! {span=>
                         #struct_name::#variant_name
                     },
+                    _ if has_discriminant_field
+                        && !matches!(Discriminant::from(variant), Discriminant::Wildcard) =>
+                    {
+                        quote_spanned! {span=>
+                            compile_error!("#[discriminant] fields are only supported for wildcard variants")
+                        }
+                    }
+                    fields if is_field_by_field(fields) => {
+                        let align = align(&variant.attrs, quote!(stream), span);
+                        let read = read_fields(
+                            &variant.fields,
+                            &type_name,
+                            quote!(#struct_name::#variant_name),
+                            false,
+                            !unchecked,
+                            span,
+                        );
+                        let read = read_with_endianness(&variant.attrs, read, span);
+                        if unchecked {
+                            quote_spanned! { span =>
+                                {
+                                    #align
+                                    let value: ::bitbuffer::Result<_> = { #read };
+                                    value?
+                                }
+                            }
+                        } else {
+                            quote_spanned! { span =>
+                                stream.read_with_context(
+                                    ::bitbuffer::PathSegment::Variant { type_name: #type_name, variant: #variant_lit },
+                                    // the stream is unused if the discriminant is the only field
+                                    |#[allow(unused_variables)] stream| {
+                                        #align
+                                        #read
+                                    },
+                                )?
+                            }
+                        }
+                    }
                     Fields::Unnamed(f) => {
                         let size = get_field_size(&variant.attrs, f.span());
-                        match size {
+                        let align = align(&variant.attrs, quote!(stream), span);
+                        let value = match size {
                             Some(size) => {
                                 quote_spanned! { span =>
-                                    #struct_name::#variant_name({
-                                        let _size:usize = #size;
-                                        stream.read_sized(_size)?
-                                    })
+                                    let _size:usize = #size;
+                                    stream.read_sized(_size)
                                 }
                             }
                             None => {
                                 quote_spanned! { span =>
-                                    #struct_name::#variant_name(stream.read()?)
+                                    stream.read()
                                 }
                             }
+                        };
+                        let value = read_with_endianness(&variant.attrs, value, span);
+                        if unchecked {
+                            quote_spanned! { span =>
+                                #struct_name::#variant_name({
+                                    #align
+                                    let value: ::bitbuffer::Result<_> = { #value };
+                                    value?
+                                })
+                            }
+                        } else {
+                            quote_spanned! { span =>
+                                #struct_name::#variant_name(stream.read_with_context(
+                                    ::bitbuffer::PathSegment::Variant { type_name: #type_name, variant: #variant_lit },
+                                    |stream| {
+                                        #align
+                                        #value
+                                    },
+                                )?)
+                            }
                         }
                     }
-                    _ => unimplemented!(),
+                    Fields::Named(_) => unreachable!(),
                 };
 
                 let discriminant_token: TokenStream = match Discriminant::from(variant) {
@@ -426,8 +861,29 @@ fn parse(data: Data, struct_name: &Ident, attrs: &[Attribute], unchecked: bool)
                         quote_spanned! { span => #lit }
                     }
                 };
+                // the captured discriminant is converted with `as`, so the field needs to be able to hold all discriminants
+                let capture_check = variant
+                    .fields
+                    .iter()
+                    .find(|f| is_discriminant_field(&f.attrs))
+                    .map(|f| {
+                        let ty = &f.ty;
+                        let message = format!(
+                            "the #[discriminant] field of {}::{} can't hold all values of {} discriminant bits",
+                            struct_name, variant_name, discriminant_bits
+                        );
+                        quote_spanned! { f.span() =>
+                            const _: () = assert!(
+                                (<#ty>::MAX as u128).count_ones() as u64 >= #discriminant_bits,
+                                #message
+                            );
+                        }
+                    });
                 quote_spanned! {span=>
-                    #discriminant_token => #read_fields,
+                    #discriminant_token => {
+                        #capture_check
+                        #read_fields
+                    }
                 }
             });
 
@@ -446,13 +902,253 @@ fn parse(data: Data, struct_name: &Ident, attrs: &[Attribute], unchecked: bool)
                 })
             }
         }
-        _ => unimplemented!(),
+        Data::Union(_) => quote_spanned! { span =>
+            compile_error!("BitRead can't be derived for unions")
+        },
     }
 }
 
+/// Read all fields of a struct or enum variant, returning the constructed value
+///
+/// With `context` set, errors are annotated with the path of the field that failed to read.
+/// This is skipped for the unchecked fast path, where the read is repeated with context if it fails.
+fn read_fields(
+    fields: &Fields,
+    type_name: &LitStr,
+    constructor: TokenStream,
+    unchecked: bool,
+    context: bool,
+    span: Span,
+) -> TokenStream {
+    let values = fields.iter().enumerate().map(|(index, f)| {
+        // Get attributes `#[..]` on each field
+        let size = get_field_size(&f.attrs, f.span());
+        let field_name = match &f.ident {
+            Some(ident) => LitStr::new(&ident.to_string(), ident.span()),
+            None => LitStr::new(&index.to_string(), f.span()),
+        };
+        let span = f.span();
+        if is_discriminant_field(&f.attrs) {
+            let field_type = &f.ty;
+            return quote_spanned! { span =>
+                discriminant as #field_type
+            };
+        }
+        let condition = get_read_if(&f.attrs);
+        // fields with a condition are read as the inner type of the `Option`
+        let field_type = match (&condition, inner_type(&f.ty, "Option")) {
+            (Some(_), Some(inner)) => inner,
+            (Some(_), None) => {
+                return quote_spanned! { span =>
+                    compile_error!("#[read_if] is only supported for fields of type Option<T>")
+                };
+            }
+            (None, _) => &f.ty,
+        };
+        let align = align(&f.attrs, quote!(stream), span);
+        let custom_read = get_custom_fn(&f.attrs, "bitread_with", "read");
+        let value = if let Some(terminator) = Terminator::get(&f.attrs) {
+            let item_type = match inner_type(field_type, "Vec") {
+                Some(item_type) => item_type,
+                None => {
+                    return quote_spanned! { span =>
+                        compile_error!("#[until], #[until_eof] and #[continuation_bit] are only supported for fields of type Vec<T>")
+                    };
+                }
+            };
+            match terminator {
+                Terminator::Value(terminator) => quote_spanned! { span =>
+                    stream.read_until::<#item_type>(&(#terminator))
+                },
+                Terminator::Eof => quote_spanned! { span =>
+                    stream.read_until_eof::<#item_type>()
+                },
+                Terminator::ContinuationBit => quote_spanned! { span =>
+                    stream.read_continued::<#item_type>()
+                },
+                Terminator::Conflict(error) => return error,
+            }
+        } else if let Some((read_fn, args)) = custom_read {
+            match size {
+                Some(size) => {
+                    quote_spanned! { span =>
+                        {
+                            let _size: usize = #size;
+                            #read_fn(stream, _size #(, #args)*)
+                        }
+                    }
+                }
+                None => {
+                    quote_spanned! { span =>
+                        #read_fn(stream #(, #args)*)
+                    }
+                }
+            }
+        } else if unchecked {
+            match size {
+                Some(size) => {
+                    quote_spanned! { span =>
+                        {
+                            let _size: usize = #size;
+                            stream.read_sized_unchecked::<#field_type>(_size, end)
+                        }
+                    }
+                }
+                None => {
+                    quote_spanned! { span =>
+                        stream.read_unchecked::<#field_type>(end)
+                    }
+                }
+            }
+        } else {
+            match size {
+                Some(size) => {
+                    quote_spanned! { span =>
+                        {
+                            let _size: usize = #size;
+                            stream.read_sized::<#field_type>(_size)
+                        }
+                    }
+                }
+                None => {
+                    quote_spanned! { span =>
+                        stream.read::<#field_type>()
+                    }
+                }
+            }
+        };
+        let magic = read_magic(&f.attrs, quote!(stream), span);
+        let value = read_with_endianness(
+            &f.attrs,
+            quote_spanned! { span =>
+                #magic
+                #value
+            },
+            span,
+        );
+        let value = quote_spanned! { span =>
+            {
+                #align
+                #value
+            }
+        };
+        let value = match condition {
+            Some(condition) => quote_spanned! { span =>
+                if #condition {
+                    #value.map(Some)
+                } else {
+                    Ok(None)
+                }
+            },
+            None => value,
+        };
+        // the assertion is checked inside the context so the error points to the start of the field
+        let value = match get_assert(&f.attrs) {
+            Some(assertion) => {
+                let assertion_string = quote!(#assertion).to_string();
+                let binding = match &f.ident {
+                    Some(ident) => quote!(#ident),
+                    None => quote!(__value),
+                };
+                let ty = &f.ty;
+                quote_spanned! { span =>
+                    {
+                        let #binding: #ty = #value?;
+                        if !(#assertion) {
+                            return Err(::bitbuffer::BitError::AssertionFailed {
+                                field: #field_name.to_string(),
+                                assertion: #assertion_string.to_string(),
+                            });
+                        }
+                        Ok(#binding)
+                    }
+                }
+            }
+            None => value,
+        };
+        if context {
+            quote_spanned! { span =>
+                stream.read_with_context(
+                    ::bitbuffer::PathSegment::Field { type_name: #type_name, field: #field_name },
+                    |stream| #value,
+                )?
+            }
+        } else {
+            quote_spanned! { span =>
+                {
+                    let value: ::bitbuffer::Result<_> = #value;
+                    value?
+                }
+            }
+        }
+    });
+
+    match fields {
+        Fields::Named(fields) => {
+            let definitions = fields.named.iter().zip(values).map(|(f, value)| {
+                let name = &f.ident;
+                quote_spanned! { f.span() =>
+                    let #name = #value;
+                }
+            });
+            let struct_definition = fields.named.iter().map(|f| {
+                let name = &f.ident;
+                quote_spanned! { f.span() =>
+                    #name,
+                }
+            });
+            quote_spanned! { span =>
+                #(#definitions)*
+
+                Ok(#constructor {
+                    #(#struct_definition)*
+                })
+            }
+        }
+        Fields::Unnamed(_) => quote_spanned! { span =>
+            Ok(#constructor(
+                #(#values ,)*
+            ))
+        },
+        Fields::Unit => quote_spanned! {span=>
+            Ok(#constructor)
+        },
+    }
+}
+
+/// Check if the fields of an enum variant are read and written one by one
+///
+/// Variants with a single unnamed field without attributes are read as the type of the field, using the
+/// attributes set on the variant.
+fn is_field_by_field(fields: &Fields) -> bool {
+    match fields {
+        Fields::Named(_) => true,
+        Fields::Unnamed(fields) => {
+            fields.unnamed.len() != 1
+                || fields
+                    .unnamed
+                    .iter()
+                    .any(|f| f.attrs.iter().any(|attr| !attr.path.is_ident("doc")))
+        }
+        Fields::Unit => false,
+    }
+}
+
+/// Check if the field is marked to contain the discriminant of the enum variant
+fn is_discriminant_field(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("discriminant"))
+}
+
 fn size(data: Data, struct_name: &Ident, attrs: &[Attribute], has_input_size: bool) -> TokenStream {
     let span = struct_name.span();
 
+    // the padding needed for alignment depends on the position in the stream
+    if get_align(attrs).is_some() {
+        return quote_spanned! { span =>
+            None
+        };
+    }
+
     match data {
         Data::Struct(DataStruct { fields, .. }) => {
             let sizes = fields.iter().map(|f| {
@@ -461,7 +1157,7 @@ fn size(data: Data, struct_name: &Ident, attrs: &[Attribute], has_input_size: bo
                     let size = get_field_size(&f.attrs, f.span());
                     let field_type = &f.ty;
                     let span = f.span();
-                    match size {
+                    let size = match size {
                         Some(size) => {
                             quote_spanned! { span =>
                                 <#field_type as ::bitbuffer::BitReadSized<'_, ::bitbuffer::LittleEndian>>::bit_size_sized(#size)
@@ -472,7 +1168,8 @@ fn size(data: Data, struct_name: &Ident, attrs: &[Attribute], has_input_size: bo
                                 <#field_type as ::bitbuffer::BitRead<'_, ::bitbuffer::LittleEndian>>::bit_size()
                             }
                         }
-                    }
+                    };
+                    add_magic_size(&f.attrs, size, span)
                 } else {
                     quote_spanned! { span =>
                         None
@@ -518,12 +1215,138 @@ fn size(data: Data, struct_name: &Ident, attrs: &[Attribute], has_input_size: bo
                 }
             }
         }
-        _ => unimplemented!(),
+        // unions are rejected when generating the read implementation
+        Data::Union(_) => quote_spanned! { span => None },
+    }
+}
+
+/// Implement `BitSize` for the type and check the size if the `assert_bit_size` attribute is set
+fn bit_size_impl(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let span = input.span();
+    let (size, bounds) = const_size(&input.data, &input.attrs, span);
+    let mut generics = input.generics.clone();
+    let where_clause = generics.make_where_clause();
+    for bound in bounds {
+        where_clause.predicates.push(bound);
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let assert = match get_attribute_value::<Lit>(&input.attrs, &["assert_bit_size"]) {
+        Some(Lit::Int(expected)) => {
+            if input
+                .generics
+                .params
+                .iter()
+                .any(|param| !matches!(param, GenericParam::Lifetime(_)))
+            {
+                quote_spanned! { span =>
+                    compile_error!("#[assert_bit_size] is not supported for generic types");
+                }
+            } else {
+                let lifetimes = input.generics.params.iter().map(|_| quote!('static));
+                let message = format!(
+                    "the bit size of {} doesn't match the asserted size of {} bits",
+                    name, expected
+                );
+                quote_spanned! { span =>
+                    const _: () = ::std::assert!(
+                        ::std::matches!(
+                            <#name<#(#lifetimes),*> as ::bitbuffer::BitSize>::BIT_SIZE,
+                            Some(#expected)
+                        ),
+                        #message
+                    );
+                }
+            }
+        }
+        Some(_) => quote_spanned! { span =>
+            compile_error!("assert_bit_size attribute is required to be an integer literal");
+        },
+        None => quote! {},
+    };
+
+    quote_spanned! { span =>
+        impl #impl_generics ::bitbuffer::BitSize for #name #ty_generics #where_clause {
+            const BIT_SIZE: Option<usize> = #size;
+        }
+
+        #assert
     }
 }
 
+/// Const expression for the size of a type, and the bounds required for the field types
+///
+/// This follows the same rules as `size`, but only uses sizes that are known at compile time.
+fn const_size(data: &Data, attrs: &[Attribute], span: Span) -> (TokenStream, Vec<WherePredicate>) {
+    let with_magic = |attrs: &[Attribute], size: TokenStream| match Magic::get(attrs) {
+        Some(magic) => {
+            let bits = magic.bits();
+            quote!(::bitbuffer::add_bit_sizes(#size, Some(#bits)))
+        }
+        None => size,
+    };
+
+    // the padding needed for alignment depends on the position in the stream
+    if get_align(attrs).is_some() {
+        return (quote!(None), Vec::new());
+    }
+
+    let mut bounds = Vec::new();
+    let size = match data {
+        Data::Struct(DataStruct { fields, .. }) => {
+            let sizes = fields.iter().map(|f| {
+                if !is_const_size(&f.attrs, false) {
+                    return quote!(None);
+                }
+                let field_type = &f.ty;
+                let size = match get_attribute_value::<Lit>(&f.attrs, &["size"]) {
+                    Some(size) => {
+                        bounds.push(parse_quote!(#field_type: ::bitbuffer::BitSizeSized<#size>));
+                        quote!(<#field_type as ::bitbuffer::BitSizeSized<#size>>::BIT_SIZE)
+                    }
+                    None => {
+                        bounds.push(parse_quote!(#field_type: ::bitbuffer::BitSize));
+                        quote!(<#field_type as ::bitbuffer::BitSize>::BIT_SIZE)
+                    }
+                };
+                with_magic(&f.attrs, size)
+            });
+            let sizes: Vec<TokenStream> = sizes.collect();
+            quote_spanned! { span =>
+                {
+                    let size = Some(0usize);
+                    #(let size = ::bitbuffer::add_bit_sizes(size, #sizes);)*
+                    size
+                }
+            }
+        }
+        Data::Enum(data) => {
+            let discriminant_bits = get_attribute_value::<u64>(attrs, &["discriminant_bits"]);
+            let is_unit = data
+                .variants
+                .iter()
+                .all(|variant| matches!(variant.fields, Fields::Unit));
+            match discriminant_bits {
+                Some(discriminant_bits) if is_unit => {
+                    let discriminant_bits = discriminant_bits as usize;
+                    quote!(Some(#discriminant_bits))
+                }
+                _ => quote!(None),
+            }
+        }
+        Data::Union(_) => quote!(None),
+    };
+    (with_magic(attrs, size), bounds)
+}
+
 fn is_const_size(attrs: &[Attribute], has_input_size: bool) -> bool {
-    if get_attribute_value::<Lit>(attrs, &["size_bits"]).is_some() {
+    if get_attribute_value::<Lit>(attrs, &["size_bits"]).is_some()
+        || get_align(attrs).is_some()
+        || get_read_if(attrs).is_some()
+        || get_custom_fn(attrs, "bitread_with", "read").is_some()
+        || Terminator::get(attrs).is_some()
+    {
         return false;
     }
     get_attribute_value(attrs, &["size"])
@@ -560,6 +1383,190 @@ fn get_field_size(attrs: &[Attribute], span: Span) -> Option<TokenStream> {
         })
 }
 
+/// Get the condition for reading an optional field
+fn get_read_if(attrs: &[Attribute]) -> Option<Expr> {
+    get_attribute_value::<Lit>(attrs, &["read_if"]).map(|condition_lit| match condition_lit {
+        Lit::Str(condition) => {
+            parse_str::<Expr>(&condition.value()).expect("invalid expression for read_if attribute")
+        }
+        _ => panic!("read_if attribute is required to be a string containing an expression"),
+    })
+}
+
+/// How the end of a list with an unknown number of items is marked
+enum Terminator {
+    /// The list is terminated by an item with the value of the expression
+    Value(Expr),
+    /// The list continues until the end of the stream
+    Eof,
+    /// Every item is preceded by a set bit, the list is terminated by an unset bit
+    ContinuationBit,
+    /// More than one terminator is set, contains the compile error to emit
+    Conflict(TokenStream),
+}
+
+impl Terminator {
+    fn get(attrs: &[Attribute]) -> Option<Self> {
+        let terminators = attrs
+            .iter()
+            .filter(|attr| {
+                attr.path.is_ident("until")
+                    || attr.path.is_ident("until_eof")
+                    || attr.path.is_ident("continuation_bit")
+            })
+            .collect::<Vec<_>>();
+        if let Some(attr) = terminators.get(1) {
+            return Some(Terminator::Conflict(quote_spanned! { attr.span() =>
+                compile_error!("only one of #[until], #[until_eof] and #[continuation_bit] can be set for a field")
+            }));
+        }
+        if let Some(terminator_lit) = get_attribute_value::<Lit>(attrs, &["until"]) {
+            return Some(match terminator_lit {
+                Lit::Str(terminator) => Terminator::Value(
+                    parse_str::<Expr>(&terminator.value())
+                        .expect("invalid expression for until attribute"),
+                ),
+                _ => panic!("until attribute is required to be a string containing an expression"),
+            });
+        }
+        attrs.iter().find_map(|attr| {
+            if attr.path.is_ident("until_eof") {
+                Some(Terminator::Eof)
+            } else if attr.path.is_ident("continuation_bit") {
+                Some(Terminator::ContinuationBit)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Get the custom function used to read or write a field and the extra arguments to pass to the function
+///
+/// The function is either set directly using `attr` (`bitread_with` or `bitwrite_with`),
+/// or as `module_fn` in the module set using `bit_with`.
+fn get_custom_fn(attrs: &[Attribute], attr: &str, module_fn: &str) -> Option<(Expr, Vec<Expr>)> {
+    let parse_lit = |lit: Lit, attr: &str| match lit {
+        Lit::Str(lit) => lit.value(),
+        _ => panic!("{} attribute is required to be a string", attr),
+    };
+    let expr = match get_attribute_value::<Lit>(attrs, &[attr]) {
+        Some(lit) => parse_str::<Expr>(&parse_lit(lit, attr))
+            .unwrap_or_else(|_| panic!("invalid function for {} attribute", attr)),
+        None => {
+            let module = parse_lit(get_attribute_value(attrs, &["bit_with"])?, "bit_with");
+            parse_str::<Expr>(&format!("{}::{}", module, module_fn))
+                .expect("invalid module for bit_with attribute")
+        }
+    };
+    match expr {
+        Expr::Call(call) => Some((*call.func, call.args.into_iter().collect())),
+        Expr::Path(_) => Some((expr, Vec::new())),
+        _ => panic!(
+            "{} attribute is required to be a function path or a function call",
+            attr
+        ),
+    }
+}
+
+/// Get the assertion that has to hold for the value read for a field
+fn get_assert(attrs: &[Attribute]) -> Option<Expr> {
+    get_attribute_value::<Lit>(attrs, &["assert"]).map(|assertion_lit| match assertion_lit {
+        Lit::Str(assertion) => {
+            parse_str::<Expr>(&assertion.value()).expect("invalid expression for assert attribute")
+        }
+        _ => panic!("assert attribute is required to be a string containing an expression"),
+    })
+}
+
+/// Get `T` for a type `Wrapper<T>`, where `wrapper` is the name of the generic type
+fn inner_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(GenericArgument::Type(inner)) if args.args.len() == 1 => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn get_align(attrs: &[Attribute]) -> Option<usize> {
+    get_attribute_value::<Lit>(attrs, &["align"]).map(|align_lit| match align_lit {
+        Lit::Int(align) => align
+            .base10_parse()
+            .expect("align attribute is required to be an integer literal"),
+        _ => panic!("align attribute is required to be an integer literal"),
+    })
+}
+
+/// Statement that aligns the stream if the `align` attribute is set
+fn align(attrs: &[Attribute], stream: TokenStream, span: Span) -> TokenStream {
+    match get_align(attrs) {
+        Some(align) => quote_spanned! { span =>
+            #stream.align(#align)?;
+        },
+        None => quote! {},
+    }
+}
+
+/// Get the endianness set for a field or variant using the `le` or `be` attributes
+fn get_endianness_override(attrs: &[Attribute]) -> Option<TokenStream> {
+    let le = attrs.iter().any(|attr| attr.path.is_ident("le"));
+    let be = attrs.iter().any(|attr| attr.path.is_ident("be"));
+    match (le, be) {
+        (true, true) => Some(quote! {
+            compile_error!("#[le] and #[be] can't be used together")
+        }),
+        (true, false) => Some(quote!(::bitbuffer::LittleEndian)),
+        (false, true) => Some(quote!(::bitbuffer::BigEndian)),
+        (false, false) => None,
+    }
+}
+
+/// Wrap the statements reading a value in a read with the endianness set by the `le` or `be` attributes
+fn read_with_endianness(attrs: &[Attribute], read: TokenStream, span: Span) -> TokenStream {
+    match get_endianness_override(attrs) {
+        Some(endianness) => {
+            let check = check_endianness_aligned(attrs, quote!(stream), quote!(stream.pos()), span);
+            quote_spanned! { span =>
+                stream.with_endianness(#endianness, |stream| -> ::bitbuffer::Result<_> {
+                    #check
+                    #read
+                })
+            }
+        }
+        None => read,
+    }
+}
+
+/// Statement that checks that a value with the endianness set by the `le` or `be` attributes starts at a byte boundary
+///
+/// The endianness also determines the order of the bits within a byte, so switching it halfway through a byte
+/// mixes up the bits. Values with an `align` attribute are aligned after the check in the generated write code,
+/// so they aren't checked.
+fn check_endianness_aligned(
+    attrs: &[Attribute],
+    stream: TokenStream,
+    pos: TokenStream,
+    span: Span,
+) -> TokenStream {
+    if get_align(attrs).is_some() {
+        return quote! {};
+    }
+    quote_spanned! { span =>
+        if !#stream.is_aligned() {
+            return Err(::bitbuffer::BitError::UnalignedEndianness { pos: #pos });
+        }
+    }
+}
+
 fn repr_for_bits(discriminant_bits: u64) -> TokenStream {
     if discriminant_bits <= 8 {
         quote!(u8)
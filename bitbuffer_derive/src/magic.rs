@@ -0,0 +1,122 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote_spanned;
+use syn::{Attribute, Lit, LitByteStr, LitInt};
+use syn_util::get_attribute_value;
+
+/// A constant value that is read and checked before a type or field
+pub enum Magic {
+    Bytes(LitByteStr),
+    Int { value: u128, bits: usize },
+}
+
+impl From<Lit> for Magic {
+    fn from(lit: Lit) -> Self {
+        match lit {
+            Lit::ByteStr(bytes) => Magic::Bytes(bytes),
+            Lit::Str(string) => {
+                Magic::Bytes(LitByteStr::new(string.value().as_bytes(), string.span()))
+            }
+            Lit::Int(int) => {
+                let value = int
+                    .base10_parse::<u128>()
+                    .expect("magic attribute is required to be a positive integer");
+                let bits = int_magic_bits(&int);
+                if bits < 128 && value >> bits != 0 {
+                    panic!("magic value {} doesn't fit in {} bits", int, bits);
+                }
+                Magic::Int { value, bits }
+            }
+            _ => panic!("magic attribute is required to be a byte string or integer literal"),
+        }
+    }
+}
+
+/// Get the number of bits for an integer magic value
+///
+/// The size is the number of digits of a hexadecimal, octal or binary literal,
+/// so leading zeroes are included in the magic value.
+fn int_magic_bits(int: &LitInt) -> usize {
+    let literal = int.to_string().replace('_', "");
+    let bits_per_digit = match literal.get(0..2) {
+        Some("0x") => 4,
+        Some("0o") => 3,
+        Some("0b") => 1,
+        _ => panic!(
+            "the size of decimal magic value {} is ambiguous, use a hexadecimal or binary literal",
+            int
+        ),
+    };
+    (literal.len() - 2) * bits_per_digit
+}
+
+impl Magic {
+    pub fn get(attrs: &[Attribute]) -> Option<Self> {
+        get_attribute_value::<Lit>(attrs, &["magic"]).map(Magic::from)
+    }
+
+    pub fn bits(&self) -> usize {
+        match self {
+            Magic::Bytes(bytes) => bytes.value().len() * 8,
+            Magic::Int { bits, .. } => *bits,
+        }
+    }
+
+    /// Statement that reads the magic value from `stream` and checks it
+    pub fn read(&self, stream: TokenStream, span: Span) -> TokenStream {
+        match self {
+            Magic::Bytes(bytes) => quote_spanned! { span =>
+                #stream.read_magic_bytes(#bytes)?;
+            },
+            Magic::Int { value, bits } => {
+                let value = LitInt::new(&format!("{}u128", value), span);
+                quote_spanned! { span =>
+                    #stream.read_magic_int(#value, #bits)?;
+                }
+            }
+        }
+    }
+
+    /// Statement that writes the magic value to `stream`
+    pub fn write(&self, stream: TokenStream, span: Span) -> TokenStream {
+        match self {
+            Magic::Bytes(bytes) => quote_spanned! { span =>
+                #stream.write_bytes(#bytes)?;
+            },
+            Magic::Int { value, bits } => {
+                let value = LitInt::new(&format!("{}u128", value), span);
+                quote_spanned! { span =>
+                    #stream.write_int(#value, #bits)?;
+                }
+            }
+        }
+    }
+}
+
+/// Statement that reads and checks the magic value if the `magic` attribute is set
+pub fn read_magic(attrs: &[Attribute], stream: TokenStream, span: Span) -> TokenStream {
+    match Magic::get(attrs) {
+        Some(magic) => magic.read(stream, span),
+        None => TokenStream::new(),
+    }
+}
+
+/// Statement that writes the magic value if the `magic` attribute is set
+pub fn write_magic(attrs: &[Attribute], stream: TokenStream, span: Span) -> TokenStream {
+    match Magic::get(attrs) {
+        Some(magic) => magic.write(stream, span),
+        None => TokenStream::new(),
+    }
+}
+
+/// Add the size of the magic value, if any, to an `Option<usize>` size expression
+pub fn add_magic_size(attrs: &[Attribute], size: TokenStream, span: Span) -> TokenStream {
+    match Magic::get(attrs) {
+        Some(magic) => {
+            let bits = magic.bits();
+            quote_spanned! { span =>
+                (#size).map(|size: usize| size + #bits)
+            }
+        }
+        None => size,
+    }
+}
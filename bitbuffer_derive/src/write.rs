@@ -1,11 +1,16 @@
 use crate::discriminant::Discriminant;
-use crate::{repr_for_bits, size};
+use crate::magic::{write_magic, Magic};
+use crate::{
+    align, check_endianness_aligned, get_align, get_custom_fn, get_endianness_override,
+    get_read_if, inner_type, is_discriminant_field, is_field_by_field, repr_for_bits, size,
+    Terminator,
+};
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, quote_spanned};
 use syn::spanned::Spanned;
 use syn::{
     parse_macro_input, parse_quote, parse_str, Attribute, Data, DataStruct, DeriveInput, Expr,
-    Fields, GenericParam, Ident, Index, Lit, LitInt, Member, Path, Type,
+    Field, Fields, GenericParam, Ident, Index, Lit, LitInt, LitStr, Member, Path, Type,
 };
 use syn_util::get_attribute_value;
 
@@ -49,6 +54,8 @@ pub fn derive_bitwrite_trait(
         extra_param.is_some(),
     );
     let parsed = write(input.data.clone(), name, &input.attrs);
+    let align = align(&input.attrs, quote!(__target__stream), span);
+    let magic = write_magic(&input.attrs, quote!(__target__stream), span);
     let _parsed_unchecked = write(input.data.clone(), name, &input.attrs);
 
     let endianness_placeholder = endianness.unwrap_or_else(|| "_E".to_owned());
@@ -71,11 +78,34 @@ pub fn derive_bitwrite_trait(
 
     let write_method = Ident::new(&write_method_name, span);
 
+    // without a generated implementation the size is measured by the default implementation
+    let bit_size_method =
+        bit_size(input.data.clone(), name, &input.attrs, &endianness_ident).map(|body| {
+            if extra_param.is_some() {
+                quote! {
+                    #[allow(unused_variables)]
+                    fn written_bit_size_sized(&self, input_size: usize) -> usize {
+                        #body
+                    }
+                }
+            } else {
+                quote! {
+                    fn written_bit_size(&self) -> usize {
+                        #body
+                    }
+                }
+            }
+        });
+
     let expanded = quote! {
         impl #impl_generics #trait_def for #name #ty_generics #where_clause {
             fn #write_method(&self, __target__stream: &mut ::bitbuffer::BitWriteStream<#endianness_ident>#extra_param) -> ::bitbuffer::Result<()> {
+                #align
+                #magic
                 #parsed
             }
+
+            #bit_size_method
         }
     };
 
@@ -89,58 +119,12 @@ fn write(data: Data, struct_name: &Ident, attrs: &[Attribute]) -> TokenStream {
 
     match data {
         Data::Struct(DataStruct { fields, .. }) => {
-            let expand = fields.iter().enumerate().map(|(i, field)| {
-                let name = field
-                    .ident
-                    .clone()
-                    .unwrap_or_else(|| Ident::new(&format!("__{}", i), span));
-                let member = field.ident.clone().map(Member::Named).unwrap_or_else(|| {
-                    Member::Unnamed(Index {
-                        index: i as u32,
-                        span: field.span(),
-                    })
-                });
-                // extract int fields to be used in size expressions
-                if type_is_int(&field.ty) {
-                    quote_spanned! { field.span() =>
-                        #[allow(unused_variables)]
-                        let #name = self.#member;
-                    }
-                } else {
-                    quote! {}
-                }
-            });
-
-            let writes = fields.iter().enumerate().map(|(i, f)| {
-                // Get attributes `#[..]` on each field
-                let size = get_field_size(&f.attrs, f.span());
-                let span = f.span();
-                let member = f.ident.clone().map(Member::Named).unwrap_or_else(|| {
-                    Member::Unnamed(Index {
-                        index: i as u32,
-                        span,
-                    })
-                });
-                match size {
-                    Some(size) => {
-                        quote_spanned! { span =>
-                            {
-                                let _size: usize = #size;
-                                __target__stream.write_sized(&self.#member, _size)?;
-                            }
-                        }
-                    }
-                    None => {
-                        quote_spanned! { span => {
-                            __target__stream.write(&self.#member)?;
-                        }}
-                    }
-                }
-            });
+            let (prelude, fields) = struct_fields(&fields, span, false);
+            let writes = write_fields(&fields);
 
             quote_spanned! {span=>
-                #(#expand)*
-                #(#writes)*
+                #prelude
+                #writes
                 Ok(())
             }
         }
@@ -174,6 +158,50 @@ fn write(data: Data, struct_name: &Ident, attrs: &[Attribute]) -> TokenStream {
                 .max()
                 .unwrap_or(0);
 
+            let mut last_discriminant = -1;
+            let repr = repr_for_bits(discriminant_bits);
+            let known_discriminants: Vec<usize> = data
+                .variants
+                .iter()
+                .filter_map(|variant| match Discriminant::from(variant) {
+                    Discriminant::Int(discriminant) => {
+                        last_discriminant = discriminant as isize;
+                        Some(discriminant)
+                    }
+                    Discriminant::Wildcard => None,
+                    Discriminant::Default => {
+                        last_discriminant += 1;
+                        Some(last_discriminant as usize)
+                    }
+                })
+                .collect();
+            let enum_name = LitStr::new(&struct_name.to_string(), span);
+            // the captured discriminant has to fit and can't belong to another variant, or it would be read back differently
+            let check_captured = quote_spanned! { span =>
+                {
+                    let captured = *__discriminant;
+                    #[allow(clippy::useless_conversion)]
+                    let converted = <#repr as ::core::convert::TryFrom<_>>::try_from(captured);
+                    match converted {
+                        Ok(value) if (value as u128) >> #discriminant_bits == 0 => {
+                            if [#(#known_discriminants),*].contains(&(value as usize)) {
+                                return Err(::bitbuffer::BitError::DiscriminantConflict {
+                                    discriminant: value as usize,
+                                    enum_name: #enum_name.to_string(),
+                                });
+                            }
+                            value
+                        }
+                        _ => {
+                            return Err(::bitbuffer::BitError::ValueTooLarge {
+                                value: captured.to_string(),
+                                bits: #discriminant_bits as usize,
+                            })
+                        }
+                    }
+                }
+            };
+
             let mut last_discriminant = -1;
 
             let discriminant_value = data.variants.iter().map(|variant| {
@@ -199,16 +227,41 @@ fn write(data: Data, struct_name: &Ident, attrs: &[Attribute]) -> TokenStream {
                     }
                 };
 
-                match &variant.fields {
-                    Fields::Unit => quote_spanned! {span =>
+                let discriminant_field = variant
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .find(|(_, f)| is_discriminant_field(&f.attrs));
+
+                match (&variant.fields, discriminant_field) {
+                    (Fields::Unit, _) => quote_spanned! {span =>
                         #struct_name::#variant_name => #discriminant_token
                     },
-                    Fields::Unnamed(_f) => {
+                    // write the captured discriminant back
+                    (Fields::Named(_), Some((_, field))) => {
+                        let name = &field.ident;
                         quote_spanned! { span =>
-                            #struct_name::#variant_name(_) => #discriminant_token
+                            #struct_name::#variant_name { #name: __discriminant, .. } => #check_captured
                         }
                     }
-                    _ => unimplemented!(),
+                    (Fields::Unnamed(_), Some((index, _))) => {
+                        let fields = variant.fields.iter().enumerate().map(|(i, _)| {
+                            if i == index {
+                                quote!(__discriminant)
+                            } else {
+                                quote!(_)
+                            }
+                        });
+                        quote_spanned! { span =>
+                            #struct_name::#variant_name(#(#fields),*) => #check_captured
+                        }
+                    }
+                    (Fields::Named(_), None) => quote_spanned! { span =>
+                        #struct_name::#variant_name { .. } => #discriminant_token
+                    },
+                    (Fields::Unnamed(_), None) => quote_spanned! { span =>
+                        #struct_name::#variant_name(..) => #discriminant_token
+                    },
                 }
             });
 
@@ -220,30 +273,50 @@ fn write(data: Data, struct_name: &Ident, attrs: &[Attribute]) -> TokenStream {
                     Fields::Unit => quote_spanned! {span =>
                         #struct_name::#variant_name => {},
                     },
+                    fields if is_field_by_field(fields) => {
+                        let align = align(&variant.attrs, quote!(__target__stream), span);
+                        let (pattern, body) = write_variant_fields(
+                            &variant.fields,
+                            quote!(#struct_name::#variant_name),
+                            span,
+                        );
+                        let body = write_with_endianness(&variant.attrs, body, span);
+                        quote_spanned! { span =>
+                            #pattern => {
+                                #align
+                                #body
+                            }
+                        }
+                    }
                     Fields::Unnamed(f) => {
                         let size = get_field_size(&variant.attrs, f.span());
-                        match size {
+                        let align = align(&variant.attrs, quote!(__target__stream), span);
+                        let write = match size {
                             Some(size) => {
                                 quote_spanned! { span =>
-                                    #struct_name::#variant_name(inner) => {
-                                        let size:usize = #size;
-                                        __target__stream.write_sized(inner, size)?;
-                                    }
+                                    let size:usize = #size;
+                                    __target__stream.write_sized(inner, size)?;
                                 }
                             }
                             None => {
                                 quote_spanned! { span =>
-                                    #struct_name::#variant_name(inner) => { __target__stream.write(inner)?; }
+                                    __target__stream.write(inner)?;
                                 }
                             }
+                        };
+                        let write = write_with_endianness(&variant.attrs, write, span);
+                        quote_spanned! { span =>
+                            #struct_name::#variant_name(inner) => {
+                                #align
+                                #write
+                            }
                         }
                     }
-                    _ => unimplemented!(),
+                    Fields::Named(_) => unreachable!(),
                 }
             });
 
             let span = data.enum_token.span();
-            let repr = repr_for_bits(discriminant_bits);
 
             quote_spanned! {span=>
                 let discriminant:#repr = match &self {
@@ -256,7 +329,627 @@ fn write(data: Data, struct_name: &Ident, attrs: &[Attribute]) -> TokenStream {
                 Ok(())
             }
         }
-        _ => unimplemented!(),
+        Data::Union(_) => quote_spanned! { span =>
+            compile_error!("BitWrite can't be derived for unions")
+        },
+    }
+}
+
+/// Expression for the number of bits written for the type
+///
+/// Returns `None` if the size can't be determined from the fields, in which case the size is measured by the
+/// default implementation of the trait
+fn bit_size(
+    data: Data,
+    struct_name: &Ident,
+    attrs: &[Attribute],
+    endianness: &Ident,
+) -> Option<TokenStream> {
+    let span = struct_name.span();
+
+    // the padding needed for alignment depends on the position in the stream
+    if get_align(attrs).is_some() {
+        return None;
+    }
+    let magic_bits = Magic::get(attrs).map(|magic| magic.bits());
+
+    match data {
+        Data::Struct(DataStruct { fields, .. }) => {
+            let (prelude, fields) = struct_fields(&fields, span, true);
+            let sizes = fields
+                .iter()
+                .map(|(f, _, value)| field_bit_size(f, value.clone(), endianness))
+                .collect::<Option<Vec<_>>>()?;
+            let sizes = sum_bits(
+                magic_bits
+                    .into_iter()
+                    .map(|bits| quote!(#bits))
+                    .chain(sizes),
+            );
+
+            Some(quote_spanned! { span =>
+                #prelude
+                #sizes
+            })
+        }
+        Data::Enum(data) => {
+            let discriminant_bits: u64 = get_attribute_value(attrs, &["discriminant_bits"])?;
+            let discriminant_bits = discriminant_bits as usize;
+            let variant_sizes = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let span = variant.span();
+                    let variant_name = &variant.ident;
+                    if get_align(&variant.attrs).is_some() {
+                        return None;
+                    }
+
+                    Some(match &variant.fields {
+                        Fields::Unit => quote_spanned! { span =>
+                            #struct_name::#variant_name => 0,
+                        },
+                        fields if is_field_by_field(fields) => {
+                            let (pattern, prelude, fields) = variant_fields(
+                                &variant.fields,
+                                quote!(#struct_name::#variant_name),
+                                span,
+                                true,
+                            );
+                            let sizes = fields
+                                .iter()
+                                .map(|(f, _, value)| field_bit_size(f, value.clone(), endianness))
+                                .collect::<Option<Vec<_>>>()?;
+                            let sizes = sum_bits(sizes);
+                            quote_spanned! { span =>
+                                #pattern => {
+                                    #prelude
+                                    #sizes
+                                }
+                            }
+                        }
+                        Fields::Unnamed(f) => match get_field_size(&variant.attrs, f.span()) {
+                            Some(size) => quote_spanned! { span =>
+                                #struct_name::#variant_name(inner) => {
+                                    let size: usize = #size;
+                                    ::bitbuffer::BitWriteSized::<#endianness>::written_bit_size_sized(inner, size)
+                                }
+                            },
+                            None => quote_spanned! { span =>
+                                #struct_name::#variant_name(inner) => ::bitbuffer::BitWrite::<#endianness>::written_bit_size(inner),
+                            },
+                        },
+                        Fields::Named(_) => unreachable!(),
+                    })
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let sizes = sum_bits(
+                std::iter::once(quote!(#discriminant_bits))
+                    .chain(magic_bits.map(|bits| quote!(#bits)))
+                    .chain(std::iter::once(quote_spanned! { span =>
+                        match self {
+                            #(#variant_sizes)*
+                        }
+                    })),
+            );
+
+            Some(sizes)
+        }
+        // unions are rejected when generating the write implementation
+        Data::Union(_) => None,
+    }
+}
+
+/// Expression for the number of bits written for a single field, `value` is an expression for a reference to the field value
+///
+/// Returns `None` if the size depends on the position in the stream or on a custom write function
+fn field_bit_size(f: &Field, value: TokenStream, endianness: &Ident) -> Option<TokenStream> {
+    let span = f.span();
+    if get_align(&f.attrs).is_some()
+        || get_custom_fn(&f.attrs, "bitwrite_with", "write").is_some()
+        || get_attribute_value::<Lit>(&f.attrs, &["byte_length_of"]).is_some()
+    {
+        return None;
+    }
+
+    let size = get_field_size(&f.attrs, span);
+    let magic_bits = Magic::get(&f.attrs).map(|magic| magic.bits());
+    let read_if = get_read_if(&f.attrs);
+    let ty = match read_if {
+        Some(_) => inner_type(&f.ty, "Option")?,
+        None => &f.ty,
+    };
+    let bit_size = |value: TokenStream| {
+        let size = match (&size, Terminator::get(&f.attrs)) {
+            (_, Some(Terminator::Value(terminator))) => {
+                let item = inner_type(ty, "Vec")?;
+                quote_spanned! { span =>
+                    (#value).iter().map(|__item| <#item as ::bitbuffer::BitWrite<#endianness>>::written_bit_size(__item)).sum::<usize>()
+                        + <#item as ::bitbuffer::BitWrite<#endianness>>::written_bit_size(&(#terminator))
+                }
+            }
+            (_, Some(Terminator::Conflict(_))) => return None,
+            (_, Some(Terminator::ContinuationBit)) => quote_spanned! { span =>
+                (#value).iter().map(|__item| 1 + ::bitbuffer::BitWrite::<#endianness>::written_bit_size(__item)).sum::<usize>() + 1
+            },
+            (Some(size), _) => quote_spanned! { span =>
+                {
+                    let _size: usize = #size;
+                    ::bitbuffer::BitWriteSized::<#endianness>::written_bit_size_sized(#value, _size)
+                }
+            },
+            (None, _) => quote_spanned! { span =>
+                ::bitbuffer::BitWrite::<#endianness>::written_bit_size(#value)
+            },
+        };
+        Some(sum_bits(
+            magic_bits
+                .map(|bits| quote!(#bits))
+                .into_iter()
+                .chain(Some(size)),
+        ))
+    };
+
+    match read_if {
+        // the field is only written if the condition holds
+        Some(_) => {
+            let size = bit_size(quote!(__value))?;
+            Some(quote_spanned! { span =>
+                match #value {
+                    Some(__value) => #size,
+                    None => 0,
+                }
+            })
+        }
+        None => bit_size(value),
+    }
+}
+
+/// Sum a list of bit size expressions
+fn sum_bits(sizes: impl IntoIterator<Item = TokenStream>) -> TokenStream {
+    let sizes: Vec<TokenStream> = sizes.into_iter().collect();
+    match sizes.as_slice() {
+        [] => quote!(0),
+        [size] => size.clone(),
+        sizes => quote!(#((#sizes))+*),
+    }
+}
+
+/// Create a pattern binding all fields of an enum variant and the code for writing the bound fields
+///
+/// Fields marked with `#[discriminant]` are skipped, since they are written as the discriminant
+fn write_variant_fields(
+    fields: &Fields,
+    constructor: TokenStream,
+    span: Span,
+) -> (TokenStream, TokenStream) {
+    let (pattern, prelude, writes) = variant_fields(fields, constructor, span, false);
+    let writes = write_fields(&writes);
+
+    let body = quote_spanned! { span =>
+        #prelude
+        #writes
+    };
+    (pattern, body)
+}
+
+/// Extract the fields of a struct for use in expressions and get the value written for every field
+///
+/// See [`compute_fields`] for `size_only`
+fn struct_fields(
+    fields: &Fields,
+    span: Span,
+    size_only: bool,
+) -> (TokenStream, Vec<(&Field, usize, TokenStream)>) {
+    let expand = fields.iter().enumerate().map(|(i, field)| {
+        let name = field
+            .ident
+            .clone()
+            .unwrap_or_else(|| Ident::new(&format!("__{}", i), span));
+        let member = field.ident.clone().map(Member::Named).unwrap_or_else(|| {
+            Member::Unnamed(Index {
+                index: i as u32,
+                span: field.span(),
+            })
+        });
+        // extract fields to be used in size, condition and computed expressions
+        if type_is_primitive(&field.ty) {
+            quote_spanned! { field.span() =>
+                #[allow(unused_variables)]
+                let #name = self.#member;
+            }
+        } else {
+            quote_spanned! { field.span() =>
+                #[allow(unused_variables)]
+                let #name = &self.#member;
+            }
+        }
+    });
+
+    let fields: Vec<(&Field, usize, TokenStream)> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let member = f.ident.clone().map(Member::Named).unwrap_or_else(|| {
+                Member::Unnamed(Index {
+                    index: i as u32,
+                    span: f.span(),
+                })
+            });
+            (f, i, quote!(&self.#member))
+        })
+        .collect();
+    let (computed, fields) = compute_fields(
+        fields,
+        |i, f| {
+            f.ident
+                .clone()
+                .unwrap_or_else(|| Ident::new(&format!("__{}", i), span))
+        },
+        size_only,
+    );
+
+    let prelude = quote_spanned! { span =>
+        #(#expand)*
+        #computed
+    };
+    (prelude, fields)
+}
+
+/// Create a pattern binding all fields of an enum variant, extract the bound fields for use in expressions
+/// and get the value written for every field
+///
+/// Fields marked with `#[discriminant]` are skipped, since they are written as the discriminant
+///
+/// See [`compute_fields`] for `size_only`
+fn variant_fields(
+    fields: &Fields,
+    constructor: TokenStream,
+    span: Span,
+    size_only: bool,
+) -> (TokenStream, TokenStream, Vec<(&Field, usize, TokenStream)>) {
+    let bindings: Vec<Ident> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(name) => Ident::new(&format!("__field_{}", name), name.span()),
+            None => Ident::new(&format!("__field_{}", i), field.span()),
+        })
+        .collect();
+
+    let pattern = match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| &f.ident);
+            quote_spanned! { span =>
+                #constructor { #(#names: #bindings),* }
+            }
+        }
+        _ => quote_spanned! { span =>
+            #constructor(#(#bindings),*)
+        },
+    };
+
+    // extract fields to be used in size, condition and computed expressions
+    let expand = fields
+        .iter()
+        .zip(bindings.iter())
+        .map(|(field, binding)| match &field.ident {
+            Some(name) if type_is_primitive(&field.ty) => quote_spanned! { field.span() =>
+                #[allow(unused_variables)]
+                let #name = *#binding;
+            },
+            Some(name) => quote_spanned! { field.span() =>
+                #[allow(unused_variables)]
+                let #name = #binding;
+            },
+            None => quote! {},
+        });
+
+    let writes: Vec<(&Field, usize, TokenStream)> = fields
+        .iter()
+        .zip(bindings.iter())
+        .enumerate()
+        .filter(|(_, (field, _))| !is_discriminant_field(&field.attrs))
+        .map(|(i, (f, binding))| (f, i, quote!(#binding)))
+        .collect();
+    let (computed, writes) = compute_fields(
+        writes,
+        |i, f| f.ident.clone().unwrap_or_else(|| bindings[i].clone()),
+        size_only,
+    );
+
+    let prelude = quote_spanned! { span =>
+        #(#expand)*
+        #computed
+    };
+    (pattern, prelude, writes)
+}
+
+/// Compute the values for fields marked with `computed`, replacing the values written for those fields
+///
+/// The computed values are bound to the name given by `local`, overwriting the extracted field values
+/// so they are also used in size and condition expressions of later fields.
+///
+/// Writing fails with `ValueTooLarge` if a computed value doesn't fit in the type of the field,
+/// when `size_only` is set the size of such a value is reported as `0` instead.
+fn compute_fields(
+    fields: Vec<(&Field, usize, TokenStream)>,
+    local: impl Fn(usize, &Field) -> Ident,
+    size_only: bool,
+) -> (TokenStream, Vec<(&Field, usize, TokenStream)>) {
+    let mut computed = TokenStream::new();
+    let fields = fields
+        .into_iter()
+        .map(|(f, i, value)| match get_computed(&f.attrs) {
+            Some(expr) => {
+                let local = local(i, f);
+                let ty = &f.ty;
+                let overflow = if size_only {
+                    quote_spanned! { f.span() => return 0 }
+                } else {
+                    quote_spanned! { f.span() =>
+                        return Err(::bitbuffer::BitError::ValueTooLarge {
+                            value: computed.to_string(),
+                            bits: ::core::mem::size_of::<#ty>() * 8,
+                        })
+                    }
+                };
+                computed.extend(quote_spanned! { f.span() =>
+                    let #local: #ty = {
+                        let computed = #expr;
+                        #[allow(clippy::useless_conversion)]
+                        let converted = <#ty as ::core::convert::TryFrom<_>>::try_from(computed);
+                        match converted {
+                            Ok(value) => value,
+                            Err(_) => #overflow,
+                        }
+                    };
+                });
+                (f, i, quote!(&#local))
+            }
+            None => (f, i, value),
+        })
+        .collect();
+    (computed, fields)
+}
+
+/// Write a list of fields, `value` is an expression for a reference to the field value
+///
+/// Fields marked with `bit_length_of` or `byte_length_of` are written after the section they contain the length of.
+fn write_fields(fields: &[(&Field, usize, TokenStream)]) -> TokenStream {
+    let mut writes = TokenStream::new();
+    let mut rest = fields;
+    while let Some(((f, index, value), tail)) = rest.split_first() {
+        let span = f.span();
+        let (section_end, reserve) = match get_length_of(&f.attrs) {
+            Some((target, reserve)) => (
+                tail.iter()
+                    .position(|(field, i, _)| field_name(field, *i) == target),
+                reserve,
+            ),
+            None => {
+                writes.extend(write_field(f, *index, value.clone()));
+                rest = tail;
+                continue;
+            }
+        };
+        let section_end = match section_end {
+            Some(section_end) => section_end,
+            None => {
+                writes.extend(quote_spanned! { span =>
+                    compile_error!("the field containing the length of a section has to be placed before the section");
+                });
+                break;
+            }
+        };
+        let (section, tail) = tail.split_at(section_end + 1);
+        let section = write_fields(section);
+        let align = align(&f.attrs, quote!(__target__stream), span);
+        let magic = write_magic(&f.attrs, quote!(__target__stream), span);
+        let size = match length_bits(f) {
+            Ok(size) => size,
+            Err(error) => {
+                writes.extend(error);
+                break;
+            }
+        };
+        writes.extend(quote_spanned! { span =>
+            #align
+            #magic
+            __target__stream.#reserve(#size, |#[allow(non_snake_case)] __target__stream| -> ::bitbuffer::Result<()> {
+                #section
+                Ok(())
+            })?;
+        });
+        rest = tail;
+    }
+    writes
+}
+
+/// Get the number of bits used for a length field, either set with `size` or the width of the integer type
+///
+/// Lengths are filled in after the section is written, which is limited to less than 56 bits
+fn length_bits(f: &Field) -> Result<TokenStream, TokenStream> {
+    let span = f.span();
+    let too_large = quote_spanned! { span =>
+        compile_error!("the field containing the length of a section can be at most 55 bits");
+    };
+    match get_attribute_value::<Lit>(&f.attrs, &["size"]) {
+        Some(Lit::Int(size)) if size.base10_parse::<usize>().map_or(true, |size| size >= 56) => {
+            return Err(too_large)
+        }
+        Some(_) => return Ok(get_field_size(&f.attrs, span).unwrap()),
+        None => {}
+    }
+    let bits = match &f.ty {
+        Type::Path(path) => {
+            path.path
+                .get_ident()
+                .and_then(|ident| match ident.to_string().as_str() {
+                    "u8" | "i8" => Some(8usize),
+                    "u16" | "i16" => Some(16),
+                    "u32" | "i32" => Some(32),
+                    _ => None,
+                })
+        }
+        _ => None,
+    };
+    match bits {
+        Some(bits) => Ok(quote_spanned! { span => #bits }),
+        None => Err(quote_spanned! { span =>
+            compile_error!("the field containing the length of a section needs a #[size] of at most 55 bits unless it's a u8, u16 or u32");
+        }),
+    }
+}
+
+fn field_name(f: &Field, index: usize) -> String {
+    f.ident
+        .as_ref()
+        .map(|ident| ident.to_string())
+        .unwrap_or_else(|| index.to_string())
+}
+
+/// Get the expression for the value written for a computed field
+fn get_computed(attrs: &[Attribute]) -> Option<Expr> {
+    get_attribute_value::<Lit>(attrs, &["computed"]).map(|computed_lit| match computed_lit {
+        Lit::Str(computed) => {
+            parse_str::<Expr>(&computed.value()).expect("invalid expression for computed attribute")
+        }
+        _ => panic!("computed attribute is required to be a string containing an expression"),
+    })
+}
+
+/// Get the name of the field ending the section a length field contains the length of,
+/// and the method used to reserve the length
+fn get_length_of(attrs: &[Attribute]) -> Option<(String, Ident)> {
+    let parse = |attr: &str, reserve: &str| {
+        get_attribute_value::<Lit>(attrs, &[attr]).map(|lit| match lit {
+            Lit::Str(target) => (target.value(), Ident::new(reserve, Span::call_site())),
+            _ => panic!(
+                "{} attribute is required to be a string containing a field name",
+                attr
+            ),
+        })
+    };
+    parse("bit_length_of", "reserve_length")
+        .or_else(|| parse("byte_length_of", "reserve_byte_length"))
+}
+
+/// Write a single field, `value` is an expression for a reference to the field value
+fn write_field(f: &Field, index: usize, value: TokenStream) -> TokenStream {
+    // Get attributes `#[..]` on each field
+    let size = get_field_size(&f.attrs, f.span());
+    let span = f.span();
+    let align = align(&f.attrs, quote!(__target__stream), span);
+    let magic = write_magic(&f.attrs, quote!(__target__stream), span);
+    let custom_write = get_custom_fn(&f.attrs, "bitwrite_with", "write");
+    let terminator = Terminator::get(&f.attrs);
+    if let Some(Terminator::Conflict(error)) = terminator {
+        return error;
+    }
+    let write = |value: TokenStream| match (&size, &custom_write, &terminator) {
+        (_, _, Some(Terminator::Value(terminator))) => {
+            quote_spanned! { span =>
+                {
+                    #align
+                    #magic
+                    __target__stream.write_until(#value, &(#terminator))?;
+                }
+            }
+        }
+        (_, _, Some(Terminator::ContinuationBit)) => {
+            quote_spanned! { span =>
+                {
+                    #align
+                    #magic
+                    __target__stream.write_continued(#value)?;
+                }
+            }
+        }
+        (Some(size), Some((write_fn, args)), _) => {
+            quote_spanned! { span =>
+                {
+                    #align
+                    #magic
+                    let _size: usize = #size;
+                    #write_fn(#value, __target__stream, _size #(, #args)*)?;
+                }
+            }
+        }
+        (None, Some((write_fn, args)), _) => {
+            quote_spanned! { span =>
+                {
+                    #align
+                    #magic
+                    #write_fn(#value, __target__stream #(, #args)*)?;
+                }
+            }
+        }
+        (Some(size), None, _) => {
+            quote_spanned! { span =>
+                {
+                    #align
+                    #magic
+                    let _size: usize = #size;
+                    __target__stream.write_sized(#value, _size)?;
+                }
+            }
+        }
+        (None, None, _) => {
+            quote_spanned! { span => {
+                #align
+                #magic
+                __target__stream.write(#value)?;
+            }}
+        }
+    };
+
+    let write = match get_read_if(&f.attrs) {
+        // the field is only written if the condition holds, the state of the option has to match the condition
+        Some(condition) => {
+            let field_name = field_name(f, index);
+            let condition_string = quote!(#condition).to_string();
+            let write = write(quote!(__value));
+            quote_spanned! { span =>
+                match (#value, #condition) {
+                    (Some(__value), true) => #write,
+                    (None, false) => {}
+                    _ => {
+                        return Err(::bitbuffer::BitError::OptionalFieldMismatch {
+                            field: #field_name.to_string(),
+                            condition: #condition_string.to_string(),
+                        })
+                    }
+                }
+            }
+        }
+        None => write(value),
+    };
+    write_with_endianness(&f.attrs, write, span)
+}
+
+/// Wrap the statements writing a value in a write with the endianness set by the `le` or `be` attributes
+fn write_with_endianness(attrs: &[Attribute], write: TokenStream, span: Span) -> TokenStream {
+    match get_endianness_override(attrs) {
+        Some(endianness) => {
+            let check = check_endianness_aligned(
+                attrs,
+                quote!(__target__stream),
+                quote!(__target__stream.bit_len()),
+                span,
+            );
+            quote_spanned! { span =>
+                __target__stream.with_endianness(
+                    #endianness,
+                    |#[allow(non_snake_case)] __target__stream| -> ::bitbuffer::Result<()> {
+                        #check
+                        #write
+                        Ok(())
+                    },
+                )?;
+            }
+        }
+        None => write,
     }
 }
 
@@ -285,13 +978,22 @@ fn get_field_size(attrs: &[Attribute], span: Span) -> Option<TokenStream> {
         })
 }
 
-fn type_is_int(ty: &Type) -> bool {
+fn type_is_primitive(ty: &Type) -> bool {
     if let Type::Path(path) = ty {
         if let Some(ident) = path.path.get_ident() {
             let name = ident.to_string();
             matches!(
                 name.as_str(),
-                "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize"
+                "u8" | "u16"
+                    | "u32"
+                    | "u64"
+                    | "usize"
+                    | "i8"
+                    | "i16"
+                    | "i32"
+                    | "i64"
+                    | "isize"
+                    | "bool"
             )
         } else {
             false
@@ -2,12 +2,13 @@
 #![allow(unreachable_patterns)]
 
 use bitbuffer::{
-    bit_size_of, bit_size_of_sized, BigEndian, BitReadBuffer, BitReadStream, Endianness,
-    LittleEndian,
+    bit_size_of, bit_size_of_sized, BigEndian, BitError, BitReadBuffer, BitReadStream,
+    BitWriteStream, Endianness, LittleEndian, PathSegment,
 };
 use bitbuffer_derive::{BitRead, BitReadSized};
 
 #[derive(BitRead, PartialEq, Debug)]
+#[bit_size]
 struct TestStruct {
     foo: u8,
     str: String,
@@ -65,6 +66,7 @@ fn test_read_struct() {
 
 #[derive(BitRead, PartialEq, Debug)]
 #[discriminant_bits = 2]
+#[bit_size]
 enum TestBareEnum {
     Foo,
     Bar,
@@ -94,6 +96,7 @@ fn test_read_bare_enum() {
 
 #[derive(BitRead, PartialEq, Debug)]
 #[discriminant_bits = 2]
+#[bit_size]
 enum TestUnnamedFieldEnum {
     #[size = 5]
     Foo(i8),
@@ -303,6 +306,7 @@ fn test_empty_struct() {
 }
 
 #[derive(BitRead)]
+#[bit_size]
 struct SizeStruct {
     foo: u8,
     #[size = 6]
@@ -311,12 +315,56 @@ struct SizeStruct {
 }
 
 #[derive(BitRead)]
+#[bit_size]
 struct UnnamedSizeStruct(u8, #[size = 6] String, bool);
 
+#[derive(BitRead)]
+#[endianness = "LittleEndian"]
+#[assert_bit_size = 84]
+struct ConstSizeStruct<'a> {
+    size: SizeStruct,
+    #[size = 2]
+    flags: [u8; 3],
+    #[magic = 0xFF]
+    pair: (bool, i8),
+    #[size = 4]
+    stream: BitReadStream<'a, LittleEndian>,
+}
+
+struct NoBitSize;
+
+impl<E: Endianness> bitbuffer::BitRead<'_, E> for NoBitSize {
+    fn read(_stream: &mut BitReadStream<E>) -> bitbuffer::Result<Self> {
+        Ok(NoBitSize)
+    }
+}
+
+// types without the `bit_size` attribute don't require `BitSize` for their fields
+#[derive(BitRead)]
+struct NoBitSizeStruct {
+    value: u8,
+    none: NoBitSize,
+}
+
 #[test]
 fn test_bit_size() {
+    use bitbuffer::BitSize;
+
     assert_eq!(bit_size_of::<SizeStruct>(), Some(8 + 8 * 6 + 1));
     assert_eq!(bit_size_of::<UnnamedSizeStruct>(), Some(8 + 8 * 6 + 1));
+
+    const SIZE: Option<usize> = SizeStruct::BIT_SIZE;
+    assert_eq!(SIZE, Some(8 + 8 * 6 + 1));
+    assert_eq!(UnnamedSizeStruct::BIT_SIZE, Some(8 + 8 * 6 + 1));
+    assert_eq!(ConstSizeStruct::BIT_SIZE, bit_size_of::<ConstSizeStruct>());
+    assert_eq!(TestBareEnum::BIT_SIZE, Some(2));
+    assert_eq!(TestStruct::BIT_SIZE, None);
+    assert_eq!(AlignStruct::BIT_SIZE, None);
+    assert_eq!(TestUnnamedFieldEnum::BIT_SIZE, None);
+    assert_eq!(MagicReadStruct::BIT_SIZE, Some(76));
+
+    let buffer = [0u8; ConstSizeStruct::BIT_SIZE.unwrap().div_ceil(8)];
+    assert_eq!(11, buffer.len());
 }
 
 #[derive(BitReadSized)]
@@ -335,3 +383,546 @@ fn test_bit_size_sized() {
         Some(8 + 8 * 16 + 1)
     );
 }
+
+#[derive(BitRead, PartialEq, Debug)]
+#[align = 8]
+#[bit_size]
+struct AlignStruct {
+    #[size = 3]
+    foo: u8,
+    #[align = 8]
+    bar: u8,
+    baz: bool,
+    #[align = 16]
+    #[size = 4]
+    qux: u8,
+}
+
+#[test]
+fn test_read_align() {
+    let bytes = vec![
+        0b1110_0000,
+        0b1010_1010,
+        0b1000_0000,
+        0b0000_0000,
+        0b0101_0000,
+        0b1111_0000,
+        0b0101_0101,
+        0b1000_0000,
+        0b0011_0000,
+    ];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    assert_eq!(
+        AlignStruct {
+            foo: 0b111,
+            bar: 0b1010_1010,
+            baz: true,
+            qux: 0b0101,
+        },
+        stream.read().unwrap()
+    );
+    assert_eq!(36, stream.pos());
+    stream.skip_bits(1).unwrap();
+    assert_eq!(
+        AlignStruct {
+            foo: 0b111,
+            bar: 0b0101_0101,
+            baz: true,
+            qux: 0b0011,
+        },
+        stream.read().unwrap()
+    );
+    assert_eq!(68, stream.pos());
+    assert_eq!(None, bit_size_of::<AlignStruct>());
+}
+
+#[derive(BitRead, Debug)]
+struct ContextVector {
+    x: u16,
+    y: u16,
+}
+
+#[derive(BitRead, Debug)]
+struct ContextEntity(u8, ContextVector);
+
+#[derive(BitRead, Debug)]
+#[discriminant_bits = 1]
+enum ContextMessage {
+    Empty,
+    Entity(ContextEntity),
+}
+
+#[derive(BitRead, Debug)]
+struct ContextPacket {
+    count: u8,
+    #[size = "count"]
+    messages: Vec<ContextMessage>,
+}
+
+#[test]
+fn test_read_error_context() {
+    let mut bytes = vec![0xFF; 15];
+    bytes[0] = 3;
+    let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    let mut stream = BitReadStream::from(buffer);
+    let err = stream.read::<ContextPacket>().unwrap_err();
+    match &err {
+        BitError::Context { path, pos, .. } => {
+            assert_eq!(115, *pos);
+            assert_eq!("ContextPacket.messages[2]::Entity.1.y", path.to_string());
+            assert_eq!(
+                &PathSegment::Field {
+                    type_name: "ContextPacket",
+                    field: "messages"
+                },
+                &path.segments()[0]
+            );
+        }
+        _ => panic!("expected error context, got {:?}", err),
+    }
+    assert!(matches!(
+        err.root(),
+        BitError::NotEnoughData {
+            requested: 16,
+            bits_left: 5
+        }
+    ));
+    assert!(err
+        .to_string()
+        .starts_with("ContextPacket.messages[2]::Entity.1.y at bit 115: Not enough data"));
+}
+
+#[derive(BitRead, Debug)]
+struct ContextChecked {
+    #[assert = "value < 10"]
+    value: u8,
+}
+
+#[derive(BitRead, Debug)]
+struct ContextFixedSize {
+    header: u8,
+    items: [ContextChecked; 3],
+}
+
+#[test]
+fn test_read_error_context_fixed_size() {
+    // enough data for the fast path, the context is added when reading again after the error
+    let bytes = vec![0, 1, 2, 30, 0, 0, 0, 0, 0, 0, 0, 0];
+    let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    let mut stream = BitReadStream::from(buffer);
+    assert_eq!(Some(32), bit_size_of::<ContextFixedSize>());
+    let err = stream.read::<ContextFixedSize>().unwrap_err();
+    match &err {
+        BitError::Context { path, pos, .. } => {
+            assert_eq!(24, *pos);
+            assert_eq!("ContextFixedSize.items[2].value", path.to_string());
+        }
+        _ => panic!("expected error context, got {:?}", err),
+    }
+    assert!(matches!(err.root(), BitError::AssertionFailed { .. }));
+}
+
+#[derive(BitRead, PartialEq, Debug)]
+#[discriminant_bits = 4]
+#[endianness = "E"]
+enum CaptureEnum<'a, E: Endianness> {
+    Ping,
+    Value(u8),
+    #[discriminant = "_"]
+    Unknown {
+        #[discriminant]
+        tag: u8,
+        #[size = 8]
+        body: BitReadStream<'a, E>,
+    },
+}
+
+#[derive(BitRead, PartialEq, Debug)]
+#[discriminant_bits = 2]
+enum CaptureUnnamedEnum {
+    Foo,
+    #[discriminant = "_"]
+    Other(#[discriminant] u8),
+}
+
+#[test]
+fn test_read_wildcard_capture() {
+    let bytes = vec![0b1010_1100, 0b0011_0001, 0b0010_0000, 0b0000_0010];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    match stream.read::<CaptureEnum<BigEndian>>().unwrap() {
+        CaptureEnum::Unknown { tag, mut body } => {
+            assert_eq!(0b1010, tag);
+            assert_eq!(8, body.bit_len());
+            assert_eq!(0b1100_0011, body.read_int::<u8>(8).unwrap());
+        }
+        value => panic!("unexpected value {:?}", value),
+    }
+    assert_eq!(
+        CaptureEnum::Value(0b0010_0000),
+        stream.read::<CaptureEnum<BigEndian>>().unwrap()
+    );
+    assert_eq!(CaptureEnum::Ping, stream.read().unwrap());
+    assert_eq!(CaptureUnnamedEnum::Foo, stream.read().unwrap());
+    assert_eq!(CaptureUnnamedEnum::Other(2), stream.read().unwrap());
+    assert_eq!(None, bit_size_of::<CaptureUnnamedEnum>());
+}
+
+#[derive(BitRead, PartialEq, Debug)]
+#[discriminant_bits = 2]
+enum FieldsEnum {
+    Move {
+        x: i16,
+        #[size = 3]
+        dir: u8,
+    },
+    Pair(u8, #[size = 4] u8),
+    Text {
+        len: u8,
+        #[size = "len"]
+        text: String,
+    },
+    Dynamic(#[size_bits = 3] u8),
+}
+
+#[derive(BitReadSized, PartialEq, Debug)]
+#[discriminant_bits = 1]
+enum FieldsEnumSized {
+    Short {
+        #[size = "input_size"]
+        value: u8,
+        flag: bool,
+    },
+    Long(#[size = "input_size"] u16, bool),
+}
+
+#[test]
+fn test_read_variant_fields() {
+    let mut bytes = Vec::new();
+    let mut write = BitWriteStream::new(&mut bytes, BigEndian);
+    write.write_int(0u8, 2).unwrap();
+    write.write_int(-5i16, 16).unwrap();
+    write.write_int(5u8, 3).unwrap();
+    write.write_int(1u8, 2).unwrap();
+    write.write_int(12u8, 8).unwrap();
+    write.write_int(9u8, 4).unwrap();
+    write.write_int(2u8, 2).unwrap();
+    write.write_int(2u8, 8).unwrap();
+    write.write_bytes(b"hi").unwrap();
+    write.write_int(3u8, 2).unwrap();
+    write.write_int(5u8, 3).unwrap();
+    write.write_int(17u8, 5).unwrap();
+    write.write_int(1u8, 1).unwrap();
+    write.write_int(10u16, 4).unwrap();
+    write.write_bool(true).unwrap();
+    write.write_int(0u8, 1).unwrap();
+    write.write_int(3u8, 4).unwrap();
+    write.write_bool(false).unwrap();
+
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    assert_eq!(FieldsEnum::Move { x: -5, dir: 5 }, stream.read().unwrap());
+    assert_eq!(FieldsEnum::Pair(12, 9), stream.read().unwrap());
+    assert_eq!(
+        FieldsEnum::Text {
+            len: 2,
+            text: "hi".to_string()
+        },
+        stream.read().unwrap()
+    );
+    assert_eq!(FieldsEnum::Dynamic(17), stream.read().unwrap());
+    assert_eq!(
+        FieldsEnumSized::Long(10, true),
+        stream.read_sized(4).unwrap()
+    );
+    assert_eq!(
+        FieldsEnumSized::Short {
+            value: 3,
+            flag: false
+        },
+        stream.read_sized(4).unwrap()
+    );
+    assert_eq!(None, bit_size_of::<FieldsEnum>());
+}
+
+#[derive(BitRead, PartialEq, Debug)]
+struct ConditionalStruct {
+    flag: bool,
+    #[read_if = "flag"]
+    value: Option<u8>,
+    #[size = 3]
+    kind: u8,
+    #[read_if = "kind == 2"]
+    #[size = 4]
+    extra: Option<u8>,
+}
+
+#[derive(BitReadSized, PartialEq, Debug)]
+struct ConditionalSized {
+    #[read_if = "input_size > 2"]
+    #[size = "input_size"]
+    value: Option<u8>,
+    end: bool,
+}
+
+#[test]
+fn test_read_conditional() {
+    let bytes = vec![0b1000_0001, 0b1010_1010, 0b0000_0001, 0b1000_0000];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    assert_eq!(
+        ConditionalStruct {
+            flag: true,
+            value: Some(0b0000_0011),
+            kind: 0b010,
+            extra: Some(0b1010),
+        },
+        stream.read().unwrap()
+    );
+    assert_eq!(16, stream.pos());
+    assert_eq!(
+        ConditionalStruct {
+            flag: false,
+            value: None,
+            kind: 0,
+            extra: None,
+        },
+        stream.read().unwrap()
+    );
+    assert_eq!(20, stream.pos());
+    assert_eq!(
+        ConditionalSized {
+            value: Some(0),
+            end: true
+        },
+        stream.read_sized(3).unwrap()
+    );
+    assert_eq!(
+        ConditionalSized {
+            value: None,
+            end: true
+        },
+        stream.read_sized(2).unwrap()
+    );
+    assert_eq!(None, bit_size_of::<ConditionalStruct>());
+    assert_eq!(None, bit_size_of_sized::<ConditionalSized>(3));
+}
+
+#[derive(BitRead, PartialEq, Debug)]
+#[magic = b"RIFF"]
+#[bit_size]
+struct MagicReadStruct {
+    size: u16,
+    #[magic = 0xCAFE]
+    #[assert = "version <= 2"]
+    version: u8,
+    #[assert = "flags != version"]
+    #[size = 4]
+    flags: u8,
+}
+
+#[test]
+fn test_read_magic() {
+    let bytes = vec![
+        b'R',
+        b'I',
+        b'F',
+        b'F',
+        0x00,
+        0x10,
+        0xCA,
+        0xFE,
+        0x02,
+        0b0101_0000,
+    ];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    assert_eq!(
+        MagicReadStruct {
+            size: 16,
+            version: 2,
+            flags: 5,
+        },
+        stream.read().unwrap()
+    );
+    assert_eq!(76, stream.pos());
+    assert_eq!(Some(76), bit_size_of::<MagicReadStruct>());
+
+    let bytes = vec![
+        b'R',
+        b'I',
+        b'F',
+        b'X',
+        0x00,
+        0x10,
+        0xCA,
+        0xFE,
+        0x02,
+        0b0101_0000,
+    ];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    match stream.read::<MagicReadStruct>().unwrap_err() {
+        BitError::InvalidMagic { expected, found } => {
+            assert_eq!("b\"RIFF\"", expected);
+            assert_eq!("b\"RIFX\"", found);
+        }
+        err => panic!("unexpected error {:?}", err),
+    }
+
+    let bytes = vec![
+        b'R',
+        b'I',
+        b'F',
+        b'F',
+        0x00,
+        0x10,
+        0xCA,
+        0xFF,
+        0x02,
+        0b0101_0000,
+    ];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    let err = stream.read::<MagicReadStruct>().unwrap_err();
+    assert_eq!(
+        "MagicReadStruct.version at bit 48: Invalid magic value, expected 0xCAFE but found 0xCAFF",
+        err.to_string()
+    );
+}
+
+#[test]
+fn test_read_assert() {
+    let bytes = vec![
+        b'R',
+        b'I',
+        b'F',
+        b'F',
+        0x00,
+        0x10,
+        0xCA,
+        0xFE,
+        0x03,
+        0b0101_0000,
+    ];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    let err = stream.read::<MagicReadStruct>().unwrap_err();
+    match err.root() {
+        BitError::AssertionFailed { field, assertion } => {
+            assert_eq!("version", field);
+            assert_eq!("version <= 2", assertion);
+        }
+        err => panic!("unexpected error {:?}", err),
+    }
+    assert_eq!(
+        "MagicReadStruct.version at bit 48: The value of the field 'version' doesn't pass the assertion 'version <= 2'",
+        err.to_string()
+    );
+
+    let bytes = vec![
+        b'R',
+        b'I',
+        b'F',
+        b'F',
+        0x00,
+        0x10,
+        0xCA,
+        0xFE,
+        0x02,
+        0b0010_0000,
+    ];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    assert!(matches!(
+        stream.read::<MagicReadStruct>().unwrap_err().root(),
+        BitError::AssertionFailed { .. }
+    ));
+}
+
+#[derive(BitRead, PartialEq, Debug)]
+struct ListReadStruct {
+    #[until = "0"]
+    ids: Vec<u8>,
+    #[continuation_bit]
+    flags: Vec<bool>,
+    #[align = 8]
+    #[until_eof]
+    rest: Vec<u8>,
+}
+
+#[test]
+fn test_read_terminated_lists() {
+    let bytes = vec![1, 2, 0, 0b1110_0000, 5, 6];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    assert_eq!(
+        ListReadStruct {
+            ids: vec![1, 2],
+            flags: vec![true, false],
+            rest: vec![5, 6],
+        },
+        stream.read().unwrap()
+    );
+    assert_eq!(0, stream.bits_left());
+    assert_eq!(None, bit_size_of::<ListReadStruct>());
+
+    // missing terminator
+    let bytes = vec![1, 2, 3];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut stream = BitReadStream::from(buffer);
+    let err = stream.read::<ListReadStruct>().unwrap_err();
+    match err {
+        BitError::Context { path, .. } => assert_eq!("ListReadStruct.ids[3]", path.to_string()),
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[derive(BitRead, PartialEq, Debug)]
+struct MixedEndianReadStruct {
+    #[le]
+    little: u32,
+    #[be]
+    #[size = 16]
+    big: u16,
+    #[be]
+    #[read_if = "little > 0"]
+    optional: Option<u8>,
+}
+
+#[test]
+fn test_read_endianness_override() {
+    let bytes = vec![0x01, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56];
+    let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    let mut stream = BitReadStream::from(buffer);
+    assert_eq!(
+        MixedEndianReadStruct {
+            little: 1,
+            big: 0x1234,
+            optional: Some(0x56),
+        },
+        stream.read().unwrap()
+    );
+    assert_eq!(56, stream.pos());
+}
+
+#[test]
+fn test_read_endianness_override_unaligned() {
+    #[derive(BitRead, Debug)]
+    struct Unaligned {
+        #[allow(dead_code)]
+        flag: bool,
+        #[le]
+        #[allow(dead_code)]
+        value: u16,
+    }
+
+    let bytes = vec![0x12, 0x34, 0x56];
+    let mut stream = BitReadStream::from(BitReadBuffer::new(&bytes, BigEndian));
+    let err = stream.read::<Unaligned>().unwrap_err();
+    assert!(matches!(
+        err.root(),
+        bitbuffer::BitError::UnalignedEndianness { pos: 1 }
+    ));
+}
@@ -289,3 +289,791 @@ fn test_read_size_expression() {
     stream.write(&val).unwrap();
     assert_eq!(bytes, data);
 }
+
+#[derive(BitWrite, BitRead, PartialEq, Debug)]
+#[discriminant_bits = 2]
+#[align = 8]
+enum AlignEnum {
+    #[size = 3]
+    #[align = 4]
+    Foo(u8),
+    Bar(bool),
+}
+
+#[test]
+fn test_write_align() {
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write_bool(true).unwrap();
+    stream.write(&AlignEnum::Foo(0b101)).unwrap();
+    stream.write(&AlignEnum::Bar(true)).unwrap();
+    assert_eq!(19, stream.bit_len());
+    assert_eq!(data, [0b1000_0000, 0b0000_1010, 0b0110_0000]);
+
+    let mut read = BitReadStream::<BigEndian>::from(data.as_slice());
+    assert!(read.read_bool().unwrap());
+    assert_eq!(AlignEnum::Foo(0b101), read.read().unwrap());
+    assert_eq!(AlignEnum::Bar(true), read.read().unwrap());
+}
+
+#[test]
+fn test_write_prealloc_align() {
+    let mut data = [0; 1];
+    let mut stream = BitWriteStream::from_slice(&mut data, BigEndian);
+    stream.write_bool(true).unwrap();
+    // the padding depends on the position the value is written at
+    assert!(matches!(
+        stream.write_prealloc(&AlignEnum::Foo(0b101)),
+        Err(bitbuffer::BitError::NotEnoughSpace {
+            requested: 14,
+            bits_left: 7
+        })
+    ));
+    assert_eq!(1, stream.bit_len());
+
+    #[derive(BitWrite)]
+    struct WideAlign {
+        #[align = 128]
+        value: u8,
+    }
+
+    // alignments that don't divide 64 are measured at the full position of the stream
+    let mut data = [0; 10];
+    let mut stream = BitWriteStream::from_slice(&mut data, BigEndian);
+    stream.write_int(0u64, 64).unwrap();
+    assert!(matches!(
+        stream.write_prealloc(&WideAlign { value: 1 }),
+        Err(bitbuffer::BitError::NotEnoughSpace {
+            requested: 72,
+            bits_left: 16
+        })
+    ));
+    assert_eq!(64, stream.bit_len());
+
+    let mut data = [0; 17];
+    let mut stream = BitWriteStream::from_slice(&mut data, BigEndian);
+    stream.write_int(0u64, 64).unwrap();
+    stream.write_prealloc(&WideAlign { value: 1 }).unwrap();
+    assert_eq!(136, stream.bit_len());
+    assert_eq!(1, data[16]);
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+#[discriminant_bits = 4]
+#[endianness = "E"]
+enum CaptureEnum<'a, E: Endianness> {
+    Ping,
+    Value(u8),
+    #[discriminant = "_"]
+    Unknown {
+        #[discriminant]
+        tag: u8,
+        #[size = 8]
+        body: BitReadStream<'a, E>,
+    },
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+#[discriminant_bits = 2]
+enum CaptureUnnamedEnum {
+    Foo,
+    #[discriminant = "_"]
+    Other(#[discriminant] u8),
+}
+
+#[test]
+fn test_write_wildcard_capture() {
+    let bytes = vec![0b1010_1100, 0b0011_0001, 0b0010_0000, 0b0000_0010];
+    let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    let mut read = BitReadStream::from(buffer);
+    let unknown: CaptureEnum<BigEndian> = read.read().unwrap();
+    let value: CaptureEnum<BigEndian> = read.read().unwrap();
+    let ping: CaptureEnum<BigEndian> = read.read().unwrap();
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write(&unknown).unwrap();
+    stream.write(&value).unwrap();
+    stream.write(&ping).unwrap();
+    stream.write(&CaptureUnnamedEnum::Foo).unwrap();
+    stream.write(&CaptureUnnamedEnum::Other(2)).unwrap();
+    assert_eq!(bytes, data);
+}
+
+#[test]
+fn test_write_wildcard_capture_invalid() {
+    use bitbuffer::BitError;
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    match stream.write(&CaptureUnnamedEnum::Other(0)) {
+        Err(BitError::DiscriminantConflict {
+            discriminant,
+            enum_name,
+        }) => {
+            assert_eq!(0, discriminant);
+            assert_eq!("CaptureUnnamedEnum", enum_name);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+    match stream.write(&CaptureUnnamedEnum::Other(4)) {
+        Err(BitError::ValueTooLarge { value, bits }) => {
+            assert_eq!("4", value);
+            assert_eq!(2, bits);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(0, stream.bit_len());
+}
+
+#[derive(BitWrite, PartialEq, Debug)]
+#[discriminant_bits = 2]
+enum FieldsEnum {
+    Move {
+        x: i16,
+        #[size = 3]
+        dir: u8,
+    },
+    Pair(u8, #[size = 4] u8),
+    Text {
+        len: u8,
+        #[size = "len"]
+        text: String,
+    },
+}
+
+#[derive(BitWriteSized, PartialEq, Debug)]
+#[discriminant_bits = 1]
+enum FieldsEnumSized {
+    Short {
+        #[size = "input_size"]
+        value: u8,
+        flag: bool,
+    },
+    Long(#[size = "input_size"] u16, bool),
+}
+
+#[test]
+fn test_write_variant_fields() {
+    let mut expected = Vec::new();
+    let mut write = BitWriteStream::new(&mut expected, BigEndian);
+    write.write_int(0u8, 2).unwrap();
+    write.write_int(-5i16, 16).unwrap();
+    write.write_int(5u8, 3).unwrap();
+    write.write_int(1u8, 2).unwrap();
+    write.write_int(12u8, 8).unwrap();
+    write.write_int(9u8, 4).unwrap();
+    write.write_int(2u8, 2).unwrap();
+    write.write_int(2u8, 8).unwrap();
+    write.write_bytes(b"hi").unwrap();
+    write.write_int(1u8, 1).unwrap();
+    write.write_int(10u16, 4).unwrap();
+    write.write_bool(true).unwrap();
+    write.write_int(0u8, 1).unwrap();
+    write.write_int(3u8, 4).unwrap();
+    write.write_bool(false).unwrap();
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write(&FieldsEnum::Move { x: -5, dir: 5 }).unwrap();
+    stream.write(&FieldsEnum::Pair(12, 9)).unwrap();
+    stream
+        .write(&FieldsEnum::Text {
+            len: 2,
+            text: "hi".to_string(),
+        })
+        .unwrap();
+    stream
+        .write_sized(&FieldsEnumSized::Long(10, true), 4)
+        .unwrap();
+    stream
+        .write_sized(
+            &FieldsEnumSized::Short {
+                value: 3,
+                flag: false,
+            },
+            4,
+        )
+        .unwrap();
+    assert_eq!(expected, data);
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+struct ConditionalStruct {
+    flag: bool,
+    #[read_if = "flag"]
+    value: Option<u8>,
+    #[size = 3]
+    kind: u8,
+    #[read_if = "kind == 2"]
+    #[size = 4]
+    extra: Option<u8>,
+}
+
+#[derive(BitWrite, PartialEq, Debug)]
+#[discriminant_bits = 1]
+enum ConditionalEnum {
+    Empty,
+    Value {
+        present: bool,
+        #[read_if = "present"]
+        value: Option<u16>,
+    },
+}
+
+#[test]
+fn test_write_conditional() {
+    let bytes = vec![
+        0b1000_0001,
+        0b1010_1010,
+        0b0000_1100,
+        0b0000_0000,
+        0b0000_0100,
+    ];
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream
+        .write(&ConditionalStruct {
+            flag: true,
+            value: Some(0b0000_0011),
+            kind: 0b010,
+            extra: Some(0b1010),
+        })
+        .unwrap();
+    stream
+        .write(&ConditionalStruct {
+            flag: false,
+            value: None,
+            kind: 0,
+            extra: None,
+        })
+        .unwrap();
+    stream
+        .write(&ConditionalEnum::Value {
+            present: true,
+            value: Some(1),
+        })
+        .unwrap();
+    assert_eq!(bytes, data);
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&bytes, BigEndian));
+    assert_eq!(
+        ConditionalStruct {
+            flag: true,
+            value: Some(0b0000_0011),
+            kind: 0b010,
+            extra: Some(0b1010),
+        },
+        read.read().unwrap()
+    );
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let result = stream.write(&ConditionalStruct {
+        flag: false,
+        value: Some(1),
+        kind: 0,
+        extra: None,
+    });
+    assert!(matches!(
+        result,
+        Err(bitbuffer::BitError::OptionalFieldMismatch { field, .. }) if field == "value"
+    ));
+    let result = stream.write(&ConditionalEnum::Value {
+        present: true,
+        value: None,
+    });
+    assert!(matches!(
+        result,
+        Err(bitbuffer::BitError::OptionalFieldMismatch { field, condition })
+            if field == "value" && condition == "present"
+    ));
+}
+
+mod timestamp {
+    use bitbuffer::{BitReadStream, BitWriteStream, Endianness, Result};
+
+    pub fn read<E: Endianness>(stream: &mut BitReadStream<E>) -> Result<u32> {
+        stream.read_int(24)
+    }
+
+    pub fn write<E: Endianness>(value: &u32, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_int(*value, 24)
+    }
+}
+
+fn read_scaled<E: Endianness>(
+    stream: &mut BitReadStream<E>,
+    size: usize,
+    scale: u8,
+) -> bitbuffer::Result<f32> {
+    Ok(stream.read_int::<u16>(size)? as f32 / scale as f32)
+}
+
+fn write_scaled<E: Endianness>(
+    value: &f32,
+    stream: &mut BitWriteStream<E>,
+    size: usize,
+    scale: u8,
+) -> bitbuffer::Result<()> {
+    stream.write_int((value * scale as f32) as u16, size)
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+struct CustomFieldStruct {
+    #[bit_with = "timestamp"]
+    time: u32,
+    scale: u8,
+    #[size = 10]
+    #[bitread_with = "read_scaled(scale)"]
+    #[bitwrite_with = "write_scaled(scale)"]
+    value: f32,
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+#[discriminant_bits = 1]
+enum CustomFieldEnum {
+    Empty,
+    Time(#[bit_with = "timestamp"] u32),
+}
+
+#[test]
+fn test_write_custom_fn() {
+    let bytes = vec![
+        0x01,
+        0x02,
+        0x03,
+        4,
+        0b0000_0101,
+        0b0110_0000,
+        0b0010_0000,
+        0b0100_0000,
+        0b0110_0000,
+    ];
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let value = CustomFieldStruct {
+        time: 0x010203,
+        scale: 4,
+        value: 5.25,
+    };
+    stream.write(&value).unwrap();
+    stream.write(&CustomFieldEnum::Time(0x010203)).unwrap();
+    assert_eq!(bytes, data);
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&bytes, BigEndian));
+    assert_eq!(value, read.read().unwrap());
+    assert_eq!(42, read.pos());
+    assert_eq!(None, bitbuffer::bit_size_of::<CustomFieldStruct>());
+    assert_eq!(
+        CustomFieldEnum::Time(0x010203),
+        read.read::<CustomFieldEnum>().unwrap()
+    );
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+#[magic = b"RIFF"]
+struct MagicStruct {
+    size: u16,
+    #[magic = 0b101]
+    #[size = 5]
+    flags: u8,
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+#[discriminant_bits = 8]
+enum MagicEnum {
+    Empty,
+    Header {
+        #[magic = 0xCAFE]
+        version: u8,
+    },
+}
+
+#[test]
+fn test_write_magic() {
+    let bytes = vec![
+        b'R',
+        b'I',
+        b'F',
+        b'F',
+        0x00,
+        0x10,
+        0b1010_0011,
+        0x01,
+        0xCA,
+        0xFE,
+        0x02,
+    ];
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let value = MagicStruct { size: 16, flags: 3 };
+    stream.write(&value).unwrap();
+    stream.write(&MagicEnum::Header { version: 2 }).unwrap();
+    assert_eq!(bytes, data);
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&bytes, BigEndian));
+    assert_eq!(value, read.read().unwrap());
+    assert_eq!(
+        MagicEnum::Header { version: 2 },
+        read.read::<MagicEnum>().unwrap()
+    );
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+struct ListStruct {
+    #[until = "0"]
+    ids: Vec<u8>,
+    #[continuation_bit]
+    flags: Vec<bool>,
+    #[align = 8]
+    #[until_eof]
+    rest: Vec<u8>,
+}
+
+#[test]
+fn test_write_terminated_lists() {
+    let bytes = vec![1, 2, 0, 0b1110_0000, 5, 6];
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let value = ListStruct {
+        ids: vec![1, 2],
+        flags: vec![true, false],
+        rest: vec![5, 6],
+    };
+    stream.write(&value).unwrap();
+    assert_eq!(bytes, data);
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&bytes, BigEndian));
+    assert_eq!(value, read.read().unwrap());
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let value = ListStruct {
+        ids: vec![1, 0, 2],
+        flags: vec![],
+        rest: vec![],
+    };
+    assert!(matches!(
+        stream.write(&value),
+        Err(bitbuffer::BitError::TerminatorInItems { index: 1 })
+    ));
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+struct ComputedStruct {
+    #[computed = "data.len()"]
+    len: u8,
+    #[size = "len"]
+    data: Vec<u8>,
+    #[byte_length_of = "body"]
+    body_len: u16,
+    tag: u8,
+    body: String,
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+#[discriminant_bits = 8]
+enum ComputedEnum {
+    Items {
+        #[computed = "items.len()"]
+        count: u8,
+        #[size = "count"]
+        items: Vec<u8>,
+    },
+    Payload {
+        #[size = 8]
+        #[bit_length_of = "payload"]
+        bits: u8,
+        #[size = 5]
+        payload: u8,
+    },
+}
+
+#[test]
+fn test_write_computed() {
+    let bytes = vec![3, 1, 2, 3, 0x00, 0x04, 7, b'a', b'b', 0];
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let value = ComputedStruct {
+        len: 0,
+        data: vec![1, 2, 3],
+        body_len: 0,
+        tag: 7,
+        body: "ab".into(),
+    };
+    stream.write(&value).unwrap();
+    assert_eq!(bytes, data);
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&bytes, BigEndian));
+    assert_eq!(
+        ComputedStruct {
+            len: 3,
+            body_len: 4,
+            ..value
+        },
+        read.read().unwrap()
+    );
+
+    let bytes = vec![0, 2, 5, 6, 1, 5, 0b1011_0000];
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream
+        .write(&ComputedEnum::Items {
+            count: 0,
+            items: vec![5, 6],
+        })
+        .unwrap();
+    stream
+        .write(&ComputedEnum::Payload {
+            bits: 0,
+            payload: 0b10110,
+        })
+        .unwrap();
+    assert_eq!(bytes, data);
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&bytes, BigEndian));
+    assert_eq!(
+        ComputedEnum::Items {
+            count: 2,
+            items: vec![5, 6]
+        },
+        read.read().unwrap()
+    );
+    assert_eq!(
+        ComputedEnum::Payload {
+            bits: 5,
+            payload: 0b10110
+        },
+        read.read().unwrap()
+    );
+}
+
+#[test]
+fn test_write_computed_too_large() {
+    use bitbuffer::BitError;
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let value = ComputedStruct {
+        len: 0,
+        data: vec![0; 256],
+        body_len: 0,
+        tag: 7,
+        body: "ab".into(),
+    };
+    assert!(matches!(
+        stream.write(&value),
+        Err(BitError::ValueTooLarge { ref value, bits: 8 }) if value == "256"
+    ));
+    assert_eq!(0, stream.bit_len());
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+struct MixedEndianStruct {
+    #[le]
+    little: u16,
+    #[be]
+    big: u16,
+    native: u16,
+}
+
+#[derive(BitRead, BitWrite, PartialEq, Debug)]
+#[discriminant_bits = 8]
+enum MixedEndianEnum {
+    #[be]
+    Big(u16),
+    #[le]
+    Little { value: u16 },
+}
+
+#[test]
+fn test_write_endianness_override() {
+    let bytes = vec![0x34, 0x12, 0x12, 0x34, 0x12, 0x34];
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let value = MixedEndianStruct {
+        little: 0x1234,
+        big: 0x1234,
+        native: 0x1234,
+    };
+    stream.write(&value).unwrap();
+    assert_eq!(bytes, data);
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&bytes, BigEndian));
+    assert_eq!(value, read.read().unwrap());
+
+    let bytes = vec![0x34, 0x12, 0x12, 0x34, 0x34, 0x12];
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    stream.write(&value).unwrap();
+    assert_eq!(bytes, data);
+
+    let bytes = vec![0, 0x12, 0x34, 1, 0x34, 0x12];
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    stream.write(&MixedEndianEnum::Big(0x1234)).unwrap();
+    stream
+        .write(&MixedEndianEnum::Little { value: 0x1234 })
+        .unwrap();
+    assert_eq!(bytes, data);
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&bytes, BigEndian));
+    assert_eq!(MixedEndianEnum::Big(0x1234), read.read().unwrap());
+    assert_eq!(
+        MixedEndianEnum::Little { value: 0x1234 },
+        read.read().unwrap()
+    );
+}
+
+#[test]
+fn test_write_endianness_override_unaligned() {
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write_bool(true).unwrap();
+    assert!(matches!(
+        stream.write(&MixedEndianStruct {
+            little: 0x1234,
+            big: 0x1234,
+            native: 0x1234,
+        }),
+        Err(bitbuffer::BitError::UnalignedEndianness { pos: 1 })
+    ));
+}
+
+#[derive(BitWrite, PartialEq, Debug)]
+#[magic = 0xF]
+struct SizedListStruct {
+    #[until = "0"]
+    ids: Vec<u16>,
+    #[continuation_bit]
+    flags: Vec<bool>,
+    #[read_if = "flags.len() > 1"]
+    #[magic = 0b11]
+    extra: Option<u8>,
+}
+
+fn assert_bit_size<T: bitbuffer::BitWrite<BigEndian>>(value: &T) {
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write(value).unwrap();
+    assert_eq!(stream.bit_len(), value.written_bit_size());
+}
+
+#[test]
+fn test_write_bit_size() {
+    assert_bit_size(&TestStruct {
+        foo: 1,
+        str: "foo".into(),
+        truncated: "ab".into(),
+        bar: 2,
+        float: 1.5,
+        asd: 3,
+        previous_field: 1,
+    });
+    assert_bit_size(&TestUnnamedFieldEnum::Foo(-3));
+    assert_bit_size(&TestUnnamedFieldEnum::Bar(true));
+    assert_bit_size(&ConditionalStruct {
+        flag: true,
+        value: Some(1),
+        kind: 2,
+        extra: Some(3),
+    });
+    assert_bit_size(&ConditionalStruct {
+        flag: false,
+        value: None,
+        kind: 1,
+        extra: None,
+    });
+    assert_bit_size(&ConditionalEnum::Empty);
+    assert_bit_size(&ConditionalEnum::Value {
+        present: true,
+        value: Some(12),
+    });
+    assert_bit_size(&MagicStruct { size: 16, flags: 3 });
+    assert_bit_size(&MagicEnum::Header { version: 2 });
+    assert_bit_size(&ComputedEnum::Items {
+        count: 0,
+        items: vec![1, 2, 3],
+    });
+    assert_bit_size(&ComputedEnum::Payload {
+        bits: 0,
+        payload: 3,
+    });
+    assert_bit_size(&MixedEndianEnum::Little { value: 1 });
+    assert_bit_size(&SizedListStruct {
+        ids: vec![1, 2, 3],
+        flags: vec![true, false],
+        extra: Some(5),
+    });
+    assert_bit_size(&SizedListStruct {
+        ids: vec![],
+        flags: vec![],
+        extra: None,
+    });
+    assert_eq!(
+        4 + 3 * 16 + 16 + 2 * 2 + 1 + 2 + 8,
+        bitbuffer::BitWrite::<BigEndian>::written_bit_size(&SizedListStruct {
+            ids: vec![1, 2, 3],
+            flags: vec![true, false],
+            extra: Some(5),
+        })
+    );
+
+    // sizes that depend on the position or custom functions are measured
+    assert_bit_size(&AlignEnum::Foo(3));
+    assert_bit_size(&ListStruct {
+        ids: vec![1, 2],
+        flags: vec![true, false],
+        rest: vec![5, 6],
+    });
+    assert_bit_size(&ComputedStruct {
+        len: 0,
+        data: vec![1, 2, 3],
+        body_len: 0,
+        tag: 7,
+        body: "ab".into(),
+    });
+    assert_bit_size(&CustomFieldStruct {
+        time: 0,
+        scale: 2,
+        value: 1.5,
+    });
+
+    let value = TestStructSized {
+        foo: 1,
+        string: "ab".into(),
+        int: 3,
+    };
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write_sized(&value, 4).unwrap();
+    assert_eq!(
+        stream.bit_len(),
+        bitbuffer::BitWriteSized::<BigEndian>::written_bit_size_sized(&value, 4)
+    );
+}
+
+#[test]
+fn test_write_value_too_large() {
+    use bitbuffer::BitError;
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let result = stream.write(&ConditionalStruct {
+        flag: false,
+        value: None,
+        kind: 8,
+        extra: None,
+    });
+    assert!(matches!(
+        result,
+        Err(BitError::ValueTooLarge { ref value, bits: 3 }) if value == "8"
+    ));
+
+    let result = stream.write(&TestUnnamedFieldEnum::Foo(-17));
+    assert!(matches!(
+        result,
+        Err(BitError::ValueTooLarge { ref value, bits: 5 }) if value == "-17"
+    ));
+}
@@ -0,0 +1,283 @@
+use crate::{
+    BitError, BitRead, BitReadStream, BitSize, BitWrite, BitWriteStream, Endianness, Result,
+};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::num::TryFromIntError;
+
+/// Unsigned integer of `N` bits
+///
+/// The integer is read and written using exactly `N` bits, removing the need for a `size` attribute when
+/// the type is used in a derived struct. The value is checked to fit in `N` bits on construction.
+///
+/// `N` is required to be between 1 and 128.
+///
+/// The value is always stored as `u128`, so the type takes 16 bytes regardless of `N`. Integers of up to 64 bits
+/// are read and written as `u64`.
+///
+/// The integer converts into `u128` with `From` and from and into the other unsigned primitive integers
+/// with `TryFrom`, converting a value that doesn't fit in `N` bits returns a [`BitError::ValueTooLarge`].
+///
+/// # Examples
+///
+/// ```
+/// # use bitbuffer::{BitRead, BitReadBuffer, BitReadStream, BitSize, BitWrite, LittleEndian, Result, UInt};
+/// #
+/// # fn main() -> Result<()> {
+/// #[derive(BitRead, BitWrite)]
+/// #[bit_size]
+/// struct Header {
+///     version: UInt<3>,
+///     length: UInt<13>,
+/// }
+///
+/// assert_eq!(Header::BIT_SIZE, Some(16));
+/// assert_eq!(UInt::<3>::new(7).map(UInt::get), Some(7));
+/// assert_eq!(UInt::<3>::new(8), None);
+///
+/// let bytes = [0b0101_0011, 0b0000_0001];
+/// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+/// let header: Header = stream.read()?;
+/// assert_eq!(header.version.get(), 3);
+/// assert_eq!(header.length.get(), 0b1_0101_0);
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct UInt<const N: usize>(u128);
+
+impl<const N: usize> UInt<N> {
+    const VALID: () = assert!(N > 0 && N <= 128, "UInt is required to have 1 to 128 bits");
+
+    /// The number of bits of the integer
+    pub const BITS: usize = N;
+    /// The smallest value of the integer
+    pub const MIN: Self = UInt(0);
+    /// The largest value of the integer
+    pub const MAX: Self = UInt(u128::MAX >> (128 - N));
+
+    /// Create an integer from the value, or `None` if the value doesn't fit in `N` bits
+    #[allow(clippy::let_unit_value)]
+    pub const fn new(value: u128) -> Option<Self> {
+        let _ = Self::VALID;
+        if value <= Self::MAX.0 {
+            Some(UInt(value))
+        } else {
+            None
+        }
+    }
+
+    /// Create an integer from the lowest `N` bits of the value
+    #[allow(clippy::let_unit_value)]
+    pub const fn new_truncated(value: u128) -> Self {
+        let _ = Self::VALID;
+        UInt(value & Self::MAX.0)
+    }
+
+    /// Get the value of the integer
+    pub const fn get(self) -> u128 {
+        self.0
+    }
+}
+
+/// Signed integer of `N` bits
+///
+/// The integer is read and written as `N` bit two's complement integer, removing the need for a `size` attribute
+/// when the type is used in a derived struct. The value is checked to fit in `N` bits on construction.
+///
+/// `N` is required to be between 1 and 128.
+///
+/// The value is always stored as `i128`, so the type takes 16 bytes regardless of `N`. Integers of up to 64 bits
+/// are read and written as `i64`.
+///
+/// The integer converts into `i128` with `From` and from and into the other signed primitive integers
+/// with `TryFrom`, converting a value that doesn't fit in `N` bits returns a [`BitError::ValueTooLarge`].
+///
+/// # Examples
+///
+/// ```
+/// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result, SInt};
+/// #
+/// # fn main() -> Result<()> {
+/// assert_eq!(SInt::<4>::MIN.get(), -8);
+/// assert_eq!(SInt::<4>::new(8), None);
+///
+/// let bytes = [0b1110_0000];
+/// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+/// let value: SInt<4> = stream.read()?;
+/// assert_eq!(value.get(), -2);
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct SInt<const N: usize>(i128);
+
+impl<const N: usize> SInt<N> {
+    const VALID: () = assert!(N > 0 && N <= 128, "SInt is required to have 1 to 128 bits");
+
+    /// The number of bits of the integer
+    pub const BITS: usize = N;
+    /// The smallest value of the integer
+    pub const MIN: Self = SInt(i128::MIN >> (128 - N));
+    /// The largest value of the integer
+    pub const MAX: Self = SInt(i128::MAX >> (128 - N));
+
+    /// Create an integer from the value, or `None` if the value doesn't fit in `N` bits
+    #[allow(clippy::let_unit_value)]
+    pub const fn new(value: i128) -> Option<Self> {
+        let _ = Self::VALID;
+        if value >= Self::MIN.0 && value <= Self::MAX.0 {
+            Some(SInt(value))
+        } else {
+            None
+        }
+    }
+
+    /// Create an integer from the lowest `N` bits of the value
+    #[allow(clippy::let_unit_value)]
+    pub const fn new_truncated(value: i128) -> Self {
+        let _ = Self::VALID;
+        let shift = (128 - N) as u32;
+        SInt(value << shift >> shift)
+    }
+
+    /// Get the value of the integer
+    pub const fn get(self) -> i128 {
+        self.0
+    }
+}
+
+impl<const N: usize> From<UInt<N>> for u128 {
+    fn from(value: UInt<N>) -> Self {
+        value.0
+    }
+}
+
+impl<const N: usize> From<SInt<N>> for i128 {
+    fn from(value: SInt<N>) -> Self {
+        value.0
+    }
+}
+
+macro_rules! impl_try_from_primitive {
+    ($int:ident, $storage:ty, $($primitive:ty),*) => {
+        $(
+            impl<const N: usize> TryFrom<$primitive> for $int<N> {
+                type Error = BitError;
+
+                #[allow(clippy::unnecessary_cast)]
+                fn try_from(value: $primitive) -> Result<Self> {
+                    $int::new(value as $storage).ok_or_else(|| BitError::ValueTooLarge {
+                        value: value.to_string(),
+                        bits: N,
+                    })
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_try_into_primitive {
+    ($int:ident, $($primitive:ty),*) => {
+        $(
+            impl<const N: usize> TryFrom<$int<N>> for $primitive {
+                type Error = TryFromIntError;
+
+                fn try_from(value: $int<N>) -> std::result::Result<Self, Self::Error> {
+                    <$primitive>::try_from(value.0)
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_primitive!(UInt, u128, u8, u16, u32, u64, u128, usize);
+impl_try_from_primitive!(SInt, i128, i8, i16, i32, i64, i128, isize);
+impl_try_into_primitive!(UInt, u8, u16, u32, u64, usize);
+impl_try_into_primitive!(SInt, i8, i16, i32, i64, isize);
+
+impl<const N: usize> Display for UInt<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<const N: usize> Display for SInt<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<'a, E: Endianness, const N: usize> BitRead<'a, E> for UInt<N> {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        if N <= 64 {
+            stream.read_int::<u64>(N).map(|value| UInt(value as u128))
+        } else {
+            stream.read_int::<u128>(N).map(UInt::new_truncated)
+        }
+    }
+
+    #[inline]
+    fn bit_size() -> Option<usize> {
+        Some(N)
+    }
+}
+
+impl<'a, E: Endianness, const N: usize> BitRead<'a, E> for SInt<N> {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        if N <= 64 {
+            stream.read_int::<i64>(N).map(|value| SInt(value as i128))
+        } else {
+            stream.read_int::<i128>(N).map(SInt::new_truncated)
+        }
+    }
+
+    #[inline]
+    fn bit_size() -> Option<usize> {
+        Some(N)
+    }
+}
+
+impl<const N: usize> BitSize for UInt<N> {
+    const BIT_SIZE: Option<usize> = Some(N);
+}
+
+impl<const N: usize> BitSize for SInt<N> {
+    const BIT_SIZE: Option<usize> = Some(N);
+}
+
+impl<E: Endianness, const N: usize> BitWrite<E> for UInt<N> {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        if N <= 64 {
+            stream.write_int(self.0 as u64, N)
+        } else {
+            stream.write_int(self.0, N)
+        }
+    }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        N
+    }
+}
+
+impl<E: Endianness, const N: usize> BitWrite<E> for SInt<N> {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        if N <= 64 {
+            stream.write_int(self.0 as i64, N)
+        } else {
+            stream.write_int(self.0, N)
+        }
+    }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        N
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{BitReadStream, Endianness};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Trait for types where the number of bits read or written is known at compile time
+///
+/// Unlike [`BitRead::bit_size`], the size can be used in const contexts such as array lengths.
+///
+/// The trait is implemented by the `BitRead` derive for types with the `bit_size` attribute, when all fields have a
+/// fixed size the size is the sum of the field sizes.
+///
+/// # Examples
+///
+/// ```
+/// # use bitbuffer::{BitRead, BitSize};
+/// #
+/// #[derive(BitRead)]
+/// #[bit_size]
+/// struct Header {
+///     version: u8,
+///     #[size = 4]
+///     flags: u8,
+///     length: u32,
+/// }
+///
+/// assert_eq!(Header::BIT_SIZE, Some(44));
+/// const HEADER_BYTES: usize = Header::BIT_SIZE.unwrap().div_ceil(8);
+/// let buffer = [0u8; HEADER_BYTES];
+/// ```
+///
+/// [`BitRead::bit_size`]: trait.BitRead.html#method.bit_size
+pub trait BitSize {
+    /// The number of bits read or written for the type, or `None` if it depends on the data
+    const BIT_SIZE: Option<usize>;
+}
+
+/// Trait for types where the number of bits read or written with a size of `SIZE` is known at compile time
+///
+/// This is the compile time counterpart of [`BitReadSized::bit_size_sized`], used by the `BitRead` derive for fields
+/// with a `size` attribute.
+///
+/// [`BitReadSized::bit_size_sized`]: trait.BitReadSized.html#method.bit_size_sized
+pub trait BitSizeSized<const SIZE: usize> {
+    /// The number of bits read or written for the type, or `None` if it depends on the data
+    const BIT_SIZE: Option<usize>;
+}
+
+#[doc(hidden)]
+pub const fn add_bit_sizes(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        _ => None,
+    }
+}
+
+#[doc(hidden)]
+pub const fn mul_bit_size(size: Option<usize>, count: usize) -> Option<usize> {
+    match size {
+        Some(size) => Some(size * count),
+        None => None,
+    }
+}
+
+macro_rules! impl_bit_size_int {
+    ($type:ty) => {
+        impl BitSize for $type {
+            const BIT_SIZE: Option<usize> = Some(<$type>::BITS as usize);
+        }
+
+        impl<const SIZE: usize> BitSizeSized<SIZE> for $type {
+            const BIT_SIZE: Option<usize> = Some(SIZE);
+        }
+    };
+}
+
+impl_bit_size_int!(u8);
+impl_bit_size_int!(u16);
+impl_bit_size_int!(u32);
+impl_bit_size_int!(u64);
+impl_bit_size_int!(u128);
+impl_bit_size_int!(i8);
+impl_bit_size_int!(i16);
+impl_bit_size_int!(i32);
+impl_bit_size_int!(i64);
+impl_bit_size_int!(i128);
+
+macro_rules! impl_bit_size {
+    ($type:ty, $size:expr) => {
+        impl BitSize for $type {
+            const BIT_SIZE: Option<usize> = $size;
+        }
+    };
+}
+
+impl_bit_size!(std::num::NonZeroU8, Some(8));
+impl_bit_size!(std::num::NonZeroU16, Some(16));
+impl_bit_size!(std::num::NonZeroU32, Some(32));
+impl_bit_size!(std::num::NonZeroU64, Some(64));
+impl_bit_size!(std::num::NonZeroU128, Some(128));
+impl_bit_size!(f32, Some(32));
+impl_bit_size!(f64, Some(64));
+impl_bit_size!(bool, Some(1));
+impl_bit_size!(String, None);
+impl_bit_size!(Cow<'_, str>, None);
+
+impl<T: BitSize> BitSize for Box<T> {
+    const BIT_SIZE: Option<usize> = T::BIT_SIZE;
+}
+
+impl<T: BitSize> BitSize for Rc<T> {
+    const BIT_SIZE: Option<usize> = T::BIT_SIZE;
+}
+
+impl<T: BitSize> BitSize for Arc<T> {
+    const BIT_SIZE: Option<usize> = T::BIT_SIZE;
+}
+
+impl<T> BitSize for Option<T> {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl<T: BitSize, const N: usize> BitSize for [T; N] {
+    const BIT_SIZE: Option<usize> = mul_bit_size(T::BIT_SIZE, N);
+}
+
+macro_rules! impl_bit_size_tuple {
+    ($($type:ident),*) => {
+        impl<$($type: BitSize),*> BitSize for ($($type),*) {
+            const BIT_SIZE: Option<usize> = {
+                let size = Some(0);
+                $(let size = add_bit_sizes(size, $type::BIT_SIZE);)*
+                size
+            };
+        }
+    };
+}
+
+impl_bit_size_tuple!(T1, T2);
+impl_bit_size_tuple!(T1, T2, T3);
+impl_bit_size_tuple!(T1, T2, T3, T4);
+
+impl<const SIZE: usize> BitSizeSized<SIZE> for String {
+    const BIT_SIZE: Option<usize> = Some(SIZE * 8);
+}
+
+impl<const SIZE: usize> BitSizeSized<SIZE> for Cow<'_, str> {
+    const BIT_SIZE: Option<usize> = Some(SIZE * 8);
+}
+
+impl<const SIZE: usize> BitSizeSized<SIZE> for Cow<'_, [u8]> {
+    const BIT_SIZE: Option<usize> = Some(SIZE * 8);
+}
+
+impl<E: Endianness, const SIZE: usize> BitSizeSized<SIZE> for BitReadStream<'_, E> {
+    const BIT_SIZE: Option<usize> = Some(SIZE);
+}
+
+impl<T: BitSize, const SIZE: usize> BitSizeSized<SIZE> for Vec<T> {
+    const BIT_SIZE: Option<usize> = mul_bit_size(T::BIT_SIZE, SIZE);
+}
+
+impl<K: BitSize, T: BitSize, const SIZE: usize> BitSizeSized<SIZE> for HashMap<K, T> {
+    const BIT_SIZE: Option<usize> = mul_bit_size(add_bit_sizes(K::BIT_SIZE, T::BIT_SIZE), SIZE);
+}
+
+impl<T, const SIZE: usize> BitSizeSized<SIZE> for Option<T> {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl<T: BitSizeSized<SIZE>, const SIZE: usize, const N: usize> BitSizeSized<SIZE> for [T; N] {
+    const BIT_SIZE: Option<usize> = mul_bit_size(T::BIT_SIZE, N);
+}
@@ -0,0 +1,781 @@
+//! Universal codes for variable length integers
+//!
+//! Reading and writing of unary, Exp-Golomb, Elias gamma, Elias delta, Rice and Golomb codes
+//! as used by video bitstreams (H.264/H.265 `ue(v)` and `se(v)`) and various compression formats.
+//!
+//! The codes can either be read and written directly using the methods on [`BitReadStream`] and
+//! [`BitWriteStream`], or by using the wrapper types in this module which implement [`BitRead`]
+//! and [`BitWrite`].
+//!
+//! All codes use a unary prefix of zero bits terminated by a single one bit.
+//!
+//! The fixed width parts of the codes, like the suffix of Exp-Golomb and Elias codes and the remainder of
+//! Rice and Golomb codes, are read and written bit by bit in the [`BitOrder`] of the stream, most significant bit
+//! first for [`BitOrder::MsbFirst`] and least significant bit first for [`BitOrder::LsbFirst`].
+//! For [`LittleEndian`] and [`BigEndian`] this is the same as [`read_int`](BitReadStream::read_int),
+//! but unlike integers the bytes of the suffixes aren't reordered for [`LittleEndianMsbFirst`]
+//! and [`BigEndianLsbFirst`], so a code continues in the same bit order as its prefix.
+//!
+//! [`BitOrder`]: crate::BitOrder
+//! [`BitOrder::MsbFirst`]: crate::BitOrder::MsbFirst
+//! [`BitOrder::LsbFirst`]: crate::BitOrder::LsbFirst
+//! [`LittleEndian`]: crate::LittleEndian
+//! [`BigEndian`]: crate::BigEndian
+//! [`LittleEndianMsbFirst`]: crate::LittleEndianMsbFirst
+//! [`BigEndianLsbFirst`]: crate::BigEndianLsbFirst
+//!
+//! # Examples
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite};
+//! use bitbuffer::codes::{ExpGolomb, SignedExpGolomb};
+//!
+//! #[derive(BitRead, BitWrite)]
+//! struct SliceHeader {
+//!     first_mb_in_slice: ExpGolomb,
+//!     slice_type: ExpGolomb,
+//!     pic_parameter_set_id: ExpGolomb,
+//!     #[size = 4]
+//!     frame_num: u8,
+//!     slice_qp_delta: SignedExpGolomb,
+//! }
+//! ```
+
+use crate::endianness::has_mixed_order;
+use crate::{
+    BitError, BitRead, BitReadStream, BitSize, BitWrite, BitWriteStream, Endianness, Result,
+};
+use std::convert::TryFrom;
+use std::num::NonZeroU64;
+
+fn overflow() -> BitError {
+    BitError::VarIntOverflow { max: 64 }
+}
+
+impl<'a, E: Endianness> BitReadStream<'a, E> {
+    /// Read a unary coded integer, encoded as a number of zero bits terminated by a one bit
+    ///
+    /// The zero bits are counted a word at a time instead of bit by bit.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: no terminating one bit found in the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b0001_1001];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_unary()?, 3);
+    /// assert_eq!(stream.read_unary()?, 0);
+    /// assert_eq!(stream.read_unary()?, 2);
+    /// assert_eq!(stream.pos(), 8);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn read_unary(&mut self) -> Result<u64> {
+        let zeros = self.count_zeros()?;
+        self.skip_bits(zeros + 1)?;
+        Ok(zeros as u64)
+    }
+
+    /// Read a `count` bit integer in the bit order of the stream
+    ///
+    /// For the mixed endiannesses the bits are read a byte at a time, so the bytes aren't reordered.
+    fn read_serial(&mut self, count: usize) -> Result<u128> {
+        let endianness = self.endianness();
+        if !has_mixed_order(endianness) {
+            return self.read_int(count);
+        }
+        let lsb_first = endianness.bit_order().is_lsb_first();
+        let mut value = 0;
+        let mut pos = 0;
+        while pos < count {
+            let size = (count - pos).min(8);
+            let byte: u128 = self.read_int(size)?;
+            value = if lsb_first {
+                value | byte << pos
+            } else {
+                value << size | byte
+            };
+            pos += size;
+        }
+        Ok(value)
+    }
+
+    /// Read the value bits of an Exp-Golomb code, including the implicit leading one bit
+    fn read_exp_golomb_raw(&mut self, k: usize) -> Result<u128> {
+        let bits = self.read_unary()? as usize + k;
+        if bits >= 128 {
+            return Err(BitError::VarIntOverflow { max: 128 });
+        }
+        let rest = self.read_serial(bits)?;
+        Ok((1 << bits) | rest)
+    }
+
+    /// Read an unsigned Exp-Golomb code of order `k`
+    ///
+    /// With `k` set to `0` this reads the `ue(v)` values from H.264 and H.265 bitstreams.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::VarIntOverflow`]: the encoded integer doesn't fit in a `u64`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b1010_0110, 0b0100_0000];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_exp_golomb(0)?, 0);
+    /// assert_eq!(stream.read_exp_golomb(0)?, 1);
+    /// assert_eq!(stream.read_exp_golomb(0)?, 2);
+    /// assert_eq!(stream.read_exp_golomb(0)?, 3);
+    /// assert_eq!(stream.pos(), 12);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::VarIntOverflow`]: enum.ReadError.html#variant.VarIntOverflow
+    pub fn read_exp_golomb(&mut self, k: usize) -> Result<u64> {
+        self.try_read_with(|stream| {
+            let raw = stream.read_exp_golomb_raw(k)?;
+            u64::try_from(raw - (1 << k)).map_err(|_| overflow())
+        })
+    }
+
+    /// Read a signed Exp-Golomb code of order `k`
+    ///
+    /// The unsigned code number `0, 1, 2, 3, 4` is mapped to `0, 1, -1, 2, -2`,
+    /// with `k` set to `0` this reads the `se(v)` values from H.264 and H.265 bitstreams.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::VarIntOverflow`]: the encoded integer doesn't fit in an `i64`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b1010_0110, 0b0100_0000];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_signed_exp_golomb(0)?, 0);
+    /// assert_eq!(stream.read_signed_exp_golomb(0)?, 1);
+    /// assert_eq!(stream.read_signed_exp_golomb(0)?, -1);
+    /// assert_eq!(stream.read_signed_exp_golomb(0)?, 2);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::VarIntOverflow`]: enum.ReadError.html#variant.VarIntOverflow
+    pub fn read_signed_exp_golomb(&mut self, k: usize) -> Result<i64> {
+        self.try_read_with(|stream| {
+            let code = stream.read_exp_golomb_raw(k)? - (1 << k);
+            let value = if code & 1 == 1 {
+                i128::try_from(code.div_ceil(2))
+            } else {
+                i128::try_from(code / 2).map(|value| -value)
+            };
+            value
+                .ok()
+                .and_then(|value| i64::try_from(value).ok())
+                .ok_or_else(overflow)
+        })
+    }
+
+    /// Read an Elias gamma coded integer
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::VarIntOverflow`]: the encoded integer doesn't fit in a `u64`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b1010_0011, 0b0000_0000];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_elias_gamma()?.get(), 1);
+    /// assert_eq!(stream.read_elias_gamma()?.get(), 2);
+    /// assert_eq!(stream.read_elias_gamma()?.get(), 6);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::VarIntOverflow`]: enum.ReadError.html#variant.VarIntOverflow
+    pub fn read_elias_gamma(&mut self) -> Result<NonZeroU64> {
+        self.try_read_with(|stream| {
+            let raw = stream.read_exp_golomb_raw(0)?;
+            u64::try_from(raw)
+                .ok()
+                .and_then(NonZeroU64::new)
+                .ok_or_else(overflow)
+        })
+    }
+
+    /// Read an Elias delta coded integer
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::VarIntOverflow`]: the encoded integer doesn't fit in a `u64`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b1010_0011, 0b0100_0000];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_elias_delta()?.get(), 1);
+    /// assert_eq!(stream.read_elias_delta()?.get(), 2);
+    /// assert_eq!(stream.read_elias_delta()?.get(), 5);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::VarIntOverflow`]: enum.ReadError.html#variant.VarIntOverflow
+    pub fn read_elias_delta(&mut self) -> Result<NonZeroU64> {
+        self.try_read_with(|stream| {
+            let length = stream.read_elias_gamma()?.get();
+            if length > 64 {
+                return Err(overflow());
+            }
+            let bits = length as usize - 1;
+            let rest = stream.read_serial(bits)? as u64;
+            Ok(NonZeroU64::new((1 << bits) | rest).unwrap())
+        })
+    }
+
+    /// Read a Rice coded integer with parameter `k`
+    ///
+    /// The quotient is read as unary code, followed by `k` bits of remainder.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::VarIntOverflow`]: the encoded integer doesn't fit in a `u64`
+    /// - [`ReadError::TooManyBits`]: `k` is larger than 64
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b0011_1101, 0b0000_0000];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_rice(2)?, 11);
+    /// assert_eq!(stream.read_rice(2)?, 1);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::VarIntOverflow`]: enum.ReadError.html#variant.VarIntOverflow
+    /// [`ReadError::TooManyBits`]: enum.ReadError.html#variant.TooManyBits
+    pub fn read_rice(&mut self, k: usize) -> Result<u64> {
+        if k > 64 {
+            return Err(BitError::TooManyBits {
+                requested: k,
+                max: 64,
+            });
+        }
+        self.try_read_with(|stream| {
+            let quotient = stream.read_unary()?;
+            let remainder = stream.read_serial(k)?;
+            u64::try_from(((quotient as u128) << k) | remainder).map_err(|_| overflow())
+        })
+    }
+
+    /// Read a Golomb coded integer with parameter `m`
+    ///
+    /// The quotient is read as unary code, followed by the remainder as truncated binary code.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::VarIntOverflow`]: the encoded integer doesn't fit in a `u64`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `m` is `0`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b0110_1110];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_golomb(3)?, 4);
+    /// assert_eq!(stream.read_golomb(3)?, 2);
+    /// assert_eq!(stream.pos(), 7);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::VarIntOverflow`]: enum.ReadError.html#variant.VarIntOverflow
+    pub fn read_golomb(&mut self, m: u64) -> Result<u64> {
+        assert!(m > 0, "golomb parameter must be larger than 0");
+        self.try_read_with(|stream| {
+            let quotient = stream.read_unary()?;
+            let (bits, cutoff) = golomb_remainder_bits(m);
+            let remainder = if bits == 0 {
+                0
+            } else {
+                let short = stream.read_serial(bits - 1)? as u64;
+                if short < cutoff {
+                    short
+                } else {
+                    // the extra bit is read separately so the short form is a prefix of the long form
+                    // regardless of the endianness
+                    ((short << 1) | stream.read_bool()? as u64) - cutoff
+                }
+            };
+            quotient
+                .checked_mul(m)
+                .and_then(|value| value.checked_add(remainder))
+                .ok_or_else(overflow)
+        })
+    }
+}
+
+/// The number of bits used for the long form of the remainder and the number of remainders encoded in the short form
+fn golomb_remainder_bits(m: u64) -> (usize, u64) {
+    let bits = 64 - (m - 1).leading_zeros() as usize;
+    let cutoff = ((1u128 << bits) - m as u128) as u64;
+    (bits, cutoff)
+}
+
+impl<'a, E: Endianness> BitWriteStream<'a, E> {
+    fn write_zeros(&mut self, count: u64) -> Result<()> {
+        let mut left = count;
+        while left > 0 {
+            let chunk = left.min(64);
+            self.write_int(0u64, chunk as usize)?;
+            left -= chunk;
+        }
+        Ok(())
+    }
+
+    /// Write a unary coded integer, see [`BitReadStream::read_unary`] for details
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_unary(3)?;
+    /// stream.write_unary(0)?;
+    /// stream.write_unary(2)?;
+    /// assert_eq!(data, [0b0001_1001]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_unary(&mut self, value: u64) -> Result<()> {
+        self.write_zeros(value)?;
+        self.write_bool(true)
+    }
+
+    /// Write the lowest `count` bits of the value in the bit order of the stream, see [`BitReadStream::read_serial`]
+    fn write_serial(&mut self, value: u128, count: usize) -> Result<()> {
+        let endianness = self.endianness();
+        if !has_mixed_order(endianness) {
+            return self.write_int(value, count);
+        }
+        let lsb_first = endianness.bit_order().is_lsb_first();
+        let mut pos = 0;
+        while pos < count {
+            let size = (count - pos).min(8);
+            let shift = if lsb_first { pos } else { count - pos - size };
+            self.write_int((value >> shift) as u8, size)?;
+            pos += size;
+        }
+        Ok(())
+    }
+
+    fn write_exp_golomb_raw(&mut self, code: u128, k: usize) -> Result<()> {
+        let raw = code + (1 << k);
+        let bits = 127 - raw.leading_zeros() as usize;
+        self.write_unary((bits - k) as u64)?;
+        self.write_serial(raw, bits)
+    }
+
+    /// Write an unsigned Exp-Golomb code of order `k`, see [`BitReadStream::read_exp_golomb`] for details
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_exp_golomb(0, 0)?;
+    /// stream.write_exp_golomb(1, 0)?;
+    /// stream.write_exp_golomb(2, 0)?;
+    /// stream.write_exp_golomb(3, 0)?;
+    /// assert_eq!(data, [0b1010_0110, 0b0100_0000]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_exp_golomb(&mut self, value: u64, k: usize) -> Result<()> {
+        if k > 64 {
+            return Err(BitError::TooManyBits {
+                requested: k,
+                max: 64,
+            });
+        }
+        self.write_exp_golomb_raw(value as u128, k)
+    }
+
+    /// Write a signed Exp-Golomb code of order `k`, see [`BitReadStream::read_signed_exp_golomb`] for details
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_signed_exp_golomb(0, 0)?;
+    /// stream.write_signed_exp_golomb(1, 0)?;
+    /// stream.write_signed_exp_golomb(-1, 0)?;
+    /// stream.write_signed_exp_golomb(2, 0)?;
+    /// assert_eq!(data, [0b1010_0110, 0b0100_0000]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_signed_exp_golomb(&mut self, value: i64, k: usize) -> Result<()> {
+        if k > 64 {
+            return Err(BitError::TooManyBits {
+                requested: k,
+                max: 64,
+            });
+        }
+        let value = value as i128;
+        let code = if value > 0 { value * 2 - 1 } else { -value * 2 };
+        self.write_exp_golomb_raw(code as u128, k)
+    }
+
+    /// Write an Elias gamma coded integer, see [`BitReadStream::read_elias_gamma`] for details
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// # use std::num::NonZeroU64;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_elias_gamma(NonZeroU64::new(6).unwrap())?;
+    /// assert_eq!(data, [0b0011_0000]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_elias_gamma(&mut self, value: NonZeroU64) -> Result<()> {
+        self.write_exp_golomb_raw(value.get() as u128 - 1, 0)
+    }
+
+    /// Write an Elias delta coded integer, see [`BitReadStream::read_elias_delta`] for details
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// # use std::num::NonZeroU64;
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_elias_delta(NonZeroU64::new(5).unwrap())?;
+    /// assert_eq!(data, [0b0110_1000]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_elias_delta(&mut self, value: NonZeroU64) -> Result<()> {
+        let bits = 63 - value.leading_zeros() as usize;
+        self.write_elias_gamma(NonZeroU64::new(bits as u64 + 1).unwrap())?;
+        self.write_serial(value.get() as u128, bits)
+    }
+
+    /// Write a Rice coded integer with parameter `k`, see [`BitReadStream::read_rice`] for details
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_rice(11, 2)?;
+    /// stream.write_rice(1, 2)?;
+    /// assert_eq!(data, [0b0011_1101]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_rice(&mut self, value: u64, k: usize) -> Result<()> {
+        if k > 64 {
+            return Err(BitError::TooManyBits {
+                requested: k,
+                max: 64,
+            });
+        }
+        self.write_unary(((value as u128) >> k) as u64)?;
+        self.write_serial(value as u128, k)
+    }
+
+    /// Write a Golomb coded integer with parameter `m`, see [`BitReadStream::read_golomb`] for details
+    ///
+    /// # Panics
+    ///
+    /// Panics if `m` is `0`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_golomb(4, 3)?;
+    /// stream.write_golomb(2, 3)?;
+    /// assert_eq!(data, [0b0110_1110]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_golomb(&mut self, value: u64, m: u64) -> Result<()> {
+        assert!(m > 0, "golomb parameter must be larger than 0");
+        self.write_unary(value / m)?;
+        let remainder = value % m;
+        let (bits, cutoff) = golomb_remainder_bits(m);
+        if bits == 0 {
+            Ok(())
+        } else if remainder < cutoff {
+            self.write_serial(remainder as u128, bits - 1)
+        } else {
+            let long = remainder + cutoff;
+            self.write_serial((long >> 1) as u128, bits - 1)?;
+            self.write_bool(long & 1 == 1)
+        }
+    }
+}
+
+/// Integer that is read and written as unary code
+///
+/// See [`BitReadStream::read_unary`] for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct Unary(pub u64);
+
+impl<'a, E: Endianness> BitRead<'a, E> for Unary {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_unary().map(Unary)
+    }
+}
+
+impl<E: Endianness> BitWrite<E> for Unary {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_unary(self.0)
+    }
+}
+
+/// Integer that is read and written as unsigned Exp-Golomb code of order `K`
+///
+/// See [`BitReadStream::read_exp_golomb`] for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct ExpGolomb<const K: usize = 0>(pub u64);
+
+impl<'a, E: Endianness, const K: usize> BitRead<'a, E> for ExpGolomb<K> {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_exp_golomb(K).map(ExpGolomb)
+    }
+}
+
+impl<E: Endianness, const K: usize> BitWrite<E> for ExpGolomb<K> {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_exp_golomb(self.0, K)
+    }
+}
+
+/// Integer that is read and written as signed Exp-Golomb code of order `K`
+///
+/// See [`BitReadStream::read_signed_exp_golomb`] for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct SignedExpGolomb<const K: usize = 0>(pub i64);
+
+impl<'a, E: Endianness, const K: usize> BitRead<'a, E> for SignedExpGolomb<K> {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_signed_exp_golomb(K).map(SignedExpGolomb)
+    }
+}
+
+impl<E: Endianness, const K: usize> BitWrite<E> for SignedExpGolomb<K> {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_signed_exp_golomb(self.0, K)
+    }
+}
+
+/// Integer that is read and written as Elias gamma code
+///
+/// See [`BitReadStream::read_elias_gamma`] for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EliasGamma(pub NonZeroU64);
+
+impl<'a, E: Endianness> BitRead<'a, E> for EliasGamma {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_elias_gamma().map(EliasGamma)
+    }
+}
+
+impl<E: Endianness> BitWrite<E> for EliasGamma {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_elias_gamma(self.0)
+    }
+}
+
+/// Integer that is read and written as Elias delta code
+///
+/// See [`BitReadStream::read_elias_delta`] for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EliasDelta(pub NonZeroU64);
+
+impl<'a, E: Endianness> BitRead<'a, E> for EliasDelta {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_elias_delta().map(EliasDelta)
+    }
+}
+
+impl<E: Endianness> BitWrite<E> for EliasDelta {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_elias_delta(self.0)
+    }
+}
+
+/// Integer that is read and written as Rice code with parameter `K`
+///
+/// See [`BitReadStream::read_rice`] for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct Rice<const K: usize>(pub u64);
+
+impl<'a, E: Endianness, const K: usize> BitRead<'a, E> for Rice<K> {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_rice(K).map(Rice)
+    }
+}
+
+impl<E: Endianness, const K: usize> BitWrite<E> for Rice<K> {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_rice(self.0, K)
+    }
+}
+
+/// Integer that is read and written as Golomb code with parameter `M`
+///
+/// See [`BitReadStream::read_golomb`] for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct Golomb<const M: u64>(pub u64);
+
+impl<'a, E: Endianness, const M: u64> BitRead<'a, E> for Golomb<M> {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_golomb(M).map(Golomb)
+    }
+}
+
+impl<E: Endianness, const M: u64> BitWrite<E> for Golomb<M> {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_golomb(self.0, M)
+    }
+}
+
+// the number of bits for all codes depends on the encoded value
+impl BitSize for Unary {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl<const K: usize> BitSize for ExpGolomb<K> {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl<const K: usize> BitSize for SignedExpGolomb<K> {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl BitSize for EliasGamma {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl BitSize for EliasDelta {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl<const K: usize> BitSize for Rice<K> {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl<const M: u64> BitSize for Golomb<M> {
+    const BIT_SIZE: Option<usize> = None;
+}
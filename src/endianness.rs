@@ -1,8 +1,38 @@
+use num_traits::PrimInt;
+use std::cmp::min;
+use std::fmt::Debug;
+
 /// Trait for specifying endianness of bit buffer
-pub trait Endianness: private::Sealed {
+///
+/// The endianness is stored as a value in the buffers and streams, for the zero sized [`LittleEndian`]
+/// and [`BigEndian`] the byte order is known at compile time, while [`DynamicEndian`] allows picking
+/// the byte order at runtime.
+///
+/// Besides the byte order of integers, the endianness determines the [`BitOrder`] within a byte.
+/// [`LittleEndian`] reads the least significant bit of a byte first and [`BigEndian`] the most significant bit,
+/// for protocols that combine the byte order of one with the bit order of the other
+/// [`LittleEndianMsbFirst`] and [`BigEndianLsbFirst`] can be used.
+///
+/// With these mixed orders the bytes of integers of more than 8 bits are reordered, this applies to
+/// `read_int` and `write_int` of the buffers and streams, floats, the reserved bits of `reserve_length`
+/// and `reserve_int`, `read_ubit_var` and `write_ubit_var`, and everything built on top of them like
+/// [`UInt`](crate::UInt), [`SInt`](crate::SInt) and [`MiniFloat`](crate::minifloat::MiniFloat) values
+/// and fields with a `size` attribute.
+/// Booleans, bytes, strings, LEB128 var ints and the [universal codes](crate::codes) are read and written
+/// in the bit order of the stream without any reordering.
+///
+/// [`DynamicEndian`] only covers the byte orders of [`LittleEndian`] and [`BigEndian`] with their own bit orders,
+/// the mixed orders can't be picked at runtime.
+///
+/// # Compatibility
+///
+/// Since 0.11 the methods of this trait take `&self` instead of being associated functions,
+/// code calling `E::is_le()` needs to call `endianness.is_le()` on the endianness of the buffer or stream instead.
+/// The same applies to the functions in [`num_traits`](crate::num_traits) that now take the endianness as argument.
+pub trait Endianness: private::Sealed + Copy + Debug {
     /// Get the endianness as string, either LittleEndian or BigEndian
-    fn as_string() -> &'static str {
-        if Self::is_le() {
+    fn as_string(&self) -> &'static str {
+        if self.is_le() {
             "LittleEndian"
         } else {
             "BigEndian"
@@ -10,13 +40,80 @@ pub trait Endianness: private::Sealed {
     }
 
     /// Input is little endian
-    fn is_le() -> bool;
+    fn is_le(&self) -> bool;
     /// Input is big endian
-    fn is_be() -> bool;
+    fn is_be(&self) -> bool;
+    /// Get the order of the bits within a byte
+    ///
+    /// Unless overwritten, little endian input is read least significant bit first
+    /// and big endian input most significant bit first
+    #[inline(always)]
+    fn bit_order(&self) -> BitOrder {
+        if self.is_le() {
+            BitOrder::LsbFirst
+        } else {
+            BitOrder::MsbFirst
+        }
+    }
     /// Get an instance of the endianness
+    ///
+    /// For [`DynamicEndian`] this returns the native endianness of the target
     fn endianness() -> Self;
 }
 
+/// Endianness that is fully known at compile time
+///
+/// Only these endiannesses can be used when a buffer or stream is created without passing the endianness,
+/// like `BitReadBuffer::from(bytes)`, since there is no byte order to pick for [`DynamicEndian`].
+pub trait StaticEndianness: Endianness {}
+
+/// The order of the bits within a byte
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitOrder {
+    /// The least significant bit of a byte comes first
+    LsbFirst,
+    /// The most significant bit of a byte comes first
+    MsbFirst,
+}
+
+impl BitOrder {
+    /// The least significant bit of a byte comes first
+    #[inline(always)]
+    pub fn is_lsb_first(self) -> bool {
+        self == BitOrder::LsbFirst
+    }
+}
+
+/// Check if the bit order doesn't match the byte order
+///
+/// For these endiannesses the bytes of an integer have to be reordered, since the bits of the stream don't
+/// form the integer in a single run from least to most significant or the other way around.
+#[inline(always)]
+pub(crate) fn has_mixed_order<E: Endianness>(endianness: E) -> bool {
+    endianness.is_le() != endianness.bit_order().is_lsb_first()
+}
+
+/// Reverse the order of the bytes of a `count` bit integer
+///
+/// The bytes are taken starting from the least significant bit, when `short_first` is set the first byte holds the
+/// remaining bits if `count` isn't a multiple of 8, otherwise the last byte does.
+pub(crate) fn swap_bytes<T: PrimInt>(value: T, count: usize, short_first: bool) -> T {
+    let mut result = T::zero();
+    let mut pos = 0;
+    while pos < count {
+        let size = if short_first && pos == 0 {
+            count - (count - 1) / 8 * 8
+        } else {
+            min(8, count - pos)
+        };
+        let byte = (value >> pos) & ((T::one() << size) - T::one());
+        result = result | byte << (count - pos - size);
+        pos += size;
+    }
+    result
+}
+
 /// Marks the buffer or stream as big endian
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -27,34 +124,218 @@ pub struct BigEndian;
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LittleEndian;
 
+/// Marks the buffer or stream as little endian, with the most significant bit of every byte first
+///
+/// Integers are split into bytes starting from the least significant bit, the last byte holds the remaining
+/// bits when the number of bits isn't a multiple of 8. The bytes are ordered least significant byte first
+/// and the bits within each byte most significant bit first.
+///
+/// See [`Endianness`] for the values that are reordered this way.
+///
+/// # Examples
+///
+/// ```
+/// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndianMsbFirst, Result};
+/// #
+/// # fn main() -> Result<()> {
+/// let bytes = [0b1010_0000, 0x34, 0x12];
+/// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndianMsbFirst));
+/// assert_eq!(stream.read_int::<u8>(3)?, 0b101);
+/// stream.skip_bits(5)?;
+/// assert_eq!(stream.read_int::<u16>(16)?, 0x1234);
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LittleEndianMsbFirst;
+
+/// Marks the buffer or stream as big endian, with the least significant bit of every byte first
+///
+/// Integers are split into bytes starting from the least significant bit, the first byte holds the remaining
+/// bits when the number of bits isn't a multiple of 8. The bytes are ordered most significant byte first
+/// and the bits within each byte least significant bit first.
+///
+/// See [`Endianness`] for the values that are reordered this way.
+///
+/// # Examples
+///
+/// ```
+/// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndianLsbFirst, Result};
+/// #
+/// # fn main() -> Result<()> {
+/// let bytes = [0b0000_0101, 0x12, 0x34];
+/// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndianLsbFirst));
+/// assert_eq!(stream.read_int::<u8>(3)?, 0b101);
+/// stream.skip_bits(5)?;
+/// assert_eq!(stream.read_int::<u16>(16)?, 0x1234);
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BigEndianLsbFirst;
+
 macro_rules! impl_endianness {
     ($type:ty, $le:expr, $instance:expr) => {
         impl Endianness for $type {
             #[inline(always)]
-            fn is_le() -> bool {
+            fn is_le(&self) -> bool {
+                $le
+            }
+
+            #[inline(always)]
+            fn is_be(&self) -> bool {
+                !$le
+            }
+
+            fn endianness() -> Self {
+                $instance
+            }
+        }
+
+        impl StaticEndianness for $type {}
+    };
+    ($type:ty, $le:expr, $instance:expr, $bit_order:expr, $name:expr) => {
+        impl Endianness for $type {
+            fn as_string(&self) -> &'static str {
+                $name
+            }
+
+            #[inline(always)]
+            fn is_le(&self) -> bool {
                 $le
             }
 
             #[inline(always)]
-            fn is_be() -> bool {
+            fn is_be(&self) -> bool {
                 !$le
             }
 
+            #[inline(always)]
+            fn bit_order(&self) -> BitOrder {
+                $bit_order
+            }
+
             fn endianness() -> Self {
                 $instance
             }
         }
+
+        impl StaticEndianness for $type {}
     };
 }
 
 impl_endianness!(BigEndian, false, BigEndian);
 impl_endianness!(LittleEndian, true, LittleEndian);
+impl_endianness!(
+    LittleEndianMsbFirst,
+    true,
+    LittleEndianMsbFirst,
+    BitOrder::MsbFirst,
+    "LittleEndianMsbFirst"
+);
+impl_endianness!(
+    BigEndianLsbFirst,
+    false,
+    BigEndianLsbFirst,
+    BitOrder::LsbFirst,
+    "BigEndianLsbFirst"
+);
+
+/// Marks the buffer or stream as having an endianness that is only known at runtime
+///
+/// The bit order follows the byte order like for [`LittleEndian`] and [`BigEndian`],
+/// there is no runtime equivalent of [`LittleEndianMsbFirst`] and [`BigEndianLsbFirst`].
+///
+/// # Examples
+///
+/// ```
+/// # use bitbuffer::{BitReadBuffer, BitReadStream, DynamicEndian, Result};
+/// #
+/// # fn main() -> Result<()> {
+/// let bytes = [b'M', b'M', 0x00, 0x2A];
+/// let endianness = match &bytes[0..2] {
+///     b"II" => DynamicEndian::Little,
+///     _ => DynamicEndian::Big,
+/// };
+/// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, endianness));
+/// stream.skip_bits(16)?;
+/// assert_eq!(stream.read_int::<u16>(16)?, 42);
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DynamicEndian {
+    /// Little endian byte order
+    Little,
+    /// Big endian byte order
+    Big,
+}
+
+impl DynamicEndian {
+    /// The endianness of the target platform
+    pub fn native() -> Self {
+        if cfg!(target_endian = "little") {
+            DynamicEndian::Little
+        } else {
+            DynamicEndian::Big
+        }
+    }
+}
+
+impl Endianness for DynamicEndian {
+    #[inline(always)]
+    fn is_le(&self) -> bool {
+        *self == DynamicEndian::Little
+    }
 
-mod private {
-    pub trait Sealed {}
+    #[inline(always)]
+    fn is_be(&self) -> bool {
+        *self == DynamicEndian::Big
+    }
+
+    fn endianness() -> Self {
+        DynamicEndian::native()
+    }
+}
+
+impl From<LittleEndian> for DynamicEndian {
+    fn from(_: LittleEndian) -> Self {
+        DynamicEndian::Little
+    }
+}
+
+impl From<BigEndian> for DynamicEndian {
+    fn from(_: BigEndian) -> Self {
+        DynamicEndian::Big
+    }
+}
+
+pub(crate) mod private {
+    pub trait Sealed {
+        /// Whether the endianness is known from the type alone and doesn't have to be stored
+        fn is_static() -> bool {
+            true
+        }
+    }
 
     // Implement for those same types, but no others.
     impl Sealed for super::BigEndian {}
 
     impl Sealed for super::LittleEndian {}
+
+    impl Sealed for super::LittleEndianMsbFirst {}
+
+    impl Sealed for super::BigEndianLsbFirst {}
+
+    impl Sealed for super::DynamicEndian {
+        fn is_static() -> bool {
+            false
+        }
+    }
 }
@@ -0,0 +1,381 @@
+use std::io::{ErrorKind, Read};
+use std::ops::BitOrAssign;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use num_traits::{Float, PrimInt};
+
+use crate::endianness::Endianness;
+use crate::num_traits::{IsSigned, UncheckedPrimitiveFloat, UncheckedPrimitiveInt};
+use crate::{BitError, BitRead, BitReadBuffer, BitReadSized, BitReadStream, ReadLimits, Result};
+
+const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Stream that reads bits from any [`Read`] source without loading the entire input in memory
+///
+/// Data is pulled from the reader in chunks as needed, reads that don't fit in the currently
+/// buffered data will fetch more data and retry, a [`BitError::NotEnoughData`] for running out of input
+/// is only returned once the reader reached the end of the input. Errors that don't depend on where the
+/// input ends, like reading past the end of a sub stream created with [`BitReadStream::read_bits`],
+/// are returned right away.
+///
+/// Reads that depend on where the input ends, like [`BitReadStream::bits_left`], [`BitReadStream::read_until_eof`]
+/// or null terminated strings without a terminator in the buffered data, are retried as well.
+/// Since the buffered data at least doubles with every retry, these reads will load the rest
+/// of the input in memory, but the total amount of work stays linear in the size of the input.
+///
+/// Since the buffered data is discarded as the stream advances, only types that don't borrow from the
+/// stream can be read, reading bytes and strings always returns owned data.
+///
+/// # Examples
+///
+/// ```
+/// use bitbuffer::{BitRead, BitReadIoStream, LittleEndian};
+/// # use bitbuffer::Result;
+///
+/// #[derive(BitRead, Debug, PartialEq)]
+/// struct Entry {
+///     #[size = 4]
+///     kind: u8,
+///     name: String,
+/// }
+///
+/// # fn main() -> Result<()> {
+/// let input: &[u8] = &[0x03, b'f', b'o', b'o', 0x00, 0x00];
+/// let mut stream = BitReadIoStream::new(input, LittleEndian);
+/// assert_eq!(stream.read::<bool>()?, true);
+/// stream.skip_bits(3)?;
+/// let entry: Entry = stream.read()?;
+/// assert_eq!(entry, Entry { kind: 0, name: "foo".to_string() });
+/// assert_eq!(stream.pos(), 40);
+/// assert!(stream.read::<u16>().is_err());
+/// #
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BitReadIoStream<R, E>
+where
+    R: Read,
+    E: Endianness,
+{
+    reader: R,
+    endianness: E,
+    buffer: Vec<u8>,
+    chunk_size: usize,
+    // bit position inside the buffered data
+    pos: usize,
+    // number of bits that have been discarded from the start of the buffer
+    offset: usize,
+    eof: bool,
+    limits: ReadLimits,
+    allocated: Arc<AtomicUsize>,
+    end_observed: Arc<AtomicBool>,
+}
+
+impl<R, E> BitReadIoStream<R, E>
+where
+    R: Read,
+    E: Endianness,
+{
+    /// Create a new stream reading from a [`Read`] source
+    pub fn new(reader: R, endianness: E) -> Self {
+        Self::with_chunk_size(reader, DEFAULT_CHUNK_SIZE, endianness)
+    }
+
+    /// Create a new stream reading from a [`Read`] source, pulling at least `chunk_size` bytes
+    /// from the reader at a time
+    pub fn with_chunk_size(reader: R, chunk_size: usize, endianness: E) -> Self {
+        BitReadIoStream {
+            reader,
+            endianness,
+            buffer: Vec::new(),
+            chunk_size: chunk_size.max(1),
+            pos: 0,
+            offset: 0,
+            eof: false,
+            limits: ReadLimits::UNLIMITED,
+            allocated: Arc::new(AtomicUsize::new(0)),
+            end_observed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Get the number of bits read from the stream
+    pub fn pos(&self) -> usize {
+        self.offset + self.pos
+    }
+
+    /// Get the endianness of the stream
+    pub fn endianness(&self) -> E {
+        self.endianness
+    }
+
+    /// Set the limits for reading untrusted input
+    ///
+    /// See [`ReadLimits`] for details.
+    pub fn set_limits(&mut self, limits: ReadLimits) {
+        self.limits = limits;
+    }
+
+    /// Get the limits for reading untrusted input
+    pub fn limits(&self) -> ReadLimits {
+        self.limits
+    }
+
+    /// Check if the end of the input has been reached
+    ///
+    /// This will fetch more data from the reader if no more data is buffered
+    pub fn is_at_end(&mut self) -> Result<bool> {
+        while self.pos >= self.buffer.len() * 8 {
+            if self.eof {
+                return Ok(true);
+            }
+            self.fill()?;
+        }
+        Ok(false)
+    }
+
+    /// Discard the consumed bytes and pull more data from the reader
+    fn fill(&mut self) -> Result<()> {
+        let consumed = self.pos / 8;
+        self.buffer.drain(0..consumed);
+        self.pos -= consumed * 8;
+        self.offset += consumed * 8;
+
+        // grow at least as fast as the buffer to keep the retries for large reads limited
+        let wanted = self.chunk_size.max(self.buffer.len());
+        let start = self.buffer.len();
+        self.buffer.resize(start + wanted, 0);
+        let mut filled = start;
+        while filled < self.buffer.len() {
+            match self.reader.read(&mut self.buffer[filled..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.buffer.truncate(filled);
+                    return Err(e.into());
+                }
+            }
+        }
+        self.buffer.truncate(filled);
+        Ok(())
+    }
+
+    /// Run a read against the buffered data, pulling more data from the reader when the read
+    /// runs out of buffered data or depends on where the buffered data ends
+    fn read_with<T, F>(&mut self, mut read: F) -> Result<T>
+    where
+        F: FnMut(&mut BitReadStream<'_, E>) -> Result<T>,
+    {
+        loop {
+            let mut stream = BitReadStream::new(BitReadBuffer::new(&self.buffer, self.endianness));
+            stream.set_pos(self.pos)?;
+            stream.set_limits(self.limits);
+            // allocations of failed or retried reads aren't counted
+            let allocated = self.allocated.load(Ordering::Relaxed);
+            stream.set_allocated(self.allocated.clone());
+            // the end of the buffer is only the end of the input once the reader is exhausted
+            if !self.eof {
+                self.end_observed.store(false, Ordering::Relaxed);
+                stream.set_end_observed(self.end_observed.clone());
+            }
+            let result = read(&mut stream);
+            // errors that didn't run into the end of the buffered data, like reading past the end of a sub stream,
+            // won't go away by buffering more data
+            let retry = !self.eof && self.end_observed.load(Ordering::Relaxed);
+            if result.is_err() || retry {
+                self.allocated.store(allocated, Ordering::Relaxed);
+            }
+            if retry {
+                self.fill()?;
+                continue;
+            }
+            return match result {
+                Ok(result) => {
+                    self.pos = stream.pos();
+                    Ok(result)
+                }
+                Err(e) => Err(e.offset_pos(self.offset)),
+            };
+        }
+    }
+
+    /// Read a single bit from the stream as boolean
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the input
+    /// - [`ReadError::Io`]: an error occurred while reading from the reader
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::Io`]: enum.ReadError.html#variant.Io
+    pub fn read_bool(&mut self) -> Result<bool> {
+        self.read_with(|stream| stream.read_bool())
+    }
+
+    /// Read a sequence of bits from the stream as integer
+    ///
+    /// See [`BitReadStream::read_int`] for details
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the input
+    /// - [`ReadError::TooManyBits`]: to many bits requested for the chosen integer type
+    /// - [`ReadError::Io`]: an error occurred while reading from the reader
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadIoStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let input: &[u8] = &[0b1011_0101, 0b0110_1010];
+    /// let mut stream = BitReadIoStream::new(input, BigEndian);
+    /// assert_eq!(stream.read_int::<u8>(3)?, 0b101);
+    /// assert_eq!(stream.read_int::<u16>(13)?, 0b1_0101_0110_1010);
+    /// assert!(stream.read_int::<u8>(1).is_err());
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::TooManyBits`]: enum.ReadError.html#variant.TooManyBits
+    /// [`ReadError::Io`]: enum.ReadError.html#variant.Io
+    pub fn read_int<T>(&mut self, count: usize) -> Result<T>
+    where
+        T: PrimInt + BitOrAssign + IsSigned + UncheckedPrimitiveInt,
+    {
+        self.read_with(|stream| stream.read_int(count))
+    }
+
+    /// Read a sequence of bits from the stream as float
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the input
+    /// - [`ReadError::Io`]: an error occurred while reading from the reader
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::Io`]: enum.ReadError.html#variant.Io
+    pub fn read_float<T>(&mut self) -> Result<T>
+    where
+        T: Float + UncheckedPrimitiveFloat,
+    {
+        self.read_with(|stream| stream.read_float())
+    }
+
+    /// Read a series of bytes from the stream
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the input
+    /// - [`ReadError::Io`]: an error occurred while reading from the reader
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::Io`]: enum.ReadError.html#variant.Io
+    pub fn read_bytes(&mut self, byte_count: usize) -> Result<Vec<u8>> {
+        self.read_with(|stream| {
+            stream
+                .read_bytes(byte_count)
+                .map(|bytes| bytes.into_owned())
+        })
+    }
+
+    /// Read a series of bytes from the stream as utf8 string
+    ///
+    /// You can either read a fixed number of bytes, or a dynamic length null-terminated string
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the input
+    /// - [`ReadError::Utf8Error`]: the read bytes are not valid utf8
+    /// - [`ReadError::Io`]: an error occurred while reading from the reader
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::Utf8Error`]: enum.ReadError.html#variant.Utf8Error
+    /// [`ReadError::Io`]: enum.ReadError.html#variant.Io
+    pub fn read_string(&mut self, byte_len: Option<usize>) -> Result<String> {
+        self.read_with(|stream| {
+            stream
+                .read_string(byte_len)
+                .map(|string| string.into_owned())
+        })
+    }
+
+    /// Skip a number of bits in the stream
+    ///
+    /// Skipped data is discarded without being buffered
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the input, the stream will
+    ///   be positioned at the end of the input
+    /// - [`ReadError::Io`]: an error occurred while reading from the reader
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::Io`]: enum.ReadError.html#variant.Io
+    pub fn skip_bits(&mut self, count: usize) -> Result<()> {
+        let mut remaining = count;
+        loop {
+            let available = self.buffer.len() * 8 - self.pos;
+            if remaining <= available {
+                self.pos += remaining;
+                return Ok(());
+            }
+            if self.eof {
+                self.pos += available;
+                return Err(BitError::NotEnoughData {
+                    requested: count,
+                    bits_left: count - remaining + available,
+                });
+            }
+            remaining -= available;
+            self.pos += available;
+            self.fill()?;
+        }
+    }
+
+    /// Read a value based on the provided type
+    ///
+    /// Any type implementing [`BitRead`] that doesn't borrow from the stream can be read,
+    /// including types with a derived implementation.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the input
+    /// - [`ReadError::Io`]: an error occurred while reading from the reader
+    /// - any error returned by the [`BitRead`] implementation of the type
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::Io`]: enum.ReadError.html#variant.Io
+    pub fn read<T>(&mut self) -> Result<T>
+    where
+        T: for<'b> BitRead<'b, E>,
+    {
+        self.read_with(|stream| stream.read::<T>())
+    }
+
+    /// Read a value based on the provided type and size
+    ///
+    /// The meaning of the size parameter differs depending on the type that is being read
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the input
+    /// - [`ReadError::Io`]: an error occurred while reading from the reader
+    /// - any error returned by the [`BitReadSized`] implementation of the type
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::Io`]: enum.ReadError.html#variant.Io
+    pub fn read_sized<T>(&mut self, size: usize) -> Result<T>
+    where
+        T: for<'b> BitReadSized<'b, E>,
+    {
+        self.read_with(|stream| stream.read_sized::<T>(size))
+    }
+}
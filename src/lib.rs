@@ -78,21 +78,37 @@
 use err_derive::Error;
 
 pub use bitbuffer_derive::{BitRead, BitReadSized, BitWrite, BitWriteSized};
+pub use bitint::{SInt, UInt};
+#[doc(hidden)]
+pub use bitsize::{add_bit_sizes, mul_bit_size};
+pub use bitsize::{BitSize, BitSizeSized};
 pub use endianness::*;
+pub use ioreadstream::BitReadIoStream;
+pub use limits::{LimitKind, ReadLimits};
 pub use read::{BitRead, BitReadSized, LazyBitRead, LazyBitReadSized};
 pub use readbuffer::BitReadBuffer;
 pub use readstream::BitReadStream;
+use std::fmt::{Display, Formatter};
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
+pub use varint::{UBitVar, VarInt};
 pub use write::{BitWrite, BitWriteSized};
 pub use writestream::BitWriteStream;
 
+mod bitint;
+mod bitsize;
+pub mod codes;
 mod endianness;
+mod ioreadstream;
+mod limits;
+pub mod minifloat;
 #[allow(missing_docs)]
 pub mod num_traits;
+pub mod quantized;
 mod read;
 mod readbuffer;
 mod readstream;
+mod varint;
 mod write;
 mod writebuffer;
 mod writestream;
@@ -148,6 +164,18 @@ pub enum BitError {
         /// The name of the enum that is trying to be read
         enum_name: String,
     },
+    /// The discriminant captured by a wildcard variant belongs to another variant of the enum that is being written
+    #[error(
+        display = "The captured discriminant '{}' belongs to another variant of enum '{}'",
+        discriminant,
+        enum_name
+    )]
+    DiscriminantConflict {
+        /// The captured discriminant
+        discriminant: usize,
+        /// The name of the enum that is being written
+        enum_name: String,
+    },
     /// The read slice of bytes are not valid utf8
     #[error(display = "The read slice of bytes are not valid utf8: {}", _0)]
     Utf8Error(Utf8Error, usize),
@@ -163,6 +191,269 @@ pub enum BitError {
         /// The requested fixed size to encode the string into
         requested_length: usize,
     },
+    /// The number of items in a collection that was requested to be written doesn't match the specified length
+    #[error(
+        display = "The collection that was requested to be written contains {} items, while a length of {} has been specified",
+        length,
+        requested_length
+    )]
+    LengthMismatch {
+        /// Number of items in the collection that was requested to be written
+        length: usize,
+        /// The requested number of items
+        requested_length: usize,
+    },
+    /// An item of the collection that was requested to be written is equal to the terminator of the collection
+    #[error(
+        display = "The item at index {} of the collection that was requested to be written is equal to the terminator",
+        index
+    )]
+    TerminatorInItems {
+        /// The index of the item that is equal to the terminator
+        index: usize,
+    },
+    /// The integer that was requested to be written doesn't fit in the requested number of bits
+    #[error(display = "The value {} doesn't fit in {} bits", value, bits)]
+    ValueTooLarge {
+        /// The value that was requested to be written
+        value: String,
+        /// The number of bits the value was requested to be written with
+        bits: usize,
+    },
+    /// Not enough space left in the buffer to write all requested bits
+    #[error(
+        display = "Not enough space left in the buffer to write all requested bits, requested to write {} bits while only {} bits are left",
+        requested,
+        bits_left
+    )]
+    NotEnoughSpace {
+        /// The number of bits requested to write
+        requested: usize,
+        /// The number of bits left in the buffer
+        bits_left: usize,
+    },
+    /// The variable length integer read from the buffer does not fit in the requested data type
+    #[error(
+        display = "The variable length integer read from the buffer does not fit in the requested data type, only {} bits fit in the datatype",
+        max
+    )]
+    VarIntOverflow {
+        /// The number of bits that fit in the requested data type
+        max: usize,
+    },
+    /// The padding bits skipped while aligning the stream don't match the expected padding
+    #[error(
+        display = "The {} padding bits at position {} don't match the expected padding",
+        bits,
+        pos
+    )]
+    InvalidPadding {
+        /// The position of the padding in the stream
+        pos: usize,
+        /// The number of padding bits
+        bits: usize,
+    },
+    /// An error occurred while reading from or writing to the underlying io stream
+    #[error(display = "Error while accessing the underlying io stream: {}", _0)]
+    Io(#[error(source)] std::io::Error),
+    /// The magic value read from the stream doesn't match the expected value
+    #[error(
+        display = "Invalid magic value, expected {} but found {}",
+        expected,
+        found
+    )]
+    InvalidMagic {
+        /// The expected magic value
+        expected: String,
+        /// The value read from the stream
+        found: String,
+    },
+    /// The value read for a field doesn't pass the assertion for the field
+    #[error(
+        display = "The value of the field '{}' doesn't pass the assertion '{}'",
+        field,
+        assertion
+    )]
+    AssertionFailed {
+        /// The name of the field
+        field: String,
+        /// The assertion for the field
+        assertion: String,
+    },
+    /// The presence of an optional field doesn't match the condition for the field
+    #[error(
+        display = "The presence of the optional field '{}' doesn't match its condition '{}'",
+        field,
+        condition
+    )]
+    OptionalFieldMismatch {
+        /// The name of the field
+        field: String,
+        /// The condition for the field
+        condition: String,
+    },
+    /// A value with the endianness set by the `le` or `be` attributes doesn't start at a byte boundary
+    #[error(
+        display = "Values with #[le] or #[be] have to start at a byte boundary, but the value starts at bit {}",
+        pos
+    )]
+    UnalignedEndianness {
+        /// The position of the value in the stream
+        pos: usize,
+    },
+    /// A configured read limit was exceeded
+    #[error(
+        display = "The {} of {} exceeds the configured limit of {}",
+        limit,
+        requested,
+        max
+    )]
+    LimitExceeded {
+        /// The limit that was exceeded
+        limit: LimitKind,
+        /// The requested length or allocation
+        requested: usize,
+        /// The configured limit
+        max: usize,
+    },
+    /// An error occurred while reading a field of a type
+    ///
+    /// Errors from derived [`BitRead`] implementations and collections are wrapped in this variant,
+    /// use [`BitError::root`] to match on the underlying error.
+    #[error(display = "{} at bit {}: {}", path, pos, source)]
+    Context {
+        /// The path to the field that failed to read
+        path: FieldPath,
+        /// The position in the stream where the innermost field that failed to read started
+        pos: usize,
+        /// The error that occurred while reading the field
+        #[error(source)]
+        source: Box<BitError>,
+    },
+}
+
+impl BitError {
+    /// Add a step to the path of the field that failed to read
+    ///
+    /// The position is only recorded for the innermost field, since that is where the error occurred.
+    pub fn with_context(self, segment: PathSegment, pos: usize) -> BitError {
+        match self {
+            BitError::Context {
+                mut path,
+                pos,
+                source,
+            } => {
+                path.segments.insert(0, segment);
+                BitError::Context { path, pos, source }
+            }
+            err => BitError::Context {
+                path: FieldPath {
+                    segments: vec![segment],
+                },
+                pos,
+                source: Box::new(err),
+            },
+        }
+    }
+
+    /// Get the underlying error, without any field path context
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitRead, BitReadBuffer, BitReadStream, BitError, LittleEndian};
+    /// #
+    /// #[derive(BitRead, Debug)]
+    /// struct Vector {
+    ///     x: f32,
+    ///     y: f32,
+    /// }
+    ///
+    /// #[derive(BitRead, Debug)]
+    /// struct Entity {
+    ///     id: u8,
+    ///     origin: Vector,
+    /// }
+    ///
+    /// let bytes = vec![0; 6];
+    /// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    /// let err = stream.read::<Entity>().unwrap_err();
+    /// assert_eq!(
+    ///     "Entity.origin.y at bit 40: Not enough data in the buffer to read all requested bits, requested to read 32 bits while only 8 bits are left",
+    ///     err.to_string()
+    /// );
+    /// assert!(matches!(err.root(), BitError::NotEnoughData { .. }));
+    /// ```
+    pub fn root(&self) -> &BitError {
+        match self {
+            BitError::Context { source, .. } => source.root(),
+            err => err,
+        }
+    }
+
+    /// Adjust the position of the failed field for data that was read at an offset
+    pub(crate) fn offset_pos(self, offset: usize) -> BitError {
+        match self {
+            BitError::Context { path, pos, source } => BitError::Context {
+                path,
+                pos: pos + offset,
+                source,
+            },
+            err => err,
+        }
+    }
+}
+
+/// The path to a field that failed to read, e.g. `Packet.entities[3].origin.x`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPath {
+    segments: Vec<PathSegment>,
+}
+
+impl FieldPath {
+    /// The steps in the path, starting from the outermost type
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+}
+
+impl Display for FieldPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(PathSegment::Field { type_name, .. } | PathSegment::Variant { type_name, .. }) =
+            self.segments.first()
+        {
+            write!(f, "{}", type_name)?
+        }
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Field { field, .. } => write!(f, ".{}", field)?,
+                PathSegment::Variant { variant, .. } => write!(f, "::{}", variant)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A step in the path to a field that failed to read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// A field of a struct or enum variant
+    Field {
+        /// The name of the type containing the field
+        type_name: &'static str,
+        /// The name of the field, or the index of the field for tuple structs
+        field: &'static str,
+    },
+    /// The variant of an enum
+    Variant {
+        /// The name of the enum
+        type_name: &'static str,
+        /// The name of the variant
+        variant: &'static str,
+    },
+    /// An item in a list
+    Index(usize),
 }
 
 impl From<FromUtf8Error> for BitError {
@@ -171,6 +462,16 @@ impl From<FromUtf8Error> for BitError {
     }
 }
 
+/// The value of the padding bits used when aligning a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Padding {
+    /// Pad with zero bits
+    #[default]
+    Zeros,
+    /// Pad with one bits
+    Ones,
+}
+
 /// Either the read bits in the requested format or a [`BitError`]
 pub type Result<T, E = BitError> = std::result::Result<T, E>;
 
@@ -0,0 +1,80 @@
+use std::fmt::{Display, Formatter};
+
+/// Limits for reading untrusted input
+///
+/// Lengths for collections and strings are often read from the input itself, limiting them prevents
+/// malicious input from making the reader allocate large amounts of memory.
+///
+/// The limits are checked by the built-in [`BitRead`] and [`BitReadSized`] implementations,
+/// by default no limits are set.
+///
+/// # Examples
+///
+/// ```
+/// # use bitbuffer::{BitReadBuffer, BitReadStream, BitError, LimitKind, LittleEndian, ReadLimits};
+/// #
+/// let bytes = vec![0xFF; 64];
+/// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+/// stream.set_limits(ReadLimits {
+///     max_collection_length: 16,
+///     ..ReadLimits::default()
+/// });
+///
+/// let result = stream.read_sized::<Vec<u8>>(32);
+/// assert!(matches!(
+///     result,
+///     Err(BitError::LimitExceeded {
+///         limit: LimitKind::CollectionLength,
+///         requested: 32,
+///         max: 16
+///     })
+/// ));
+/// ```
+///
+/// [`BitRead`]: trait.BitRead.html
+/// [`BitReadSized`]: trait.BitReadSized.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadLimits {
+    /// The maximum number of items in a single collection
+    pub max_collection_length: usize,
+    /// The maximum length of a single string in bytes
+    pub max_string_length: usize,
+    /// The maximum number of bytes allocated for all collections and strings read from the stream
+    pub max_allocation: usize,
+}
+
+impl ReadLimits {
+    /// No limits
+    pub const UNLIMITED: ReadLimits = ReadLimits {
+        max_collection_length: usize::MAX,
+        max_string_length: usize::MAX,
+        max_allocation: usize::MAX,
+    };
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        ReadLimits::UNLIMITED
+    }
+}
+
+/// The limit that was exceeded while reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitKind {
+    /// The number of items in a collection
+    CollectionLength,
+    /// The length of a string in bytes
+    StringLength,
+    /// The total number of allocated bytes
+    Allocation,
+}
+
+impl Display for LimitKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitKind::CollectionLength => write!(f, "collection length"),
+            LimitKind::StringLength => write!(f, "string length"),
+            LimitKind::Allocation => write!(f, "allocation size"),
+        }
+    }
+}
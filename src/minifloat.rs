@@ -0,0 +1,431 @@
+//! Small floating point formats
+//!
+//! Reading and writing of IEEE 754 half precision (binary16), bfloat16 and custom minifloat formats
+//! as used by sensor data and machine learning payloads.
+//!
+//! The formats follow the IEEE 754 layout of a sign bit, followed by a biased exponent and the mantissa,
+//! with subnormal numbers for an exponent of all zeros and infinities and NaN for an exponent of all ones.
+//! Values are converted from and to `f32`, which can represent every value of the supported formats exactly.
+//!
+//! When converting from `f32`, values are rounded to the nearest representable value with ties rounded to an even
+//! mantissa, values that are too large for the format become infinite and the highest bits of NaN payloads are kept.
+//!
+//! The formats can either be read and written directly using the methods on [`BitReadStream`] and
+//! [`BitWriteStream`], or by using the [`MiniFloat`] type which implements [`BitRead`] and [`BitWrite`].
+//!
+//! # Examples
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite};
+//! use bitbuffer::minifloat::{MiniFloat, BF16, F16};
+//!
+//! #[derive(BitRead, BitWrite)]
+//! struct Sample {
+//!     temperature: F16,
+//!     weight: BF16,
+//!     // 1 sign bit, 4 exponent bits and 3 mantissa bits
+//!     gain: MiniFloat<4, 3>,
+//! }
+//! ```
+
+use crate::{BitRead, BitReadStream, BitSize, BitWrite, BitWriteStream, Endianness, Result};
+use std::fmt::{Display, Formatter};
+
+/// Check that the format can be converted to and from `f32` exactly
+const fn check_format(exponent_bits: usize, mantissa_bits: usize) {
+    assert!(
+        exponent_bits >= 1 && exponent_bits <= 8,
+        "minifloat formats are required to have 1 to 8 exponent bits"
+    );
+    assert!(
+        mantissa_bits >= 1 && mantissa_bits <= 23,
+        "minifloat formats are required to have 1 to 23 mantissa bits"
+    );
+}
+
+/// Convert an `f32` to the bits of the minifloat format, rounding to the nearest value
+fn f32_to_bits(value: f32, exponent_bits: usize, mantissa_bits: usize) -> u32 {
+    let bits = value.to_bits();
+    let sign = (bits >> 31) << (exponent_bits + mantissa_bits);
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+
+    let max_exponent = (1u32 << exponent_bits) - 1;
+    let bias = (1i32 << (exponent_bits - 1)) - 1;
+    let shift = 23 - mantissa_bits as u32;
+    let infinity = sign | max_exponent << mantissa_bits;
+
+    if exponent == 0xFF {
+        if mantissa == 0 {
+            return infinity;
+        }
+        // keep the highest bits of the payload, making sure the value stays a NaN
+        let payload = match mantissa >> shift {
+            0 => 1 << (mantissa_bits - 1),
+            payload => payload,
+        };
+        return infinity | payload;
+    }
+
+    // the significand including the implicit bit for normal numbers, with 23 fraction bits
+    let (exponent, significand) = if exponent == 0 {
+        (-126, mantissa)
+    } else {
+        (exponent - 127, mantissa | 0x80_0000)
+    };
+
+    // subnormal values lose additional precision
+    let target_exponent = exponent + bias;
+    let drop = if target_exponent >= 1 {
+        shift
+    } else {
+        (shift as i32 + 1 - target_exponent).min(31) as u32
+    };
+
+    let mut rounded = significand >> drop;
+    if drop > 0 {
+        let remainder = significand & ((1 << drop) - 1);
+        let half = 1 << (drop - 1);
+        if remainder > half || (remainder == half && rounded & 1 == 1) {
+            rounded += 1;
+        }
+    }
+
+    // the implicit bit of normal numbers is added to the exponent, a mantissa that overflows
+    // while rounding carries into the exponent
+    let magnitude = if target_exponent >= 1 {
+        ((target_exponent as u32 - 1) << mantissa_bits) + rounded
+    } else {
+        rounded
+    };
+
+    if magnitude >= max_exponent << mantissa_bits {
+        infinity
+    } else {
+        sign | magnitude
+    }
+}
+
+/// Convert the bits of the minifloat format to an `f32`
+fn bits_to_f32(bits: u32, exponent_bits: usize, mantissa_bits: usize) -> f32 {
+    let sign = ((bits >> (exponent_bits + mantissa_bits)) & 1) << 31;
+    let max_exponent = (1u32 << exponent_bits) - 1;
+    let exponent = (bits >> mantissa_bits) & max_exponent;
+    let mantissa = bits & ((1 << mantissa_bits) - 1);
+
+    let bias = (1u32 << (exponent_bits - 1)) - 1;
+    let shift = 23 - mantissa_bits as u32;
+
+    let magnitude = if exponent == max_exponent {
+        // infinity or NaN with payload
+        0xFF << 23 | mantissa << shift
+    } else if exponent == 0 {
+        if mantissa == 0 || bias == 127 {
+            // subnormal values of formats with the same exponent range as `f32` are subnormal `f32` values
+            mantissa << shift
+        } else {
+            // normalize the subnormal value
+            let leading = 31 - mantissa.leading_zeros();
+            let exponent = 127 + leading + 1 - bias - mantissa_bits as u32;
+            exponent << 23 | ((mantissa << (23 - leading)) & 0x7F_FFFF)
+        }
+    } else {
+        (exponent + 127 - bias) << 23 | mantissa << shift
+    };
+
+    f32::from_bits(sign | magnitude)
+}
+
+impl<'a, E: Endianness> BitReadStream<'a, E> {
+    /// Read a floating point number with the provided number of exponent and mantissa bits
+    ///
+    /// The number is read as a sign bit followed by the exponent and mantissa, `1 + exponent_bits + mantissa_bits` bits
+    /// are read in total. See the [module documentation](crate::minifloat) for details about the format.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `exponent_bits` is not between 1 and 8 or `mantissa_bits` is not between 1 and 23
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b0_0111_100, 0b1_1111_000];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_minifloat(4, 3)?, 1.5);
+    /// assert_eq!(stream.read_minifloat(4, 3)?, f32::NEG_INFINITY);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn read_minifloat(&mut self, exponent_bits: usize, mantissa_bits: usize) -> Result<f32> {
+        check_format(exponent_bits, mantissa_bits);
+        let bits = self.read_int(1 + exponent_bits + mantissa_bits)?;
+        Ok(bits_to_f32(bits, exponent_bits, mantissa_bits))
+    }
+
+    /// Read an IEEE 754 half precision floating point number
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0x00, 0xC5];
+    /// let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_f16()?, -5.0);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn read_f16(&mut self) -> Result<f32> {
+        self.read_minifloat(5, 10)
+    }
+
+    /// Read a bfloat16 floating point number
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0x3F, 0xC0];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_bf16()?, 1.5);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn read_bf16(&mut self) -> Result<f32> {
+        self.read_minifloat(8, 7)
+    }
+}
+
+impl<'a, E: Endianness> BitWriteStream<'a, E> {
+    /// Write a floating point number with the provided number of exponent and mantissa bits
+    ///
+    /// The value is rounded to the nearest representable value, see the [module documentation](crate::minifloat)
+    /// for details about the format and rounding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `exponent_bits` is not between 1 and 8 or `mantissa_bits` is not between 1 and 23
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_minifloat(1.5, 4, 3)?;
+    /// stream.write_minifloat(-1000.0, 4, 3)?;
+    /// assert_eq!(data, [0b0_0111_100, 0b1_1111_000]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_minifloat(
+        &mut self,
+        value: f32,
+        exponent_bits: usize,
+        mantissa_bits: usize,
+    ) -> Result<()> {
+        check_format(exponent_bits, mantissa_bits);
+        let bits = f32_to_bits(value, exponent_bits, mantissa_bits);
+        self.write_int(bits, 1 + exponent_bits + mantissa_bits)
+    }
+
+    /// Write an IEEE 754 half precision floating point number
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    /// stream.write_f16(-5.0)?;
+    /// assert_eq!(data, [0x00, 0xC5]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_f16(&mut self, value: f32) -> Result<()> {
+        self.write_minifloat(value, 5, 10)
+    }
+
+    /// Write a bfloat16 floating point number
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_bf16(1.5)?;
+    /// assert_eq!(data, [0x3F, 0xC0]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_bf16(&mut self, value: f32) -> Result<()> {
+        self.write_minifloat(value, 8, 7)
+    }
+}
+
+/// Floating point number with `EXPONENT` exponent bits and `MANTISSA` mantissa bits
+///
+/// The number is stored as the raw bits of the format, so NaN payloads are kept when reading and writing,
+/// and comparisons compare the bits instead of the values.
+///
+/// See the [module documentation](crate::minifloat) for details about the format.
+///
+/// # Examples
+///
+/// ```
+/// use bitbuffer::minifloat::{MiniFloat, F16};
+///
+/// let value = F16::from_f32(0.1);
+/// assert_eq!(value.to_bits(), 0x2E66);
+/// assert_eq!(value.to_f32(), 0.099975586);
+/// assert_eq!(MiniFloat::<4, 3>::from_f32(0.1).to_f32(), 0.1015625);
+/// ```
+///
+/// Formats that can't be converted to and from `f32` exactly fail to compile.
+///
+/// ```compile_fail
+/// use bitbuffer::minifloat::MiniFloat;
+///
+/// let value = MiniFloat::<9, 3>::from_f32(0.1);
+/// ```
+///
+/// ```compile_fail
+/// use bitbuffer::minifloat::MiniFloat;
+///
+/// let value = MiniFloat::<0, 3>::from_bits(0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MiniFloat<const EXPONENT: usize, const MANTISSA: usize>(u32);
+
+/// IEEE 754 half precision floating point number
+pub type F16 = MiniFloat<5, 10>;
+
+/// bfloat16 floating point number
+pub type BF16 = MiniFloat<8, 7>;
+
+impl<const EXPONENT: usize, const MANTISSA: usize> MiniFloat<EXPONENT, MANTISSA> {
+    const VALID: () = check_format(EXPONENT, MANTISSA);
+
+    /// The number of bits of the format
+    pub const BITS: usize = 1 + EXPONENT + MANTISSA;
+
+    /// Convert an `f32` to the nearest representable value
+    #[allow(clippy::let_unit_value)]
+    pub fn from_f32(value: f32) -> Self {
+        let _ = Self::VALID;
+        MiniFloat(f32_to_bits(value, EXPONENT, MANTISSA))
+    }
+
+    /// Convert the value to `f32`, the conversion is exact
+    #[allow(clippy::let_unit_value)]
+    pub fn to_f32(self) -> f32 {
+        let _ = Self::VALID;
+        bits_to_f32(self.0, EXPONENT, MANTISSA)
+    }
+
+    /// Create a value from the raw bits of the format, higher bits are ignored
+    #[allow(clippy::let_unit_value)]
+    pub fn from_bits(bits: u32) -> Self {
+        let _ = Self::VALID;
+        MiniFloat(bits & (u32::MAX >> (32 - Self::BITS)))
+    }
+
+    /// Get the raw bits of the format
+    pub fn to_bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl<const EXPONENT: usize, const MANTISSA: usize> From<f32> for MiniFloat<EXPONENT, MANTISSA> {
+    fn from(value: f32) -> Self {
+        MiniFloat::from_f32(value)
+    }
+}
+
+impl<const EXPONENT: usize, const MANTISSA: usize> From<MiniFloat<EXPONENT, MANTISSA>> for f32 {
+    fn from(value: MiniFloat<EXPONENT, MANTISSA>) -> Self {
+        value.to_f32()
+    }
+}
+
+impl<const EXPONENT: usize, const MANTISSA: usize> Display for MiniFloat<EXPONENT, MANTISSA> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.to_f32(), f)
+    }
+}
+
+impl<'a, E: Endianness, const EXPONENT: usize, const MANTISSA: usize> BitRead<'a, E>
+    for MiniFloat<EXPONENT, MANTISSA>
+{
+    #[inline]
+    #[allow(clippy::let_unit_value)]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        let _ = Self::VALID;
+        stream.read_int(Self::BITS).map(MiniFloat)
+    }
+
+    #[inline]
+    fn bit_size() -> Option<usize> {
+        Some(Self::BITS)
+    }
+}
+
+impl<const EXPONENT: usize, const MANTISSA: usize> BitSize for MiniFloat<EXPONENT, MANTISSA> {
+    const BIT_SIZE: Option<usize> = Some(1 + EXPONENT + MANTISSA);
+}
+
+impl<E: Endianness, const EXPONENT: usize, const MANTISSA: usize> BitWrite<E>
+    for MiniFloat<EXPONENT, MANTISSA>
+{
+    #[inline]
+    #[allow(clippy::let_unit_value)]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        let _ = Self::VALID;
+        stream.write_int(self.0, Self::BITS)
+    }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        Self::BITS
+    }
+}
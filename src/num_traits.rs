@@ -20,8 +20,8 @@ pub trait UncheckedPrimitiveFloat: Sized {
 
     fn from_f32_unchecked(n: f32) -> Self;
     fn from_f64_unchecked(n: f64) -> Self;
-    fn to_bytes<E: Endianness>(self) -> Self::BYTES;
-    fn from_bytes<E: Endianness>(bytes: Self::BYTES) -> Self;
+    fn to_bytes<E: Endianness>(self, endianness: E) -> Self::BYTES;
+    fn from_bytes<E: Endianness>(bytes: Self::BYTES, endianness: E) -> Self;
     fn to_int(self) -> Self::INT;
     fn from_int(int: Self::INT) -> Self;
 }
@@ -38,15 +38,15 @@ impl UncheckedPrimitiveFloat for f32 {
     fn from_f64_unchecked(n: f64) -> Self {
         n as f32
     }
-    fn to_bytes<E: Endianness>(self) -> Self::BYTES {
-        if E::is_le() {
+    fn to_bytes<E: Endianness>(self, endianness: E) -> Self::BYTES {
+        if endianness.is_le() {
             self.to_le_bytes()
         } else {
             self.to_be_bytes()
         }
     }
-    fn from_bytes<E: Endianness>(bytes: Self::BYTES) -> Self {
-        if E::is_le() {
+    fn from_bytes<E: Endianness>(bytes: Self::BYTES, endianness: E) -> Self {
+        if endianness.is_le() {
             Self::from_le_bytes(bytes)
         } else {
             Self::from_be_bytes(bytes)
@@ -72,15 +72,15 @@ impl UncheckedPrimitiveFloat for f64 {
     fn from_f64_unchecked(n: f64) -> Self {
         n
     }
-    fn to_bytes<E: Endianness>(self) -> Self::BYTES {
-        if E::is_le() {
+    fn to_bytes<E: Endianness>(self, endianness: E) -> Self::BYTES {
+        if endianness.is_le() {
             self.to_le_bytes()
         } else {
             self.to_be_bytes()
         }
     }
-    fn from_bytes<E: Endianness>(bytes: Self::BYTES) -> Self {
-        if E::is_le() {
+    fn from_bytes<E: Endianness>(bytes: Self::BYTES, endianness: E) -> Self {
+        if endianness.is_le() {
             Self::from_le_bytes(bytes)
         } else {
             Self::from_be_bytes(bytes)
@@ -277,7 +277,7 @@ impl_is_signed!(isize, true);
 pub trait SplitFitUsize {
     type Iter: Iterator<Item = (usize, u8)> + ExactSizeIterator + DoubleEndedIterator;
 
-    fn split_fit_usize<E: Endianness>(self) -> Self::Iter;
+    fn split_fit_usize<E: Endianness>(self, endianness: E) -> Self::Iter;
 }
 
 use std::array;
@@ -288,7 +288,7 @@ macro_rules! impl_split_fit {
         impl SplitFitUsize for $type {
             type Iter = array::IntoIter<(usize, u8), 1>;
 
-            fn split_fit_usize<E: Endianness>(self) -> Self::Iter {
+            fn split_fit_usize<E: Endianness>(self, _endianness: E) -> Self::Iter {
                 assert!(size_of::<Self>() < size_of::<usize>());
                 [(self as usize, size_of::<Self>() as u8 * 8)].into_iter()
             }
@@ -301,9 +301,9 @@ macro_rules! impl_split_fit_signed {
         impl SplitFitUsize for $signed_type {
             type Iter = <$unsigned_type as SplitFitUsize>::Iter;
 
-            fn split_fit_usize<E: Endianness>(self) -> Self::Iter {
+            fn split_fit_usize<E: Endianness>(self, endianness: E) -> Self::Iter {
                 let unsigned = <$unsigned_type>::from_ne_bytes(self.to_ne_bytes());
-                unsigned.split_fit_usize::<E>()
+                unsigned.split_fit_usize(endianness)
             }
         }
     };
@@ -320,8 +320,8 @@ impl_split_fit!(u32);
 impl SplitFitUsize for u32 {
     type Iter = array::IntoIter<(usize, u8), 2>;
 
-    fn split_fit_usize<E: Endianness>(self) -> Self::Iter {
-        Self::Iter::new(if E::is_le() {
+    fn split_fit_usize<E: Endianness>(self, endianness: E) -> Self::Iter {
+        Self::Iter::new(if endianness.bit_order().is_lsb_first() {
             [
                 ((self & (Self::MAX >> 8)) as usize, 24),
                 ((self >> 24) as usize, 8),
@@ -340,8 +340,8 @@ impl_split_fit_signed!(i32, u32);
 impl SplitFitUsize for u64 {
     type Iter = array::IntoIter<(usize, u8), 3>;
 
-    fn split_fit_usize<E: Endianness>(self) -> Self::Iter {
-        (if E::is_le() {
+    fn split_fit_usize<E: Endianness>(self, endianness: E) -> Self::Iter {
+        (if endianness.bit_order().is_lsb_first() {
             [
                 ((self & (Self::MAX >> 40)) as usize, 24),
                 ((self >> 24 & (Self::MAX >> 16)) as usize, 24),
@@ -363,8 +363,8 @@ impl_split_fit_signed!(i64, u64);
 impl SplitFitUsize for u128 {
     type Iter = array::IntoIter<(usize, u8), 6>;
 
-    fn split_fit_usize<E: Endianness>(self) -> Self::Iter {
-        (if E::is_le() {
+    fn split_fit_usize<E: Endianness>(self, endianness: E) -> Self::Iter {
+        (if endianness.bit_order().is_lsb_first() {
             [
                 ((self & (Self::MAX >> 104)) as usize, 24),
                 ((self >> 24 & (Self::MAX >> 80)) as usize, 24),
@@ -392,8 +392,8 @@ impl_split_fit_signed!(i128, u128);
 impl SplitFitUsize for usize {
     type Iter = array::IntoIter<(usize, u8), 2>;
 
-    fn split_fit_usize<E: Endianness>(self) -> Self::Iter {
-        (if E::is_le() {
+    fn split_fit_usize<E: Endianness>(self, endianness: E) -> Self::Iter {
+        (if endianness.bit_order().is_lsb_first() {
             [
                 (
                     (self & (Self::MAX >> (usize::BITS - 8))) as usize,
@@ -0,0 +1,560 @@
+//! Quantized and fixed point numbers
+//!
+//! Reading and writing of floats quantized over a range, Q-format fixed point numbers and the bit coord and
+//! normal encodings used by the network protocol of the Source engine.
+//!
+//! # Rounding
+//!
+//! When writing, values are converted to the nearest representable value, the conversion is done using `f64`
+//! arithmetic and ties are rounded away from zero. Values outside of the representable range are clamped to the
+//! closest representable value and NaN is written as zero, or as `min` for quantized floats.
+//!
+//! # Examples
+//!
+//! ```
+//! # use bitbuffer::{BitRead, BitWrite};
+//! use bitbuffer::quantized::{BitCoord, BitNormal, Fixed, UFixed};
+//!
+//! #[derive(BitRead, BitWrite)]
+//! struct Entity {
+//!     origin: [BitCoord; 3],
+//!     direction: BitNormal,
+//!     // Q8.8 fixed point
+//!     speed: Fixed<8, 8>,
+//!     health: UFixed<7, 1>,
+//! }
+//! ```
+
+use crate::{BitRead, BitReadStream, BitSize, BitWrite, BitWriteStream, Endianness, Result};
+use std::fmt::{Display, Formatter};
+
+const COORD_INTEGER_BITS: usize = 14;
+const COORD_FRACTIONAL_BITS: usize = 5;
+const COORD_DENOMINATOR: f64 = (1 << COORD_FRACTIONAL_BITS) as f64;
+const COORD_MAX: u32 =
+    (1 << (COORD_INTEGER_BITS + COORD_FRACTIONAL_BITS)) + (1 << COORD_FRACTIONAL_BITS) - 1;
+
+const NORMAL_FRACTIONAL_BITS: usize = 11;
+const NORMAL_DENOMINATOR: u32 = (1 << NORMAL_FRACTIONAL_BITS) - 1;
+
+/// Get the magnitude of the value scaled by `scale`, rounded to the nearest integer and clamped to `max`
+fn scale_magnitude(value: f32, scale: f64, max: u32) -> u32 {
+    // float to int casts saturate and convert NaN to 0
+    ((value.abs() as f64 * scale).round() as u32).min(max)
+}
+
+impl<'a, E: Endianness> BitReadStream<'a, E> {
+    /// Read a float quantized to `bits` bits over the range from `min` to `max`
+    ///
+    /// The raw value is read as unsigned integer, where `0` is `min` and the largest value is `max`,
+    /// with the values in between evenly spaced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is not between 1 and 32
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b0000_0101, 0b1111_1111];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_quantized_float(4, -1.0, 2.0)?, -1.0);
+    /// assert_eq!(stream.read_quantized_float(4, -1.0, 2.0)?, 0.0);
+    /// assert_eq!(stream.read_quantized_float(8, -1.0, 2.0)?, 2.0);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn read_quantized_float(&mut self, bits: usize, min: f32, max: f32) -> Result<f32> {
+        assert!(
+            (1..=32).contains(&bits),
+            "quantized floats are required to have 1 to 32 bits"
+        );
+        let raw: u32 = self.read_int(bits)?;
+        let steps = (u32::MAX >> (32 - bits)) as f64;
+        Ok((min as f64 + (max as f64 - min as f64) * raw as f64 / steps) as f32)
+    }
+
+    /// Read a coordinate in the bit coord encoding of the Source engine
+    ///
+    /// The coordinate consists of a flag for the presence of the integer and fractional parts,
+    /// followed by a sign bit, the 14 bit integer part and the 5 bit fractional part if either part is present.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b0000_0111, 0b0000_0000, 0b0010_0000];
+    /// let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_bit_coord()?, -1.5);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn read_bit_coord(&mut self) -> Result<f32> {
+        let has_int = self.read_bool()?;
+        let has_fraction = self.read_bool()?;
+        if !has_int && !has_fraction {
+            return Ok(0.0);
+        }
+        let negative = self.read_bool()?;
+        let int = if has_int {
+            self.read_int::<u32>(COORD_INTEGER_BITS)? + 1
+        } else {
+            0
+        };
+        let fraction = if has_fraction {
+            self.read_int::<u32>(COORD_FRACTIONAL_BITS)?
+        } else {
+            0
+        };
+        let value = (int as f64 + fraction as f64 / COORD_DENOMINATOR) as f32;
+        Ok(if negative { -value } else { value })
+    }
+
+    /// Read a component of a normalized vector in the bit normal encoding of the Source engine
+    ///
+    /// The value consists of a sign bit followed by 11 fractional bits.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b1111_1111, 0b1111_0000];
+    /// let buffer = BitReadBuffer::new(&bytes, BigEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_bit_normal()?, -1.0);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn read_bit_normal(&mut self) -> Result<f32> {
+        let negative = self.read_bool()?;
+        let fraction = self.read_int::<u32>(NORMAL_FRACTIONAL_BITS)?;
+        let value = (fraction as f64 / NORMAL_DENOMINATOR as f64) as f32;
+        Ok(if negative { -value } else { value })
+    }
+
+    /// Read a vector of bit coords, with a flag for every component to mark non zero components
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn read_bit_vec3_coord(&mut self) -> Result<[f32; 3]> {
+        let flags = [self.read_bool()?, self.read_bool()?, self.read_bool()?];
+        let mut vector = [0.0; 3];
+        for (component, flag) in vector.iter_mut().zip(flags) {
+            if flag {
+                *component = self.read_bit_coord()?;
+            }
+        }
+        Ok(vector)
+    }
+
+    /// Read a normalized vector of bit normals, only the x and y components and the sign of the z component are read
+    ///
+    /// The x and y components are preceded by flags to mark non zero components.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn read_bit_vec3_normal(&mut self) -> Result<[f32; 3]> {
+        let has_x = self.read_bool()?;
+        let has_y = self.read_bool()?;
+        let x = if has_x { self.read_bit_normal()? } else { 0.0 };
+        let y = if has_y { self.read_bit_normal()? } else { 0.0 };
+        let z_negative = self.read_bool()?;
+        let xy = x * x + y * y;
+        let z = if xy < 1.0 { (1.0 - xy).sqrt() } else { 0.0 };
+        Ok([x, y, if z_negative { -z } else { z }])
+    }
+}
+
+impl<'a, E: Endianness> BitWriteStream<'a, E> {
+    /// Write a float quantized to `bits` bits over the range from `min` to `max`
+    ///
+    /// See [`read_quantized_float`] for details about the encoding and the [module documentation](crate::quantized)
+    /// for details about rounding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is not between 1 and 32
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_quantized_float(-3.0, 4, -1.0, 2.0)?;
+    /// stream.write_quantized_float(0.05, 4, -1.0, 2.0)?;
+    /// stream.write_quantized_float(2.0, 8, -1.0, 2.0)?;
+    /// assert_eq!(data, [0b0000_0101, 0b1111_1111]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`read_quantized_float`]: BitReadStream::read_quantized_float
+    pub fn write_quantized_float(
+        &mut self,
+        value: f32,
+        bits: usize,
+        min: f32,
+        max: f32,
+    ) -> Result<()> {
+        assert!(
+            (1..=32).contains(&bits),
+            "quantized floats are required to have 1 to 32 bits"
+        );
+        let steps = (u32::MAX >> (32 - bits)) as f64;
+        let scaled = (value as f64 - min as f64) / (max as f64 - min as f64) * steps;
+        // float to int casts saturate and convert NaN to 0
+        let raw = scaled.round().clamp(0.0, steps) as u32;
+        self.write_int(raw, bits)
+    }
+
+    /// Write a coordinate in the bit coord encoding of the Source engine
+    ///
+    /// The value is rounded to the nearest multiple of `1/32` and clamped to `±16384.96875`,
+    /// see [`read_bit_coord`] for details about the encoding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    /// stream.write_bit_coord(-1.5)?;
+    /// assert_eq!(data, [0b0000_0111, 0b0000_0000, 0b0010_0000]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`read_bit_coord`]: BitReadStream::read_bit_coord
+    pub fn write_bit_coord(&mut self, value: f32) -> Result<()> {
+        let magnitude = scale_magnitude(value, COORD_DENOMINATOR, COORD_MAX);
+        let int = magnitude >> COORD_FRACTIONAL_BITS;
+        let fraction = magnitude & ((1 << COORD_FRACTIONAL_BITS) - 1);
+        self.write_bool(int > 0)?;
+        self.write_bool(fraction > 0)?;
+        if magnitude > 0 {
+            self.write_bool(value.is_sign_negative())?;
+            if int > 0 {
+                self.write_int(int - 1, COORD_INTEGER_BITS)?;
+            }
+            if fraction > 0 {
+                self.write_int(fraction, COORD_FRACTIONAL_BITS)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a component of a normalized vector in the bit normal encoding of the Source engine
+    ///
+    /// The value is rounded to the nearest multiple of `1/2047` and clamped to `±1`,
+    /// see [`read_bit_normal`] for details about the encoding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_bit_normal(-1.0)?;
+    /// assert_eq!(data, [0b1111_1111, 0b1111_0000]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`read_bit_normal`]: BitReadStream::read_bit_normal
+    pub fn write_bit_normal(&mut self, value: f32) -> Result<()> {
+        let magnitude = scale_magnitude(value, NORMAL_DENOMINATOR as f64, NORMAL_DENOMINATOR);
+        self.write_bool(magnitude > 0 && value.is_sign_negative())?;
+        self.write_int(magnitude, NORMAL_FRACTIONAL_BITS)
+    }
+
+    /// Write a vector of bit coords, with a flag for every component to mark non zero components
+    ///
+    /// Components that round to zero are not written.
+    pub fn write_bit_vec3_coord(&mut self, vector: [f32; 3]) -> Result<()> {
+        let flags = vector.map(|component| scale_magnitude(component, COORD_DENOMINATOR, 1) > 0);
+        for flag in flags {
+            self.write_bool(flag)?;
+        }
+        for (component, flag) in vector.into_iter().zip(flags) {
+            if flag {
+                self.write_bit_coord(component)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a normalized vector of bit normals, only the x and y components and the sign of the z component are written
+    ///
+    /// Components that round to zero are not written.
+    pub fn write_bit_vec3_normal(&mut self, vector: [f32; 3]) -> Result<()> {
+        let [x, y, z] = vector;
+        let has_x = scale_magnitude(x, NORMAL_DENOMINATOR as f64, 1) > 0;
+        let has_y = scale_magnitude(y, NORMAL_DENOMINATOR as f64, 1) > 0;
+        self.write_bool(has_x)?;
+        self.write_bool(has_y)?;
+        if has_x {
+            self.write_bit_normal(x)?;
+        }
+        if has_y {
+            self.write_bit_normal(y)?;
+        }
+        self.write_bool(z < 0.0)
+    }
+}
+
+/// Coordinate that is read and written in the bit coord encoding of the Source engine
+///
+/// See [`read_bit_coord`] for details.
+///
+/// [`read_bit_coord`]: BitReadStream::read_bit_coord
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct BitCoord(pub f32);
+
+impl<'a, E: Endianness> BitRead<'a, E> for BitCoord {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_bit_coord().map(BitCoord)
+    }
+}
+
+impl BitSize for BitCoord {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl<E: Endianness> BitWrite<E> for BitCoord {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_bit_coord(self.0)
+    }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        let magnitude = scale_magnitude(self.0, COORD_DENOMINATOR, COORD_MAX);
+        match (
+            magnitude >> COORD_FRACTIONAL_BITS,
+            magnitude & ((1 << COORD_FRACTIONAL_BITS) - 1),
+        ) {
+            (0, 0) => 2,
+            (0, _) => 3 + COORD_FRACTIONAL_BITS,
+            (_, 0) => 3 + COORD_INTEGER_BITS,
+            _ => 3 + COORD_INTEGER_BITS + COORD_FRACTIONAL_BITS,
+        }
+    }
+}
+
+/// Component of a normalized vector that is read and written in the bit normal encoding of the Source engine
+///
+/// See [`read_bit_normal`] for details.
+///
+/// [`read_bit_normal`]: BitReadStream::read_bit_normal
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct BitNormal(pub f32);
+
+impl<'a, E: Endianness> BitRead<'a, E> for BitNormal {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_bit_normal().map(BitNormal)
+    }
+
+    #[inline]
+    fn bit_size() -> Option<usize> {
+        Some(1 + NORMAL_FRACTIONAL_BITS)
+    }
+}
+
+impl BitSize for BitNormal {
+    const BIT_SIZE: Option<usize> = Some(1 + NORMAL_FRACTIONAL_BITS);
+}
+
+impl<E: Endianness> BitWrite<E> for BitNormal {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_bit_normal(self.0)
+    }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        1 + NORMAL_FRACTIONAL_BITS
+    }
+}
+
+macro_rules! impl_fixed {
+    ($type:ident, $raw:ty, $name:expr) => {
+        impl<const I: usize, const F: usize> $type<I, F> {
+            const VALID: () = assert!(
+                I + F > 0 && I + F <= 64,
+                concat!($name, " is required to have 1 to 64 bits")
+            );
+
+            /// The number of bits of the number
+            pub const BITS: usize = I + F;
+            /// The smallest value of the number
+            pub const MIN: Self = $type(<$raw>::MIN >> (64 - I - F));
+            /// The largest value of the number
+            pub const MAX: Self = $type(<$raw>::MAX >> (64 - I - F));
+
+            /// Create a number from the raw integer representation, higher bits are ignored
+            #[allow(clippy::let_unit_value)]
+            pub const fn from_bits(bits: $raw) -> Self {
+                let _ = Self::VALID;
+                let shift = (64 - I - F) as u32;
+                $type(bits << shift >> shift)
+            }
+
+            /// Get the raw integer representation of the number
+            pub const fn to_bits(self) -> $raw {
+                self.0
+            }
+
+            /// Convert a float to the nearest representable number
+            ///
+            /// Ties are rounded away from zero, values outside of the range of the number are clamped
+            /// and NaN is converted to `0`.
+            #[allow(clippy::let_unit_value)]
+            pub fn from_f64(value: f64) -> Self {
+                let _ = Self::VALID;
+                // float to int casts saturate and convert NaN to 0
+                let raw = (value * 2f64.powi(F as i32)).round() as $raw;
+                $type(raw.clamp(Self::MIN.0, Self::MAX.0))
+            }
+
+            /// Convert the number to a float
+            ///
+            /// The conversion is exact for numbers with up to 53 bits.
+            pub fn to_f64(self) -> f64 {
+                self.0 as f64 / 2f64.powi(F as i32)
+            }
+
+            /// Convert the number to a float
+            pub fn to_f32(self) -> f32 {
+                self.to_f64() as f32
+            }
+        }
+
+        impl<const I: usize, const F: usize> From<$type<I, F>> for f64 {
+            fn from(value: $type<I, F>) -> Self {
+                value.to_f64()
+            }
+        }
+
+        impl<const I: usize, const F: usize> Display for $type<I, F> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                Display::fmt(&self.to_f64(), f)
+            }
+        }
+
+        impl<'a, E: Endianness, const I: usize, const F: usize> BitRead<'a, E> for $type<I, F> {
+            #[inline]
+            fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+                stream.read_int::<$raw>(I + F).map($type::from_bits)
+            }
+
+            #[inline]
+            fn bit_size() -> Option<usize> {
+                Some(I + F)
+            }
+        }
+
+        impl<const I: usize, const F: usize> BitSize for $type<I, F> {
+            const BIT_SIZE: Option<usize> = Some(I + F);
+        }
+
+        impl<E: Endianness, const I: usize, const F: usize> BitWrite<E> for $type<I, F> {
+            #[inline]
+            fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+                stream.write_int(self.0, I + F)
+            }
+
+            #[inline]
+            fn written_bit_size(&self) -> usize {
+                I + F
+            }
+        }
+    };
+}
+
+/// Signed Q-format fixed point number with `I` integer bits and `F` fractional bits
+///
+/// The number is stored as `I + F` bit two's complement integer, the integer bits include the sign bit.
+/// See the [module documentation](crate::quantized) for details about rounding.
+///
+/// # Examples
+///
+/// ```
+/// use bitbuffer::quantized::Fixed;
+///
+/// type Q8_8 = Fixed<8, 8>;
+/// assert_eq!(Q8_8::from_f64(-1.5).to_bits(), -384);
+/// assert_eq!(Q8_8::from_f64(0.1).to_f64(), 0.1015625);
+/// assert_eq!(Q8_8::from_f64(1000.0), Q8_8::MAX);
+/// assert_eq!(Q8_8::MIN.to_f64(), -128.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct Fixed<const I: usize, const F: usize>(i64);
+
+/// Unsigned fixed point number with `I` integer bits and `F` fractional bits
+///
+/// The number is stored as `I + F` bit unsigned integer.
+/// See the [module documentation](crate::quantized) for details about rounding.
+///
+/// # Examples
+///
+/// ```
+/// use bitbuffer::quantized::UFixed;
+///
+/// type UQ4_4 = UFixed<4, 4>;
+/// assert_eq!(UQ4_4::from_f64(2.5).to_bits(), 40);
+/// assert_eq!(UQ4_4::from_f64(-1.0), UQ4_4::MIN);
+/// assert_eq!(UQ4_4::MAX.to_f64(), 15.9375);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct UFixed<const I: usize, const F: usize>(u64);
+
+impl_fixed!(Fixed, i64, "Fixed");
+impl_fixed!(UFixed, u64, "UFixed");
@@ -1,7 +1,6 @@
 use crate::endianness::{BigEndian, LittleEndian};
-use crate::{BitReadStream, Endianness, Result};
+use crate::{BitReadStream, BitSize, BitSizeSized, Endianness, PathSegment, Result};
 use std::borrow::Cow;
-use std::cell::RefCell;
 use std::cmp::min;
 use std::collections::HashMap;
 use std::hash::Hash;
@@ -50,7 +49,10 @@ use std::sync::Arc;
 ///
 /// # Enums
 ///
-/// The implementation can be derived for an enum as long as every variant of the enum either has no field, or an unnamed field that implements `BitRead` or [`BitReadSized`]
+/// The implementation can be derived for an enum as long as every variant of the enum either has no fields, or only has fields that implement `BitRead` or [`BitReadSized`]
+///
+/// Variants with fields are read field by field like structs, for variants with a single unnamed field the
+/// size can also be set on the variant itself.
 ///
 /// The enum is read by first reading a set number of bits as the discriminant of the enum, then the variant for the read discriminant is read.
 ///
@@ -87,6 +89,44 @@ use std::sync::Arc;
 /// }
 /// ```
 ///
+/// ```
+/// # use bitbuffer::BitRead;
+/// #
+/// #[derive(BitRead)]
+/// #[discriminant_bits = 2]
+/// enum TestFieldsEnum {
+///     Move {
+///         x: i16,
+///         #[size = 3]
+///         dir: u8,
+///     },
+///     Pair(u8, #[size = 4] u8),
+/// }
+/// ```
+///
+/// A variant can be marked as wildcard using `#[discriminant = "_"]`, it will be used for any discriminant that doesn't match another variant.
+/// To preserve the read discriminant, a wildcard variant can capture it in a field marked with `#[discriminant]`,
+/// when writing the variant the captured discriminant is written back.
+///
+/// ```
+/// # use bitbuffer::{BitRead, BitWrite, BitReadStream, Endianness};
+/// #
+/// #[derive(BitRead, BitWrite)]
+/// #[discriminant_bits = 4]
+/// #[endianness = "E"]
+/// enum Message<'a, E: Endianness> {
+///     Ping,
+///     Data(u8),
+///     #[discriminant = "_"]
+///     Unknown {
+///         #[discriminant]
+///         tag: u8,
+///         #[size = 8]
+///         body: BitReadStream<'a, E>,
+///     },
+/// }
+/// ```
+///
 /// [read_sized]: BitReadStream::read_sized
 /// [read]: BitReadStream::read
 pub trait BitRead<'a, E: Endianness>: Sized {
@@ -261,7 +301,9 @@ impl<E: Endianness> BitRead<'_, E> for bool {
 impl<E: Endianness> BitRead<'_, E> for String {
     #[inline]
     fn read(stream: &mut BitReadStream<E>) -> Result<String> {
-        Ok(stream.read_string(None)?.into_owned())
+        let string = stream.read_string(None)?;
+        stream.reserve_allocation(string.len())?;
+        Ok(string.into_owned())
     }
 }
 
@@ -351,25 +393,24 @@ impl_read_tuple!(T1, T2, T3, T4);
 impl<'a, E: Endianness, T: BitRead<'a, E>, const N: usize> BitRead<'a, E> for [T; N] {
     #[inline]
     fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
-        match T::bit_size() {
-            Some(bit_size) => {
-                let end = stream.check_read(bit_size * N)?;
-                unsafe { Self::read_unchecked(stream, end) }
-            }
-            None => {
-                // SAFETY: An uninitialized `[MaybeUninit<_>; LEN]` is valid.
-                let mut array =
-                    unsafe { MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init() };
-                for item in array.iter_mut() {
-                    unsafe {
-                        // length is already checked
-                        let val = stream.read()?;
-                        item.as_mut_ptr().write(val)
-                    }
-                }
-                unsafe { Ok((&array as *const _ as *const [T; N]).read()) }
+        if let Some(Ok(end)) = T::bit_size().map(|bit_size| stream.check_read(bit_size * N)) {
+            let start = stream.pos();
+            match unsafe { Self::read_unchecked(stream, end) } {
+                Ok(array) => return Ok(array),
+                // read again item by item to find the item that fails
+                Err(_) => stream.set_pos(start)?,
             }
         }
+        // read item by item if there isn't enough data, to find the item that fails
+
+        // SAFETY: An uninitialized `[MaybeUninit<_>; LEN]` is valid.
+        let mut array = unsafe { MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init() };
+        for (index, item) in array.iter_mut().enumerate() {
+            let val =
+                stream.read_with_context(PathSegment::Index(index), |stream| stream.read())?;
+            unsafe { item.as_mut_ptr().write(val) }
+        }
+        unsafe { Ok((&array as *const _ as *const [T; N]).read()) }
     }
 
     #[inline]
@@ -427,7 +468,10 @@ impl<'a, E: Endianness, T: BitRead<'a, E>, const N: usize> BitRead<'a, E> for [T
 ///
 /// # Enums
 ///
-/// The implementation can be derived for an enum as long as every variant of the enum either has no field, or an unnamed field that implements [`BitRead`] or `BitReadSized`
+/// The implementation can be derived for an enum as long as every variant of the enum either has no fields, or only has fields that implement [`BitRead`] or `BitReadSized`
+///
+/// Variants with fields are read field by field like structs, for variants with a single unnamed field the
+/// size can also be set on the variant itself.
 ///
 /// The enum is read by first reading a set number of bits as the discriminant of the enum, then the variant for the read discriminant is read.
 ///
@@ -527,7 +571,9 @@ impl_read_int_sized!(i128);
 impl<E: Endianness> BitReadSized<'_, E> for String {
     #[inline]
     fn read(stream: &mut BitReadStream<E>, size: usize) -> Result<String> {
-        Ok(stream.read_string(Some(size))?.into_owned())
+        let string = stream.read_string(Some(size))?;
+        stream.reserve_allocation(string.len())?;
+        Ok(string.into_owned())
     }
 
     #[inline]
@@ -593,28 +639,46 @@ impl<'a, E: Endianness> BitReadSized<'a, E> for BitReadStream<'a, E> {
     }
 }
 
+/// Read `size` items into `vec` without checking the length of the stream
+#[inline(always)]
+unsafe fn read_items_unchecked<'a, E: Endianness, T: BitRead<'a, E>>(
+    stream: &mut BitReadStream<'a, E>,
+    vec: &mut Vec<T>,
+    size: usize,
+    end: bool,
+) -> Result<()> {
+    for _ in 0..size {
+        vec.push(stream.read_unchecked(end)?)
+    }
+    Ok(())
+}
+
 /// Read `T` `size` times and return as `Vec<T>`
 impl<'a, E: Endianness, T: BitRead<'a, E>> BitReadSized<'a, E> for Vec<T> {
     fn read(stream: &mut BitReadStream<'a, E>, size: usize) -> Result<Self> {
+        stream.check_collection::<T>(size, T::bit_size())?;
         let mut vec = Vec::with_capacity(min(size, 128));
-        match T::bit_size() {
-            Some(bit_size) => {
-                if stream.check_read(bit_size * size)? {
-                    for _ in 0..size {
-                        vec.push(unsafe { stream.read_unchecked(true) }?)
-                    }
-                } else {
-                    for _ in 0..size {
-                        vec.push(unsafe { stream.read_unchecked(false) }?)
-                    }
-                }
-            }
-            _ => {
-                for _ in 0..size {
-                    vec.push(stream.read()?)
+        if let Some(Ok(end)) = T::bit_size().map(|bit_size| stream.check_read(bit_size * size)) {
+            let start = stream.pos();
+            // separate calls so `end` is known at compile time for the reads
+            let result = if end {
+                unsafe { read_items_unchecked(stream, &mut vec, size, true) }
+            } else {
+                unsafe { read_items_unchecked(stream, &mut vec, size, false) }
+            };
+            match result {
+                Ok(()) => return Ok(vec),
+                // read again item by item to find the item that fails
+                Err(_) => {
+                    stream.set_pos(start)?;
+                    vec.clear();
                 }
             }
         }
+        // read item by item if there isn't enough data, to find the item that fails
+        for index in 0..size {
+            vec.push(stream.read_with_context(PathSegment::Index(index), |stream| stream.read())?)
+        }
         Ok(vec)
     }
 
@@ -624,6 +688,7 @@ impl<'a, E: Endianness, T: BitRead<'a, E>> BitReadSized<'a, E> for Vec<T> {
         size: usize,
         end: bool,
     ) -> Result<Self> {
+        stream.check_collection::<T>(size, None)?;
         let mut vec = Vec::with_capacity(min(size, 128));
         for _ in 0..size {
             vec.push(stream.read_unchecked(end)?)
@@ -651,6 +716,11 @@ impl<'a, E: Endianness, K: BitRead<'a, E> + Eq + Hash, T: BitRead<'a, E>> BitRea
     for HashMap<K, T>
 {
     fn read(stream: &mut BitReadStream<'a, E>, size: usize) -> Result<Self> {
+        let entry_size = match (K::bit_size(), T::bit_size()) {
+            (Some(key_size), Some(value_size)) => Some(key_size + value_size),
+            _ => None,
+        };
+        stream.check_collection::<(K, T)>(size, entry_size)?;
         let mut map = HashMap::with_capacity(min(size, 128));
         for _ in 0..size {
             let key = stream.read()?;
@@ -666,6 +736,7 @@ impl<'a, E: Endianness, K: BitRead<'a, E> + Eq + Hash, T: BitRead<'a, E>> BitRea
         size: usize,
         end: bool,
     ) -> Result<Self> {
+        stream.check_collection::<(K, T)>(size, None)?;
         let mut map = HashMap::with_capacity(min(size, 128));
         for _ in 0..size {
             let key = stream.read_unchecked(end)?;
@@ -718,10 +789,14 @@ impl<'a, T: BitRead<'a, E>, E: Endianness> BitRead<'a, E> for LazyBitRead<'a, T,
     }
 }
 
+impl<'a, T: BitRead<'a, E> + BitSize, E: Endianness> BitSize for LazyBitRead<'a, T, E> {
+    const BIT_SIZE: Option<usize> = T::BIT_SIZE;
+}
+
 #[derive(Clone, Debug)]
 /// Struct that lazily reads it's contents from the stream
 pub struct LazyBitReadSized<'a, T: BitReadSized<'a, E>, E: Endianness> {
-    source: RefCell<BitReadStream<'a, E>>,
+    source: BitReadStream<'a, E>,
     size: usize,
     inner_type: PhantomData<T>,
 }
@@ -730,7 +805,8 @@ impl<'a, T: BitReadSized<'a, E>, E: Endianness> LazyBitReadSized<'a, T, E> {
     #[inline]
     /// Get the contents of the lazy struct
     pub fn value(self) -> Result<T> {
-        self.source.borrow_mut().read_sized::<T>(self.size)
+        let mut source = self.source;
+        source.read_sized::<T>(self.size)
     }
 }
 
@@ -739,7 +815,7 @@ impl<'a, T: BitReadSized<'a, E>, E: Endianness> BitReadSized<'a, E> for LazyBitR
     fn read(stream: &mut BitReadStream<'a, E>, size: usize) -> Result<Self> {
         match T::bit_size_sized(size) {
             Some(bit_size) => Ok(LazyBitReadSized {
-                source: RefCell::new(stream.read_bits(bit_size)?),
+                source: stream.read_bits(bit_size)?,
                 inner_type: PhantomData,
                 size,
             }),
@@ -753,28 +829,37 @@ impl<'a, T: BitReadSized<'a, E>, E: Endianness> BitReadSized<'a, E> for LazyBitR
     }
 }
 
+impl<'a, T, E, const SIZE: usize> BitSizeSized<SIZE> for LazyBitReadSized<'a, T, E>
+where
+    T: BitReadSized<'a, E> + BitSizeSized<SIZE>,
+    E: Endianness,
+{
+    const BIT_SIZE: Option<usize> = T::BIT_SIZE;
+}
+
 impl<'a, E: Endianness, T: BitReadSized<'a, E>, const N: usize> BitReadSized<'a, E> for [T; N] {
     #[inline]
     fn read(stream: &mut BitReadStream<'a, E>, size: usize) -> Result<Self> {
-        match T::bit_size_sized(size) {
-            Some(bit_size) => {
-                let end = stream.check_read(bit_size * N)?;
-                unsafe { Self::read_unchecked(stream, size, end) }
-            }
-            None => {
-                // SAFETY: An uninitialized `[MaybeUninit<_>; LEN]` is valid.
-                let mut array =
-                    unsafe { MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init() };
-                for item in array.iter_mut() {
-                    unsafe {
-                        // length is already checked
-                        let val = stream.read_sized(size)?;
-                        item.as_mut_ptr().write(val)
-                    }
-                }
-                unsafe { Ok((&array as *const _ as *const [T; N]).read()) }
+        if let Some(Ok(end)) =
+            T::bit_size_sized(size).map(|bit_size| stream.check_read(bit_size * N))
+        {
+            let start = stream.pos();
+            match unsafe { Self::read_unchecked(stream, size, end) } {
+                Ok(array) => return Ok(array),
+                // read again item by item to find the item that fails
+                Err(_) => stream.set_pos(start)?,
             }
         }
+        // read item by item if there isn't enough data, to find the item that fails
+
+        // SAFETY: An uninitialized `[MaybeUninit<_>; LEN]` is valid.
+        let mut array = unsafe { MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init() };
+        for (index, item) in array.iter_mut().enumerate() {
+            let val = stream
+                .read_with_context(PathSegment::Index(index), |stream| stream.read_sized(size))?;
+            unsafe { item.as_mut_ptr().write(val) }
+        }
+        unsafe { Ok((&array as *const _ as *const [T; N]).read()) }
     }
 
     #[inline]
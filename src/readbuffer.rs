@@ -1,26 +1,25 @@
 use std::cmp::min;
 use std::fmt;
 use std::fmt::Debug;
-use std::marker::PhantomData;
 use std::mem::size_of;
 use std::ops::{BitOrAssign, BitXor, Index, Range, RangeFrom};
 
 use num_traits::{Float, PrimInt};
 
-use crate::endianness::Endianness;
+use crate::endianness::{has_mixed_order, swap_bytes, Endianness, StaticEndianness};
 use crate::num_traits::{IsSigned, UncheckedPrimitiveFloat, UncheckedPrimitiveInt};
 use crate::{BitError, Result};
 use std::borrow::{Borrow, Cow};
 use std::convert::TryInto;
-use std::rc::Rc;
+use std::sync::Arc;
 
 const USIZE_SIZE: usize = size_of::<usize>();
 const USIZE_BIT_SIZE: usize = USIZE_SIZE * 8;
 
-// Cow<[u8]> but with cheap clones using Rc
+// Cow<[u8]> but with cheap clones using Arc
 pub(crate) enum Data<'a> {
     Borrowed(&'a [u8]),
-    Owned(Rc<[u8]>),
+    Owned(Arc<[u8]>),
 }
 
 impl<'a> Data<'a> {
@@ -37,8 +36,8 @@ impl<'a> Data<'a> {
 
     pub fn to_owned(&self) -> Data<'static> {
         let bytes = match self {
-            Data::Borrowed(bytes) => Rc::from(bytes.to_vec()),
-            Data::Owned(bytes) => Rc::clone(bytes),
+            Data::Borrowed(bytes) => Arc::from(bytes.to_vec()),
+            Data::Owned(bytes) => Arc::clone(bytes),
         };
         Data::Owned(bytes)
     }
@@ -72,7 +71,7 @@ impl<'a> Clone for Data<'a> {
     fn clone(&self) -> Self {
         match self {
             Data::Borrowed(bytes) => Data::Borrowed(bytes),
-            Data::Owned(bytes) => Data::Owned(Rc::clone(bytes)),
+            Data::Owned(bytes) => Data::Owned(Arc::clone(bytes)),
         }
     }
 }
@@ -102,7 +101,7 @@ where
 {
     pub(crate) bytes: Data<'a>,
     bit_len: usize,
-    endianness: PhantomData<E>,
+    endianness: E,
     slice: &'a [u8],
 }
 
@@ -123,13 +122,13 @@ where
     /// ];
     /// let buffer = BitReadBuffer::new(&bytes, LittleEndian);
     /// ```
-    pub fn new(bytes: &'a [u8], _endianness: E) -> Self {
+    pub fn new(bytes: &'a [u8], endianness: E) -> Self {
         let byte_len = bytes.len();
 
         BitReadBuffer {
             bytes: Data::Borrowed(bytes),
             bit_len: byte_len * 8,
-            endianness: PhantomData,
+            endianness,
             slice: bytes,
         }
     }
@@ -150,7 +149,7 @@ where
         BitReadBuffer {
             bytes,
             bit_len: byte_len * 8,
-            endianness: PhantomData,
+            endianness: self.endianness,
             slice,
         }
     }
@@ -173,9 +172,9 @@ where
     /// ];
     /// let buffer = BitReadBuffer::new_owned(bytes, LittleEndian);
     /// ```
-    pub fn new_owned(bytes: Vec<u8>, _endianness: E) -> Self {
+    pub fn new_owned(bytes: Vec<u8>, endianness: E) -> Self {
         let byte_len = bytes.len();
-        let bytes = Data::Owned(Rc::from(bytes));
+        let bytes = Data::Owned(Arc::from(bytes));
 
         // this is safe because
         //  - the slice can only be access trough this struct
@@ -186,21 +185,23 @@ where
         BitReadBuffer {
             bytes,
             bit_len: byte_len * 8,
-            endianness: PhantomData,
+            endianness,
             slice,
         }
     }
 }
 
 pub(crate) fn get_bits_from_usize<E: Endianness>(
+    endianness: E,
     val: usize,
     bit_offset: usize,
     count: usize,
 ) -> usize {
-    let shifted = if E::is_le() {
+    let shifted = if endianness.bit_order().is_lsb_first() {
         val >> bit_offset
     } else {
-        val >> (usize::BITS as usize - bit_offset - count)
+        // when reading 0 bits the shift is the full width of the usize, the mask below takes care of that case
+        val.wrapping_shr((usize::BITS as usize - bit_offset - count) as u32)
     };
     let mask = !(usize::MAX << count);
     shifted & mask
@@ -220,6 +221,11 @@ where
         self.slice.len()
     }
 
+    /// The endianness of the buffer
+    pub fn endianness(&self) -> E {
+        self.endianness
+    }
+
     unsafe fn read_usize_bytes(&self, byte_index: usize, end: bool) -> [u8; USIZE_SIZE] {
         if end {
             let mut bytes = [0; USIZE_SIZE];
@@ -252,13 +258,13 @@ where
 
         let bytes: [u8; USIZE_SIZE] = self.read_usize_bytes(byte_index, end);
 
-        let container = if E::is_le() {
+        let container = if self.endianness.bit_order().is_lsb_first() {
             usize::from_le_bytes(bytes)
         } else {
             usize::from_be_bytes(bytes)
         };
 
-        get_bits_from_usize::<E>(container, bit_offset, count)
+        get_bits_from_usize(self.endianness, container, bit_offset, count)
     }
 
     /// Read a single bit from the buffer as boolean
@@ -292,7 +298,7 @@ where
         let bit_offset = position & 7;
 
         if let Some(byte) = self.slice.get(byte_index) {
-            if E::is_le() {
+            if self.endianness.bit_order().is_lsb_first() {
                 let shifted = byte >> bit_offset as u8;
                 Ok(shifted & 1u8 == 1)
             } else {
@@ -314,7 +320,7 @@ where
         let bit_offset = position & 7;
 
         let byte = self.slice.get_unchecked(byte_index);
-        if E::is_le() {
+        if self.endianness.bit_order().is_lsb_first() {
             let shifted = byte >> bit_offset;
             shifted & 1u8 == 1
         } else {
@@ -392,13 +398,11 @@ where
     {
         let type_bit_size = size_of::<T>() * 8;
 
-        let bit_offset = position & 7;
-
-        let fit_usize = count + bit_offset < usize::BITS as usize;
-        let value = if fit_usize {
-            self.read_fit_usize(position, count, end)
+        let value = self.read_bits_unchecked(position, count, end);
+        let value = if has_mixed_order(self.endianness) && count > 8 {
+            swap_bytes(value, count, true)
         } else {
-            self.read_no_fit_usize(position, count, end)
+            value
         };
 
         if count == type_bit_size {
@@ -408,6 +412,22 @@ where
         }
     }
 
+    /// Read the bits in the order of the stream, without reordering bytes for mixed bit and byte orders
+    #[inline]
+    unsafe fn read_bits_unchecked<T>(&self, position: usize, count: usize, end: bool) -> T
+    where
+        T: PrimInt + BitOrAssign + IsSigned + UncheckedPrimitiveInt,
+    {
+        let bit_offset = position & 7;
+
+        let fit_usize = count + bit_offset < usize::BITS as usize;
+        if fit_usize {
+            self.read_fit_usize(position, count, end)
+        } else {
+            self.read_no_fit_usize(position, count, end)
+        }
+    }
+
     #[inline]
     unsafe fn read_fit_usize<T>(&self, position: usize, count: usize, end: bool) -> T
     where
@@ -430,7 +450,7 @@ where
             let bits_left = self.bit_len() - read_pos;
             let read = min(min(left_to_read, max_read), bits_left);
             let data = T::from_unchecked(self.read_usize(read_pos, read, end));
-            if E::is_le() {
+            if self.endianness.bit_order().is_lsb_first() {
                 acc |= data << bit_offset;
             } else {
                 acc = acc << read;
@@ -523,14 +543,11 @@ where
         let mut byte_left = byte_count;
         let mut read_pos = position / 8;
 
-        if E::is_le() {
+        if self.endianness.bit_order().is_lsb_first() {
             while byte_left > USIZE_SIZE - 1 {
-                let raw = self.read_shifted_usize(read_pos, shift, false);
-                let bytes = if E::is_le() {
-                    raw.to_le_bytes()
-                } else {
-                    raw.to_be_bytes()
-                };
+                let bytes = self
+                    .read_shifted_usize(read_pos, shift, false)
+                    .to_le_bytes();
                 let read_bytes = USIZE_SIZE - 1;
                 let usable_bytes = &bytes[0..read_bytes];
                 data.extend_from_slice(usable_bytes);
@@ -640,7 +657,7 @@ where
             ))
         } else {
             let mut acc = Vec::with_capacity(32);
-            if E::is_le() {
+            if self.endianness.bit_order().is_lsb_first() {
                 let mut byte_index = position / 8;
                 loop {
                     // note: if less then a usize worth of data is left in the buffer, read_usize_bytes
@@ -744,13 +761,40 @@ where
             let bytes = self.slice[byte_pos..byte_pos + size_of::<T>()]
                 .try_into()
                 .unwrap();
-            T::from_bytes::<E>(bytes)
+            T::from_bytes(bytes, self.endianness)
         } else {
             let int = self.read_int_unchecked(position, size_of::<T>() * 8, end);
             T::from_int(int)
         }
     }
 
+    /// Count the number of consecutive zero bits starting at the position
+    ///
+    /// The bits are scanned a usize at a time instead of bit by bit
+    pub(crate) fn count_zeros(&self, position: usize) -> Result<usize> {
+        let mut pos = position;
+        loop {
+            if pos >= self.bit_len() {
+                return Err(BitError::NotEnoughData {
+                    requested: pos - position + 1,
+                    bits_left: self.bit_len().saturating_sub(position),
+                });
+            }
+            let count = min(USIZE_BIT_SIZE - 8, self.bit_len() - pos);
+            // safe because count is limited to the bits left in the buffer
+            let chunk: usize = unsafe { self.read_bits_unchecked(pos, count, true) };
+            let zeros = if self.endianness.bit_order().is_lsb_first() {
+                chunk.trailing_zeros() as usize
+            } else {
+                chunk.leading_zeros() as usize - (USIZE_BIT_SIZE - count)
+            };
+            if zeros < count {
+                return Ok(pos + zeros - position);
+            }
+            pos += count;
+        }
+    }
+
     pub(crate) fn get_sub_buffer(&self, bit_len: usize) -> Result<Self> {
         if bit_len > self.bit_len() {
             return Err(BitError::NotEnoughData {
@@ -762,11 +806,21 @@ where
         Ok(BitReadBuffer {
             bytes: self.bytes.clone(),
             bit_len,
-            endianness: PhantomData,
+            endianness: self.endianness,
             slice: self.slice,
         })
     }
 
+    /// Create a buffer for the same data that reads with a different endianness
+    pub(crate) fn with_endianness<E2: Endianness>(&self, endianness: E2) -> BitReadBuffer<'a, E2> {
+        BitReadBuffer {
+            bytes: self.bytes.clone(),
+            bit_len: self.bit_len,
+            endianness,
+            slice: self.slice,
+        }
+    }
+
     /// Truncate the buffer to a given bit length
     pub fn truncate(&mut self, bit_len: usize) -> Result<()> {
         if bit_len > self.bit_len() {
@@ -781,13 +835,13 @@ where
     }
 }
 
-impl<'a, E: Endianness> From<&'a [u8]> for BitReadBuffer<'a, E> {
+impl<'a, E: StaticEndianness> From<&'a [u8]> for BitReadBuffer<'a, E> {
     fn from(bytes: &'a [u8]) -> Self {
         BitReadBuffer::new(bytes, E::endianness())
     }
 }
 
-impl<'a, E: Endianness> From<Vec<u8>> for BitReadBuffer<'a, E> {
+impl<'a, E: StaticEndianness> From<Vec<u8>> for BitReadBuffer<'a, E> {
     fn from(bytes: Vec<u8>) -> Self {
         BitReadBuffer::new_owned(bytes, E::endianness())
     }
@@ -798,7 +852,7 @@ impl<'a, E: Endianness> Clone for BitReadBuffer<'a, E> {
         BitReadBuffer {
             bytes: self.bytes.clone(),
             bit_len: self.bit_len(),
-            endianness: PhantomData,
+            endianness: self.endianness,
             slice: self.slice,
         }
     }
@@ -810,7 +864,7 @@ impl<E: Endianness> Debug for BitReadBuffer<'_, E> {
             f,
             "BitBuffer {{ bit_len: {}, endianness: {} }}",
             self.bit_len(),
-            E::as_string()
+            self.endianness.as_string()
         )
     }
 }
@@ -861,7 +915,7 @@ fn contains_zero_byte_non_top(x: usize) -> bool {
 use serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
 
 #[cfg(feature = "serde")]
-impl<'a, E: Endianness> Serialize for BitReadBuffer<'a, E> {
+impl<'a, E: Endianness + Serialize> Serialize for BitReadBuffer<'a, E> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -869,18 +923,30 @@ impl<'a, E: Endianness> Serialize for BitReadBuffer<'a, E> {
         let mut data = self.read_bytes(0, self.bit_len() / 8).unwrap().to_vec();
         let bits_left = self.bit_len() % 8;
         if bits_left > 0 {
-            data.push(self.read_int((self.bit_len() / 8) * 8, bits_left).unwrap());
+            let last: u8 = self.read_int((self.bit_len() / 8) * 8, bits_left).unwrap();
+            // the remaining bits have to end up at the start of the byte
+            if self.endianness.bit_order().is_lsb_first() {
+                data.push(last);
+            } else {
+                data.push(last << (8 - bits_left));
+            }
         }
 
         let mut s = serializer.serialize_struct("BitReadBuffer", 3)?;
         s.serialize_field("data", &data)?;
         s.serialize_field("bit_length", &self.bit_len())?;
+        // the endianness is only stored when it can't be derived from the type
+        if E::is_static() {
+            s.skip_field("endianness")?;
+        } else {
+            s.serialize_field("endianness", &self.endianness)?;
+        }
         s.end()
     }
 }
 
 #[cfg(feature = "serde")]
-impl<'de, E: Endianness> Deserialize<'de> for BitReadBuffer<'static, E> {
+impl<'de, E: Endianness + Deserialize<'de>> Deserialize<'de> for BitReadBuffer<'static, E> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
@@ -891,11 +957,22 @@ impl<'de, E: Endianness> Deserialize<'de> for BitReadBuffer<'static, E> {
             bit_length: usize,
         }
 
-        let data = BitData::deserialize(deserializer)?;
-        let mut buffer = BitReadBuffer::new_owned(data.data, E::endianness());
-        buffer
-            .truncate(data.bit_length)
-            .map_err(de::Error::custom)?;
+        #[derive(Deserialize)]
+        struct DynamicBitData<E> {
+            data: Vec<u8>,
+            bit_length: usize,
+            endianness: E,
+        }
+
+        let (data, bit_length, endianness) = if E::is_static() {
+            let data = BitData::deserialize(deserializer)?;
+            (data.data, data.bit_length, E::endianness())
+        } else {
+            let data = DynamicBitData::deserialize(deserializer)?;
+            (data.data, data.bit_length, data.endianness)
+        };
+        let mut buffer = BitReadBuffer::new_owned(data, endianness);
+        buffer.truncate(bit_length).map_err(de::Error::custom)?;
         Ok(buffer)
     }
 }
@@ -914,3 +991,23 @@ fn test_serde_roundtrip() {
 
     assert_eq!(result, buffer);
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_roundtrip_dynamic() {
+    use crate::DynamicEndian;
+
+    let non_native = match DynamicEndian::native() {
+        DynamicEndian::Little => DynamicEndian::Big,
+        DynamicEndian::Big => DynamicEndian::Little,
+    };
+    let mut buffer = BitReadBuffer::new_owned(vec![55; 8], non_native);
+    buffer.truncate(61).unwrap();
+
+    let json = serde_json::to_string(&buffer).unwrap();
+
+    let result: BitReadBuffer<DynamicEndian> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(result.endianness(), non_native);
+    assert_eq!(result, buffer);
+}
@@ -1,15 +1,19 @@
+use std::fmt::UpperHex;
 use std::mem::size_of;
 use std::ops::BitOrAssign;
 
 use num_traits::{Float, PrimInt};
 
-use crate::endianness::Endianness;
+use crate::endianness::{Endianness, StaticEndianness};
 use crate::num_traits::{IsSigned, UncheckedPrimitiveFloat, UncheckedPrimitiveInt};
 use crate::readbuffer::Data;
+use crate::varint::zigzag_decode;
 use crate::BitReadBuffer;
-use crate::{BitError, BitRead, BitReadSized, Result};
+use crate::{BitError, BitRead, BitReadSized, LimitKind, Padding, PathSegment, ReadLimits, Result};
 use std::borrow::Cow;
 use std::cmp::min;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 
 /// Stream that provides an easy way to iterate trough a [`BitBuffer`]
 ///
@@ -35,6 +39,12 @@ where
     buffer: BitReadBuffer<'a, E>,
     start_pos: usize,
     pos: usize,
+    limits: ReadLimits,
+    // number of bytes allocated by reads, checked against the allocation limit
+    // shared with sub streams and clones so they can't be used to get around the limit
+    allocated: Arc<AtomicUsize>,
+    // set when a read depends on where the buffered data ends, for streams over partially buffered input
+    end_observed: Option<Arc<AtomicBool>>,
 }
 
 impl<'a, E> BitReadStream<'a, E>
@@ -62,6 +72,9 @@ where
             start_pos: 0,
             pos: 0,
             buffer,
+            limits: ReadLimits::UNLIMITED,
+            allocated: Arc::new(AtomicUsize::new(0)),
+            end_observed: None,
         }
     }
 
@@ -94,7 +107,7 @@ where
     /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
     #[inline]
     pub fn read_bool(&mut self) -> Result<bool> {
-        let result = self.buffer.read_bool(self.pos);
+        let result = self.observe_end_on_err(self.buffer.read_bool(self.pos));
         if result.is_ok() {
             self.pos += 1;
         }
@@ -143,13 +156,86 @@ where
     where
         T: PrimInt + BitOrAssign + IsSigned + UncheckedPrimitiveInt,
     {
-        let result = self.buffer.read_int(self.pos, count);
+        let result = self.observe_end_on_err(self.buffer.read_int(self.pos, count));
         if result.is_ok() {
             self.pos += count;
         }
         result
     }
 
+    /// Read a sequence of bits from the stream as integer, using the provided endianness instead of the endianness of the stream
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::TooManyBits`]: to many bits requested for the chosen integer type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = [0x12, 0x34, 0x12, 0x34];
+    /// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    /// assert_eq!(stream.read_int::<u16>(16)?, 0x3412);
+    /// assert_eq!(stream.read_int_endian::<u16, _>(16, BigEndian)?, 0x1234);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::TooManyBits`]: enum.ReadError.html#variant.TooManyBits
+    #[inline]
+    pub fn read_int_endian<T, E2: Endianness>(&mut self, count: usize, endianness: E2) -> Result<T>
+    where
+        T: PrimInt + BitOrAssign + IsSigned + UncheckedPrimitiveInt,
+    {
+        self.with_endianness(endianness, |stream| stream.read_int(count))
+    }
+
+    /// Read from the stream using a different endianness
+    ///
+    /// The closure is called with a stream reading the same data with the provided endianness,
+    /// the position of this stream is advanced by the number of bits read by the closure.
+    ///
+    /// Since the endianness also determines the order of the bits within a byte, switching endianness
+    /// is only meaningful at byte boundaries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = [0x12, 0x34, 0x56, 0x78];
+    /// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    /// let value: u32 = stream.with_endianness(BigEndian, |stream| stream.read())?;
+    /// assert_eq!(value, 0x12345678);
+    /// assert_eq!(stream.pos(), 32);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn with_endianness<E2, T, F>(&mut self, endianness: E2, read: F) -> T
+    where
+        E2: Endianness,
+        F: FnOnce(&mut BitReadStream<'a, E2>) -> T,
+    {
+        let mut stream = BitReadStream {
+            buffer: self.buffer.with_endianness(endianness),
+            start_pos: self.start_pos,
+            pos: self.pos,
+            limits: self.limits,
+            allocated: self.allocated.clone(),
+            end_observed: self.end_observed.clone(),
+        };
+        let result = read(&mut stream);
+        self.pos = stream.pos;
+        result
+    }
+
     #[doc(hidden)]
     #[inline]
     pub unsafe fn read_int_unchecked<T>(&mut self, count: usize, end: bool) -> T
@@ -161,6 +247,119 @@ where
         result
     }
 
+    /// Read a variable length integer from the stream
+    ///
+    /// The integer is read as LEB128, where every byte holds 7 bits of the integer and the
+    /// top bit of the byte marks whether more bytes follow. This is the same encoding as
+    /// used by protobuf and by the `ReadVarInt32` and `ReadVarInt64` methods of the source engine.
+    ///
+    /// Signed integers are zigzag decoded, mapping `0, 1, 2, 3, 4` to `0, -1, 1, -2, 2`
+    ///
+    /// The bytes don't need to be aligned to the byte boundaries of the stream.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::VarIntOverflow`]: the encoded integer doesn't fit in the chosen integer type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b1010_1100, 0b0000_0010, 0b0000_0011];
+    /// let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_varint::<u32>()?, 300);
+    /// assert_eq!(stream.pos(), 16);
+    /// assert_eq!(stream.read_varint::<i32>()?, -2);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::VarIntOverflow`]: enum.ReadError.html#variant.VarIntOverflow
+    #[inline]
+    pub fn read_varint<T>(&mut self) -> Result<T>
+    where
+        T: PrimInt + BitOrAssign + IsSigned + UncheckedPrimitiveInt,
+    {
+        let raw = self.try_read_with(Self::read_varint_raw::<T>)?;
+        if T::is_signed() {
+            Ok(zigzag_decode(raw))
+        } else {
+            Ok(raw)
+        }
+    }
+
+    fn read_varint_raw<T>(&mut self) -> Result<T>
+    where
+        T: PrimInt + BitOrAssign + IsSigned + UncheckedPrimitiveInt,
+    {
+        let type_bit_size = size_of::<T>() * 8;
+        let mut acc = T::zero();
+        let mut shift = 0;
+        loop {
+            let byte: u8 = self.read_int(8)?;
+            let data = byte & 0x7F;
+            let data_bits = 8 - data.leading_zeros() as usize;
+            if shift >= type_bit_size || shift + data_bits > type_bit_size {
+                return Err(BitError::VarIntOverflow { max: type_bit_size });
+            }
+            acc |= T::from_unchecked(data) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(acc);
+            }
+        }
+    }
+
+    /// Read a prefix encoded variable length integer from the stream
+    ///
+    /// This reads integers as encoded by the `ReadUBitVar` method of the source engine, a 6 bit header
+    /// contains the lowest 4 bits of the integer and 2 bits selecting whether 0, 4, 8 or 28
+    /// more bits of the integer follow.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b1101_1010, 0b0000_0010];
+    /// let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    /// let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.read_ubit_var()?, 0b1011_1010);
+    /// assert_eq!(stream.pos(), 10);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    #[inline]
+    pub fn read_ubit_var(&mut self) -> Result<u32> {
+        self.try_read_with(Self::read_ubit_var_inner)
+    }
+
+    fn read_ubit_var_inner(&mut self) -> Result<u32> {
+        let head: u32 = self.read_int(6)?;
+        let extra_bits = match head & 0b11_0000 {
+            0b01_0000 => 4,
+            0b10_0000 => 8,
+            0b11_0000 => 28,
+            _ => return Ok(head),
+        };
+        let rest: u32 = self.read_int(extra_bits)?;
+        Ok((head & 0b1111) | (rest << 4))
+    }
+
     /// Read a sequence of bits from the stream as float
     ///
     /// # Errors
@@ -193,7 +392,7 @@ where
         T: Float + UncheckedPrimitiveFloat,
     {
         let count = size_of::<T>() * 8;
-        let result = self.buffer.read_float(self.pos);
+        let result = self.observe_end_on_err(self.buffer.read_float(self.pos));
         if result.is_ok() {
             self.pos += count;
         }
@@ -242,7 +441,7 @@ where
     #[inline]
     pub fn read_bytes(&mut self, byte_count: usize) -> Result<Cow<'a, [u8]>> {
         let count = byte_count * 8;
-        let result = self.buffer.read_bytes(self.pos, byte_count);
+        let result = self.observe_end_on_err(self.buffer.read_bytes(self.pos, byte_count));
         if result.is_ok() {
             self.pos += count;
         }
@@ -302,11 +501,13 @@ where
     /// [`ReadError::Utf8Error`]: enum.ReadError.html#variant.Utf8Error
     #[inline]
     pub fn read_string(&mut self, byte_len: Option<usize>) -> Result<Cow<'a, str>> {
-        let max_length = self.bits_left() / 8;
+        if let Some(len) = byte_len {
+            self.check_string_length(len)?;
+        }
+        let max_length = self.remaining() / 8;
 
         let result = self
-            .buffer
-            .read_string(self.pos, byte_len)
+            .observe_end_on_err(self.buffer.read_string(self.pos, byte_len))
             .map_err(|mut err| {
                 // still advance the stream on malformed utf8
                 if let BitError::Utf8Error(_, len) = &mut err {
@@ -320,13 +521,17 @@ where
             })?;
         let read = match byte_len {
             Some(len) => len * 8,
-            None => (result.len() + 1) * 8,
+            None => {
+                self.check_string_length(result.len())?;
+                (result.len() + 1) * 8
+            }
         };
 
         // due to how sub buffer/streams work, the result string can be longer than the current stream
         // (but not the top level buffer)
         // thus we trim the resulting string to make sure it fits in the source stream
-        if read > self.bits_left() {
+        if read > self.remaining() {
+            self.observe_end();
             // find the maximum well-formed utf8 string that fits in max_len
             let mut acc = String::new();
             for c in result.chars() {
@@ -375,9 +580,12 @@ where
     /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
     pub fn read_bits(&mut self, count: usize) -> Result<Self> {
         let result = BitReadStream {
-            buffer: self.buffer.get_sub_buffer(self.pos + count)?,
+            buffer: self.observe_end_on_err(self.buffer.get_sub_buffer(self.pos + count))?,
             start_pos: self.pos,
             pos: self.pos,
+            limits: self.limits,
+            allocated: self.allocated.clone(),
+            end_observed: None,
         };
         self.pos += count;
         Ok(result)
@@ -411,17 +619,146 @@ where
     ///
     /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
     pub fn skip_bits(&mut self, count: usize) -> Result<()> {
-        if count <= self.bits_left() {
+        if count <= self.remaining() {
             self.pos += count;
             Ok(())
         } else {
+            self.observe_end();
             Err(BitError::NotEnoughData {
                 requested: count,
-                bits_left: self.bits_left(),
+                bits_left: self.remaining(),
             })
         }
     }
 
+    /// Check if the current position of the stream is a multiple of 8 bits
+    ///
+    /// Like all alignment methods, the position is relative to the start of the stream, for streams
+    /// created with [`read_bits`](Self::read_bits) that is the position the sub stream was created at,
+    /// not the start of the underlying buffer. This matches [`BitWriteStream::is_aligned`](crate::BitWriteStream::is_aligned),
+    /// which is relative to the start of the write stream.
+    pub fn is_aligned(&self) -> bool {
+        self.pos().is_multiple_of(8)
+    }
+
+    /// Skip to the next position in the stream that is a multiple of `bits`, returning the number of skipped bits
+    ///
+    /// The position is relative to the start of the stream, the skipped padding bits are ignored,
+    /// use [`align_checked`](Self::align_checked) to verify the value of the padding.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// # let bytes = vec![
+    /// #     0b1011_0101, 0b0110_1010, 0b1010_1100, 0b1001_1001,
+    /// #     0b1001_1001, 0b1001_1001, 0b1001_1001, 0b1110_0111
+    /// # ];
+    /// # let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    /// # let mut stream = BitReadStream::new(buffer);
+    /// stream.skip_bits(3)?;
+    /// assert_eq!(stream.align(16)?, 13);
+    /// assert_eq!(stream.pos(), 16);
+    /// assert_eq!(stream.align(16)?, 0);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn align(&mut self, bits: usize) -> Result<usize> {
+        let padding = self.padding_to(bits);
+        self.skip_bits(padding)?;
+        Ok(padding)
+    }
+
+    /// Skip to the next byte boundary in the stream, returning the number of skipped bits
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn align_to_byte(&mut self) -> Result<usize> {
+        self.align(8)
+    }
+
+    /// Skip to the next position in the stream that is a multiple of `bits`, verifying that the skipped
+    /// padding bits match the expected padding
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::InvalidPadding`]: the skipped bits don't match the expected padding,
+    ///   the position of the stream is left unchanged
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BitError, BigEndian, Padding, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = vec![0b1010_0000, 0b1011_1111];
+    /// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+    /// assert_eq!(stream.read_int::<u8>(3)?, 0b101);
+    /// assert_eq!(stream.align_checked(8, Padding::Zeros)?, 5);
+    /// assert_eq!(stream.read_int::<u8>(2)?, 0b10);
+    /// assert!(matches!(
+    ///     stream.align_checked(8, Padding::Zeros),
+    ///     Err(BitError::InvalidPadding { pos: 10, bits: 6 })
+    /// ));
+    /// assert_eq!(stream.align_checked(8, Padding::Ones)?, 6);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::InvalidPadding`]: enum.ReadError.html#variant.InvalidPadding
+    pub fn align_checked(&mut self, bits: usize, expected: Padding) -> Result<usize> {
+        let padding = self.padding_to(bits);
+        let start = self.pos;
+        let mut remaining = padding;
+        while remaining > 0 {
+            let count = min(remaining, 64);
+            let value = match self.read_int::<u64>(count) {
+                Ok(value) => value,
+                Err(e) => {
+                    self.pos = start;
+                    return Err(e);
+                }
+            };
+            let expected_value = match expected {
+                Padding::Zeros => 0,
+                Padding::Ones => u64::MAX >> (64 - count),
+            };
+            if value != expected_value {
+                self.pos = start;
+                return Err(BitError::InvalidPadding {
+                    pos: start - self.start_pos,
+                    bits: padding,
+                });
+            }
+            remaining -= count;
+        }
+        Ok(padding)
+    }
+
+    /// The number of bits needed to get to the next multiple of `bits`
+    fn padding_to(&self, bits: usize) -> usize {
+        if bits == 0 {
+            0
+        } else {
+            (bits - self.pos() % bits) % bits
+        }
+    }
+
     /// Set the position of the stream
     ///
     /// # Errors
@@ -508,6 +845,11 @@ where
         self.pos - self.start_pos
     }
 
+    /// Get the endianness of the stream
+    pub fn endianness(&self) -> E {
+        self.buffer.endianness()
+    }
+
     /// Get the number of bits left in the stream
     ///
     /// # Examples
@@ -530,9 +872,162 @@ where
     /// # }
     /// ```
     pub fn bits_left(&self) -> usize {
+        self.observe_end();
+        self.remaining()
+    }
+
+    /// Get the number of bits left in the stream, for checks that don't depend on where the input ends
+    #[inline]
+    fn remaining(&self) -> usize {
         self.bit_len() - self.pos()
     }
 
+    /// Mark that the result of the current read depends on where the input ends
+    #[inline]
+    fn observe_end(&self) {
+        if let Some(end_observed) = &self.end_observed {
+            end_observed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Mark that the read depends on where the input ends if it failed by running past the end of the stream
+    #[inline]
+    fn observe_end_on_err<T>(&self, result: Result<T>) -> Result<T> {
+        if let Err(BitError::NotEnoughData { .. }) = &result {
+            self.observe_end();
+        }
+        result
+    }
+
+    pub(crate) fn set_end_observed(&mut self, end_observed: Arc<AtomicBool>) {
+        self.end_observed = Some(end_observed);
+    }
+
+    /// Set the limits for reading untrusted input
+    ///
+    /// Streams created from this stream, using [`read_bits`] for example, inherit the limits and
+    /// share the allocation budget of this stream, as do clones of the stream.
+    ///
+    /// See [`ReadLimits`] for details.
+    ///
+    /// [`read_bits`]: #method.read_bits
+    /// [`ReadLimits`]: struct.ReadLimits.html
+    pub fn set_limits(&mut self, limits: ReadLimits) {
+        self.limits = limits;
+    }
+
+    /// Get the limits for reading untrusted input
+    pub fn limits(&self) -> ReadLimits {
+        self.limits
+    }
+
+    /// Check that a collection of `count` items of type `T` can be read from the stream
+    ///
+    /// This checks the number of items against the configured collection length limit and
+    /// accounts the memory required for the items against the allocation limit.
+    /// If the number of bits per item is known, it also checks that the items can fit in the remaining data
+    /// before anything is allocated.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::LimitExceeded`]: the collection length or allocation limit is exceeded
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream to read all items
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BitError, LittleEndian, ReadLimits};
+    /// #
+    /// # let bytes = vec![0; 8];
+    /// # let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    /// stream.set_limits(ReadLimits {
+    ///     max_allocation: 1024,
+    ///     ..ReadLimits::default()
+    /// });
+    /// assert!(stream.check_collection::<u16>(4, Some(16)).is_ok());
+    /// assert!(matches!(
+    ///     stream.check_collection::<u16>(1_000_000, Some(16)),
+    ///     Err(BitError::NotEnoughData { .. })
+    /// ));
+    /// assert!(matches!(
+    ///     stream.check_collection::<String>(1_000_000, None),
+    ///     Err(BitError::LimitExceeded { .. })
+    /// ));
+    /// ```
+    ///
+    /// [`ReadError::LimitExceeded`]: enum.ReadError.html#variant.LimitExceeded
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    pub fn check_collection<T>(&mut self, count: usize, item_bits: Option<usize>) -> Result<()> {
+        if count > self.limits.max_collection_length {
+            return Err(BitError::LimitExceeded {
+                limit: LimitKind::CollectionLength,
+                requested: count,
+                max: self.limits.max_collection_length,
+            });
+        }
+        if let Some(item_bits) = item_bits {
+            let requested = item_bits.saturating_mul(count);
+            if requested > self.remaining() {
+                self.observe_end();
+                return Err(BitError::NotEnoughData {
+                    requested,
+                    bits_left: self.remaining(),
+                });
+            }
+        }
+        self.reserve_allocation(count.saturating_mul(size_of::<T>()))
+    }
+
+    /// Account for `bytes` of memory being allocated while reading from the stream
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::LimitExceeded`]: the total allocation exceeds the configured allocation limit
+    ///
+    /// [`ReadError::LimitExceeded`]: enum.ReadError.html#variant.LimitExceeded
+    pub fn reserve_allocation(&mut self, bytes: usize) -> Result<()> {
+        let max = self.limits.max_allocation;
+        self.allocated
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |allocated| {
+                let requested = allocated.saturating_add(bytes);
+                (requested <= max).then_some(requested)
+            })
+            .map(|_| ())
+            .map_err(|allocated| BitError::LimitExceeded {
+                limit: LimitKind::Allocation,
+                requested: allocated.saturating_add(bytes),
+                max,
+            })
+    }
+
+    pub(crate) fn set_allocated(&mut self, allocated: Arc<AtomicUsize>) {
+        self.allocated = allocated;
+    }
+
+    /// Check that another item can be added to a collection of `len` items with an unknown final length
+    fn check_next_item<T>(&mut self, len: usize) -> Result<()> {
+        if len >= self.limits.max_collection_length {
+            return Err(BitError::LimitExceeded {
+                limit: LimitKind::CollectionLength,
+                requested: len + 1,
+                max: self.limits.max_collection_length,
+            });
+        }
+        self.reserve_allocation(size_of::<T>())
+    }
+
+    fn check_string_length(&self, len: usize) -> Result<()> {
+        if len > self.limits.max_string_length {
+            Err(BitError::LimitExceeded {
+                limit: LimitKind::StringLength,
+                requested: len,
+                max: self.limits.max_string_length,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
     /// Read a value based on the provided type
     ///
     /// # Examples
@@ -650,13 +1145,385 @@ where
         T::read_unchecked(self, size, end)
     }
 
+    /// Read items until an item equal to `terminator` is read
+    ///
+    /// The terminator is consumed from the stream but not included in the result.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: the stream ends before the terminator is read
+    /// - [`ReadError::LimitExceeded`]: the collection length or allocation limit is exceeded
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = [1, 2, 3, 0, 5];
+    /// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    /// let items: Vec<u8> = stream.read_until(&0)?;
+    /// assert_eq!(items, vec![1, 2, 3]);
+    /// assert_eq!(stream.pos(), 32);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::LimitExceeded`]: enum.ReadError.html#variant.LimitExceeded
+    pub fn read_until<T: BitRead<'a, E> + PartialEq>(&mut self, terminator: &T) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        loop {
+            let item: T =
+                self.read_with_context(PathSegment::Index(items.len()), |stream| stream.read())?;
+            if &item == terminator {
+                return Ok(items);
+            }
+            self.check_next_item::<T>(items.len())?;
+            items.push(item);
+        }
+    }
+
+    /// Read items until the end of the stream
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: the remaining bits in the stream don't form a complete item
+    /// - [`ReadError::LimitExceeded`]: the collection length or allocation limit is exceeded
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = [1, 0, 2, 0];
+    /// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    /// let items: Vec<u16> = stream.read_until_eof()?;
+    /// assert_eq!(items, vec![1, 2]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::LimitExceeded`]: enum.ReadError.html#variant.LimitExceeded
+    pub fn read_until_eof<T: BitRead<'a, E>>(&mut self) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        while self.bits_left() > 0 {
+            self.check_next_item::<T>(items.len())?;
+            let item =
+                self.read_with_context(PathSegment::Index(items.len()), |stream| stream.read())?;
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    /// Read items that are each preceded by a continuation bit
+    ///
+    /// Items are read as long as the bit before them is set, the list is terminated by an unset bit.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: the stream ends before the terminating bit is read
+    /// - [`ReadError::LimitExceeded`]: the collection length or allocation limit is exceeded
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = [0b1011_1011, 0b0000_0000];
+    /// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+    /// let items: Vec<bool> = stream.read_continued()?;
+    /// assert_eq!(items, vec![false, true, false, true]);
+    /// assert_eq!(stream.pos(), 9);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::LimitExceeded`]: enum.ReadError.html#variant.LimitExceeded
+    pub fn read_continued<T: BitRead<'a, E>>(&mut self) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        while self.read_bool()? {
+            self.check_next_item::<T>(items.len())?;
+            let item =
+                self.read_with_context(PathSegment::Index(items.len()), |stream| stream.read())?;
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    /// Read a sequence of bits from the stream as integer without advancing the stream
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::TooManyBits`]: to many bits requested for the chosen integer type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// # let bytes = vec![
+    /// #     0b1011_0101, 0b0110_1010, 0b1010_1100, 0b1001_1001,
+    /// #     0b1001_1001, 0b1001_1001, 0b1001_1001, 0b1110_0111
+    /// # ];
+    /// # let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    /// # let mut stream = BitReadStream::new(buffer);
+    /// assert_eq!(stream.peek_int::<u8>(3)?, 0b101);
+    /// assert_eq!(stream.pos(), 0);
+    /// assert_eq!(stream.read_int::<u8>(3)?, 0b101);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::TooManyBits`]: enum.ReadError.html#variant.TooManyBits
+    #[inline]
+    pub fn peek_int<T>(&self, count: usize) -> Result<T>
+    where
+        T: PrimInt + BitOrAssign + IsSigned + UncheckedPrimitiveInt,
+    {
+        self.observe_end_on_err(self.buffer.read_int(self.pos, count))
+    }
+
+    /// Read a single bit from the stream as boolean without advancing the stream
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    #[inline]
+    pub fn peek_bool(&self) -> Result<bool> {
+        self.observe_end_on_err(self.buffer.read_bool(self.pos))
+    }
+
+    /// Read a value based on the provided type without advancing the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// # let bytes = vec![
+    /// #     0b1011_0101, 0b0110_1010, 0b1010_1100, 0b1001_1001,
+    /// #     0b1001_1001, 0b1001_1001, 0b1001_1001, 0b1110_0111
+    /// # ];
+    /// # let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    /// # let mut stream = BitReadStream::new(buffer);
+    /// let peeked: u16 = stream.peek()?;
+    /// assert_eq!(stream.pos(), 0);
+    /// assert_eq!(stream.read::<u16>()?, peeked);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn peek<T: BitRead<'a, E>>(&mut self) -> Result<T> {
+        let start = self.pos;
+        let result = self.read();
+        self.pos = start;
+        result
+    }
+
+    /// Read a magic byte sequence from the stream, failing if the read bytes don't match the expected bytes
+    ///
+    /// The position of the stream is not changed if the bytes don't match.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::InvalidMagic`]: the read bytes don't match the expected bytes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BitError, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = b"RIFF\x10\x00";
+    /// let mut stream = BitReadStream::new(BitReadBuffer::new(bytes, LittleEndian));
+    /// assert!(matches!(
+    ///     stream.read_magic_bytes(b"RIFX"),
+    ///     Err(BitError::InvalidMagic { .. })
+    /// ));
+    /// stream.read_magic_bytes(b"RIFF")?;
+    /// assert_eq!(stream.read_int::<u16>(16)?, 16);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::InvalidMagic`]: enum.ReadError.html#variant.InvalidMagic
+    pub fn read_magic_bytes(&mut self, expected: &[u8]) -> Result<()> {
+        self.try_read_with(|stream| {
+            let found = stream.read_bytes(expected.len())?;
+            if found.as_ref() == expected {
+                Ok(())
+            } else {
+                Err(BitError::InvalidMagic {
+                    expected: format!("b\"{}\"", expected.escape_ascii()),
+                    found: format!("b\"{}\"", found.escape_ascii()),
+                })
+            }
+        })
+    }
+
+    /// Read a magic integer from the stream, failing if the read value doesn't match the expected value
+    ///
+    /// The position of the stream is not changed if the value doesn't match.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReadError::NotEnoughData`]: not enough bits available in the stream
+    /// - [`ReadError::TooManyBits`]: to many bits requested for the chosen integer type
+    /// - [`ReadError::InvalidMagic`]: the read value doesn't match the expected value
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BitError, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let bytes = [0xCA, 0xFE, 0x01];
+    /// let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+    /// stream.read_magic_int(0xCAFEu16, 16)?;
+    /// assert!(matches!(
+    ///     stream.read_magic_int(0x02u8, 8),
+    ///     Err(BitError::InvalidMagic { .. })
+    /// ));
+    /// assert_eq!(stream.pos(), 16);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ReadError::NotEnoughData`]: enum.ReadError.html#variant.NotEnoughData
+    /// [`ReadError::TooManyBits`]: enum.ReadError.html#variant.TooManyBits
+    /// [`ReadError::InvalidMagic`]: enum.ReadError.html#variant.InvalidMagic
+    pub fn read_magic_int<T>(&mut self, expected: T, count: usize) -> Result<()>
+    where
+        T: PrimInt + BitOrAssign + IsSigned + UncheckedPrimitiveInt + UpperHex,
+    {
+        self.try_read_with(|stream| {
+            let found: T = stream.read_int(count)?;
+            if found == expected {
+                Ok(())
+            } else {
+                Err(BitError::InvalidMagic {
+                    expected: format!("{:#X}", expected),
+                    found: format!("{:#X}", found),
+                })
+            }
+        })
+    }
+
+    /// Run a read, adding the path of the field being read and the position it started at to any returned error
+    ///
+    /// This is used by the derived [`BitRead`] implementations to report which field failed to read,
+    /// manual implementations can use it to provide the same context.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BitError, LittleEndian, PathSegment};
+    /// #
+    /// # let bytes = vec![0b1011_0101, 0b0110_1010];
+    /// # let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    /// # let mut stream = BitReadStream::new(buffer);
+    /// let segment = PathSegment::Field {
+    ///     type_name: "Header",
+    ///     field: "length",
+    /// };
+    /// stream.skip_bits(8).unwrap();
+    /// let err = stream
+    ///     .read_with_context(segment, |stream| stream.read_int::<u16>(16))
+    ///     .unwrap_err();
+    /// assert!(matches!(err, BitError::Context { pos: 8, .. }));
+    /// assert!(err.to_string().starts_with("Header.length at bit 8: "));
+    /// ```
+    #[inline]
+    pub fn read_with_context<T, F>(&mut self, segment: PathSegment, read: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let start = self.pos;
+        read(self).map_err(|err| err.with_context(segment, start))
+    }
+
+    /// Run a series of reads, restoring the position of the stream if the closure returns an error
+    ///
+    /// This allows speculatively parsing data without having to clone the stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, BitReadStream, BitError, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// # let bytes = vec![
+    /// #     0b1011_0101, 0b0110_1010, 0b1010_1100, 0b1001_1001,
+    /// #     0b1001_1001, 0b1001_1001, 0b1001_1001, 0b1110_0111
+    /// # ];
+    /// # let buffer = BitReadBuffer::new(&bytes, LittleEndian);
+    /// # let mut stream = BitReadStream::new(buffer);
+    /// let result = stream.try_read_with(|stream| {
+    ///     let tag = stream.read_int::<u8>(4)?;
+    ///     if tag != 0b1111 {
+    ///         return Err(BitError::UnmatchedDiscriminant {
+    ///             discriminant: tag as usize,
+    ///             enum_name: "Tag".into(),
+    ///         });
+    ///     }
+    ///     stream.read_int::<u16>(12)
+    /// });
+    /// assert!(result.is_err());
+    /// assert_eq!(stream.pos(), 0);
+    ///
+    /// let value = stream.try_read_with(|stream| stream.read_int::<u16>(12))?;
+    /// assert_eq!(value, 0b1010_1011_0101);
+    /// assert_eq!(stream.pos(), 12);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn try_read_with<T, Err, F>(&mut self, read: F) -> Result<T, Err>
+    where
+        F: FnOnce(&mut Self) -> Result<T, Err>,
+    {
+        let start = self.pos;
+        let result = read(self);
+        if result.is_err() {
+            self.pos = start;
+        }
+        result
+    }
+
+    /// Count the number of consecutive zero bits at the current position without advancing the stream
+    pub(crate) fn count_zeros(&self) -> Result<usize> {
+        self.observe_end_on_err(self.buffer.count_zeros(self.pos))
+    }
+
     /// Check if we can read a number of bits from the stream
     pub fn check_read(&self, count: usize) -> Result<bool> {
-        if self.bits_left() < count + 64 {
-            if self.bits_left() < count {
+        if self.remaining() < count + 64 {
+            if self.remaining() < count {
+                self.observe_end();
                 Err(BitError::NotEnoughData {
                     requested: count,
-                    bits_left: self.bits_left(),
+                    bits_left: self.remaining(),
                 })
             } else {
                 Ok(true)
@@ -674,6 +1541,9 @@ where
                 buffer: self.buffer.to_owned(),
                 start_pos: self.pos,
                 pos: self.pos,
+                limits: self.limits,
+                allocated: self.allocated.clone(),
+                end_observed: self.end_observed.clone(),
             },
             Data::Borrowed(bytes) => {
                 // instead of calling buffer.to_owned blindly, we only copy the bytes that this stream covers
@@ -684,7 +1554,7 @@ where
                 let end = min(end, self.buffer.byte_len());
 
                 let sub_bytes = bytes[byte_pos..end].to_vec();
-                let buffer = BitReadBuffer::from(sub_bytes)
+                let buffer = BitReadBuffer::new_owned(sub_bytes, self.buffer.endianness())
                     .get_sub_buffer(self.buffer.bit_len() - self.start_pos + bit_offset)
                     .unwrap();
 
@@ -692,6 +1562,9 @@ where
                     buffer,
                     start_pos: bit_offset,
                     pos: bit_offset + (self.pos - self.start_pos),
+                    limits: self.limits,
+                    allocated: self.allocated.clone(),
+                    end_observed: self.end_observed.clone(),
                 }
             }
         }
@@ -704,6 +1577,9 @@ impl<'a, E: Endianness> Clone for BitReadStream<'a, E> {
             buffer: self.buffer.clone(),
             start_pos: self.pos,
             pos: self.pos,
+            limits: self.limits,
+            allocated: self.allocated.clone(),
+            end_observed: self.end_observed.clone(),
         }
     }
 }
@@ -742,17 +1618,17 @@ impl<'a, E: Endianness> From<BitReadBuffer<'a, E>> for BitReadStream<'a, E> {
     }
 }
 
-impl<'a, E: Endianness> From<&'a [u8]> for BitReadStream<'a, E> {
+impl<'a, E: StaticEndianness> From<&'a [u8]> for BitReadStream<'a, E> {
     fn from(bytes: &'a [u8]) -> Self {
         BitReadStream::new(BitReadBuffer::from(bytes))
     }
 }
 
 #[cfg(feature = "serde")]
-use serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
 
 #[cfg(feature = "serde")]
-impl<'a, E: Endianness> Serialize for BitReadStream<'a, E> {
+impl<'a, E: Endianness + Serialize> Serialize for BitReadStream<'a, E> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -760,34 +1636,35 @@ impl<'a, E: Endianness> Serialize for BitReadStream<'a, E> {
         let mut stream = self.clone();
         let mut data = stream.read_bytes(self.bits_left() / 8).unwrap().to_vec();
         if stream.bits_left() > 0 {
-            data.push(stream.read_sized(stream.bits_left()).unwrap());
+            let bits_left = stream.bits_left();
+            let last: u8 = stream.read_sized(bits_left).unwrap();
+            // the remaining bits have to end up at the start of the byte
+            if self.endianness().bit_order().is_lsb_first() {
+                data.push(last);
+            } else {
+                data.push(last << (8 - bits_left));
+            }
         }
 
         let mut s = serializer.serialize_struct("BitReadStream", 3)?;
         s.serialize_field("data", &data)?;
         s.serialize_field("bit_length", &self.bit_len())?;
+        if E::is_static() {
+            s.skip_field("endianness")?;
+        } else {
+            s.serialize_field("endianness", &self.endianness())?;
+        }
         s.end()
     }
 }
 
 #[cfg(feature = "serde")]
-impl<'de, E: Endianness> Deserialize<'de> for BitReadStream<'static, E> {
+impl<'de, E: Endianness + Deserialize<'de>> Deserialize<'de> for BitReadStream<'static, E> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        #[derive(Deserialize)]
-        struct BitData {
-            data: Vec<u8>,
-            bit_length: usize,
-        }
-
-        let data = BitData::deserialize(deserializer)?;
-        let mut buffer = BitReadBuffer::new_owned(data.data, E::endianness());
-        buffer
-            .truncate(data.bit_length)
-            .map_err(de::Error::custom)?;
-        Ok(BitReadStream::new(buffer))
+        BitReadBuffer::deserialize(deserializer).map(BitReadStream::new)
     }
 }
 
@@ -824,3 +1701,19 @@ impl<'a, E: Endianness> schemars::JsonSchema for BitReadStream<'a, E> {
         StreamSchema::json_schema(gen)
     }
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_roundtrip_big_endian() {
+    use crate::BigEndian;
+
+    let mut buffer = BitReadBuffer::new_owned(vec![55; 8], BigEndian);
+    buffer.truncate(61).unwrap();
+    let stream = BitReadStream::new(buffer);
+
+    let json = serde_json::to_string(&stream).unwrap();
+
+    let result: BitReadStream<BigEndian> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(result, stream);
+}
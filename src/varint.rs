@@ -0,0 +1,97 @@
+use crate::num_traits::{IsSigned, UncheckedPrimitiveInt};
+use crate::{BitRead, BitReadStream, BitSize, BitWrite, BitWriteStream, Endianness, Result};
+use num_traits::PrimInt;
+use std::mem::size_of;
+use std::ops::BitOrAssign;
+
+pub(crate) fn zigzag_decode<T: PrimInt>(raw: T) -> T {
+    raw.unsigned_shr(1) ^ (T::zero() - (raw & T::one()))
+}
+
+pub(crate) fn zigzag_encode<T: PrimInt>(value: T) -> T {
+    let sign_shift = (size_of::<T>() * 8 - 1) as u32;
+    (value << 1) ^ value.signed_shr(sign_shift)
+}
+
+/// Integer that is read and written as LEB128 variable length integer
+///
+/// Signed integers are zigzag encoded, see [`read_varint`] for details.
+///
+/// # Examples
+///
+/// ```
+/// # use bitbuffer::{BitRead, BitWrite, VarInt};
+/// #
+/// #[derive(BitRead, BitWrite)]
+/// struct Message {
+///     id: VarInt<u64>,
+///     delta: VarInt<i32>,
+///     flag: bool,
+/// }
+/// ```
+///
+/// [`read_varint`]: BitReadStream::read_varint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct VarInt<T>(pub T);
+
+impl<T> From<T> for VarInt<T> {
+    fn from(value: T) -> Self {
+        VarInt(value)
+    }
+}
+
+impl<'a, E: Endianness, T> BitRead<'a, E> for VarInt<T>
+where
+    T: PrimInt + BitOrAssign + IsSigned + UncheckedPrimitiveInt,
+{
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_varint().map(VarInt)
+    }
+}
+
+impl<T> BitSize for VarInt<T> {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl<E: Endianness, T> BitWrite<E> for VarInt<T>
+where
+    T: PrimInt + IsSigned + UncheckedPrimitiveInt,
+{
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_varint(self.0)
+    }
+}
+
+/// Integer that is read and written using the prefix encoding of the source engine's `UBitVar`
+///
+/// See [`read_ubit_var`] for details.
+///
+/// [`read_ubit_var`]: BitReadStream::read_ubit_var
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct UBitVar(pub u32);
+
+impl From<u32> for UBitVar {
+    fn from(value: u32) -> Self {
+        UBitVar(value)
+    }
+}
+
+impl<'a, E: Endianness> BitRead<'a, E> for UBitVar {
+    #[inline]
+    fn read(stream: &mut BitReadStream<'a, E>) -> Result<Self> {
+        stream.read_ubit_var().map(UBitVar)
+    }
+}
+
+impl BitSize for UBitVar {
+    const BIT_SIZE: Option<usize> = None;
+}
+
+impl<E: Endianness> BitWrite<E> for UBitVar {
+    #[inline]
+    fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
+        stream.write_ubit_var(self.0)
+    }
+}
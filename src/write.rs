@@ -1,4 +1,4 @@
-use crate::{BitReadStream, BitWriteStream, Endianness, Result};
+use crate::{BitError, BitReadStream, BitWriteStream, Endianness, Result};
 use std::borrow::Cow;
 use std::rc::Rc;
 use std::sync::Arc;
@@ -40,7 +40,10 @@ use std::sync::Arc;
 ///
 /// # Enums
 ///
-/// The implementation can be derived for an enum as long as every variant of the enum either has no field, or an unnamed field that implements `BitWrite` or [`BitWriteSized`]
+/// The implementation can be derived for an enum as long as every variant of the enum either has no fields, or only has fields that implement `BitWrite` or [`BitWriteSized`]
+///
+/// Variants with fields are written field by field like structs, for variants with a single unnamed field the
+/// size can also be set on the variant itself.
 ///
 /// The enum is written by first writing a set number of bits as the discriminant of the enum, then the variant written.
 ///
@@ -82,6 +85,50 @@ use std::sync::Arc;
 pub trait BitWrite<E: Endianness> {
     /// Write the type to stream
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()>;
+
+    /// The number of bits that will be written for the value
+    ///
+    /// The default implementation measures the size by writing the value to a stream that discards the data,
+    /// if writing fails the number of bits written before the error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWrite, LittleEndian};
+    /// #
+    /// assert_eq!(BitWrite::<LittleEndian>::written_bit_size(&12u16), 16);
+    /// assert_eq!(BitWrite::<LittleEndian>::written_bit_size("foo"), 32);
+    /// assert_eq!(BitWrite::<LittleEndian>::written_bit_size(&Some(true)), 2);
+    /// ```
+    fn written_bit_size(&self) -> usize {
+        measure_bit_size(|stream| self.write(stream))
+    }
+}
+
+/// Measure the number of bits written by `write` into a stream that discards the data
+fn measure_bit_size<E: Endianness, F: FnOnce(&mut BitWriteStream<E>) -> Result<()>>(
+    write: F,
+) -> usize {
+    measure_bit_size_at(0, write)
+}
+
+/// Measure the number of bits written by `write` when starting `offset` bits into a stream that discards the data
+///
+/// Since alignment is relative to the start of the stream, the padding matches the padding of a stream
+/// at the same position.
+pub(crate) fn measure_bit_size_at<
+    E: Endianness,
+    F: FnOnce(&mut BitWriteStream<E>) -> Result<()>,
+>(
+    offset: usize,
+    write: F,
+) -> usize {
+    let mut sink = std::io::sink();
+    let mut stream = BitWriteStream::from_writer_at(&mut sink, E::endianness(), offset);
+    let _ = write(&mut stream);
+    let bit_len = stream.bit_len();
+    let _ = stream.finish();
+    bit_len - offset
 }
 
 macro_rules! impl_write_int {
@@ -91,6 +138,11 @@ macro_rules! impl_write_int {
             fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
                 stream.write_int::<$type>(*self, <$type>::BITS as usize)
             }
+
+            #[inline]
+            fn written_bit_size(&self) -> usize {
+                <$type>::BITS as usize
+            }
         }
     };
 }
@@ -111,6 +163,11 @@ impl<E: Endianness> BitWrite<E> for f32 {
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         stream.write_float::<f32>(*self)
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        32
+    }
 }
 
 impl<E: Endianness> BitWrite<E> for f64 {
@@ -118,6 +175,11 @@ impl<E: Endianness> BitWrite<E> for f64 {
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         stream.write_float::<f64>(*self)
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        64
+    }
 }
 
 impl<E: Endianness> BitWrite<E> for bool {
@@ -125,6 +187,11 @@ impl<E: Endianness> BitWrite<E> for bool {
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         stream.write_bool(*self)
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        1
+    }
 }
 
 impl<E: Endianness> BitWrite<E> for str {
@@ -132,6 +199,11 @@ impl<E: Endianness> BitWrite<E> for str {
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         stream.write_string(self, None)
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        (self.len() + 1) * 8
+    }
 }
 
 impl<E: Endianness> BitWrite<E> for String {
@@ -139,6 +211,11 @@ impl<E: Endianness> BitWrite<E> for String {
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         stream.write_string(self, None)
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        (self.len() + 1) * 8
+    }
 }
 
 impl<E: Endianness> BitWrite<E> for BitReadStream<'_, E> {
@@ -146,6 +223,11 @@ impl<E: Endianness> BitWrite<E> for BitReadStream<'_, E> {
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         stream.write_bits(self)
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        self.bits_left()
+    }
 }
 
 impl<E: Endianness, T: BitWrite<E>, const N: usize> BitWrite<E> for [T; N] {
@@ -156,6 +238,11 @@ impl<E: Endianness, T: BitWrite<E>, const N: usize> BitWrite<E> for [T; N] {
         }
         Ok(())
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        self.iter().map(T::written_bit_size).sum()
+    }
 }
 
 impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Box<T> {
@@ -163,6 +250,11 @@ impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Box<T> {
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         stream.write(self.as_ref())
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        self.as_ref().written_bit_size()
+    }
 }
 
 impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Rc<T> {
@@ -170,6 +262,11 @@ impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Rc<T> {
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         stream.write(self.as_ref())
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        self.as_ref().written_bit_size()
+    }
 }
 
 impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Arc<T> {
@@ -177,6 +274,11 @@ impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Arc<T> {
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         stream.write(self.as_ref())
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        self.as_ref().written_bit_size()
+    }
 }
 
 impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Vec<T> {
@@ -187,6 +289,11 @@ impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Vec<T> {
         }
         Ok(())
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        self.iter().map(T::written_bit_size).sum()
+    }
 }
 
 impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Option<T> {
@@ -198,6 +305,11 @@ impl<T: BitWrite<E>, E: Endianness> BitWrite<E> for Option<T> {
         }
         Ok(())
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        1 + self.as_ref().map_or(0, T::written_bit_size)
+    }
 }
 
 impl<'a, T: BitWrite<E> + ToOwned + ?Sized, E: Endianness> BitWrite<E> for Cow<'a, T> {
@@ -205,6 +317,11 @@ impl<'a, T: BitWrite<E> + ToOwned + ?Sized, E: Endianness> BitWrite<E> for Cow<'
     fn write(&self, stream: &mut BitWriteStream<E>) -> Result<()> {
         self.as_ref().write(stream)
     }
+
+    #[inline]
+    fn written_bit_size(&self) -> usize {
+        self.as_ref().written_bit_size()
+    }
 }
 
 macro_rules! impl_write_tuple {
@@ -215,6 +332,11 @@ macro_rules! impl_write_tuple {
                 $(self.$i.write(stream)?;)*
                 Ok(())
             }
+
+            #[inline]
+            fn written_bit_size(&self) -> usize {
+                0 $(+ self.$i.written_bit_size())*
+            }
         }
     };
 }
@@ -257,7 +379,10 @@ impl_write_tuple!(0: T1, 1: T2, 2: T3, 3: T4);
 ///
 /// # Enums
 ///
-/// The implementation can be derived for an enum as long as every variant of the enum either has no field, or an unnamed field that implements [`BitWrite`] or `BitWriteSized`
+/// The implementation can be derived for an enum as long as every variant of the enum either has no fields, or only has fields that implement [`BitWrite`] or `BitWriteSized`
+///
+/// Variants with fields are written field by field like structs, for variants with a single unnamed field the
+/// size can also be set on the variant itself.
 ///
 /// The enum is written by first writing a set number of bits as the discriminant of the enum, then the variant is written.
 ///
@@ -288,6 +413,14 @@ impl_write_tuple!(0: T1, 1: T2, 2: T3, 3: T4);
 pub trait BitWriteSized<E: Endianness> {
     /// Write the type to stream
     fn write_sized(&self, stream: &mut BitWriteStream<E>, len: usize) -> Result<()>;
+
+    /// The number of bits that will be written for the value with the provided size
+    ///
+    /// The default implementation measures the size by writing the value to a stream that discards the data,
+    /// if writing fails the number of bits written before the error is returned.
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        measure_bit_size(|stream| self.write_sized(stream, len))
+    }
 }
 
 impl<E: Endianness> BitWriteSized<E> for str {
@@ -295,6 +428,11 @@ impl<E: Endianness> BitWriteSized<E> for str {
     fn write_sized(&self, stream: &mut BitWriteStream<E>, len: usize) -> Result<()> {
         stream.write_string(self, Some(len))
     }
+
+    #[inline]
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        len * 8
+    }
 }
 
 impl<E: Endianness> BitWriteSized<E> for String {
@@ -302,6 +440,11 @@ impl<E: Endianness> BitWriteSized<E> for String {
     fn write_sized(&self, stream: &mut BitWriteStream<E>, len: usize) -> Result<()> {
         stream.write_string(self, Some(len))
     }
+
+    #[inline]
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        len * 8
+    }
 }
 
 macro_rules! impl_write_sized_int {
@@ -309,7 +452,12 @@ macro_rules! impl_write_sized_int {
         impl<E: Endianness> BitWriteSized<E> for $type {
             #[inline]
             fn write_sized(&self, stream: &mut BitWriteStream<E>, len: usize) -> Result<()> {
-                stream.write_int::<$type>(*self, len)
+                stream.write_int_checked::<$type>(*self, len)
+            }
+
+            #[inline]
+            fn written_bit_size_sized(&self, len: usize) -> usize {
+                len
             }
         }
     };
@@ -334,6 +482,11 @@ impl<E: Endianness> BitWriteSized<E> for BitReadStream<'_, E> {
         let bits = self.clone().read_bits(len)?;
         stream.write_bits(&bits)
     }
+
+    #[inline]
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        len
+    }
 }
 
 impl<E: Endianness, T: BitWriteSized<E>, const N: usize> BitWriteSized<E> for [T; N] {
@@ -344,6 +497,35 @@ impl<E: Endianness, T: BitWriteSized<E>, const N: usize> BitWriteSized<E> for [T
         }
         Ok(())
     }
+
+    #[inline]
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        self.iter()
+            .map(|element| element.written_bit_size_sized(len))
+            .sum()
+    }
+}
+
+/// Write exactly `len` items, failing if the number of items doesn't match
+impl<T: BitWrite<E>, E: Endianness> BitWriteSized<E> for Vec<T> {
+    #[inline]
+    fn write_sized(&self, stream: &mut BitWriteStream<E>, len: usize) -> Result<()> {
+        if self.len() != len {
+            return Err(BitError::LengthMismatch {
+                length: self.len(),
+                requested_length: len,
+            });
+        }
+        for element in self.iter() {
+            stream.write(element)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn written_bit_size_sized(&self, _len: usize) -> usize {
+        self.iter().map(T::written_bit_size).sum()
+    }
 }
 
 impl<T: BitWriteSized<E>, E: Endianness> BitWriteSized<E> for Box<T> {
@@ -351,6 +533,11 @@ impl<T: BitWriteSized<E>, E: Endianness> BitWriteSized<E> for Box<T> {
     fn write_sized(&self, stream: &mut BitWriteStream<E>, len: usize) -> Result<()> {
         stream.write_sized(self.as_ref(), len)
     }
+
+    #[inline]
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        self.as_ref().written_bit_size_sized(len)
+    }
 }
 
 impl<T: BitWriteSized<E>, E: Endianness> BitWriteSized<E> for Rc<T> {
@@ -358,6 +545,11 @@ impl<T: BitWriteSized<E>, E: Endianness> BitWriteSized<E> for Rc<T> {
     fn write_sized(&self, stream: &mut BitWriteStream<E>, len: usize) -> Result<()> {
         stream.write_sized(self.as_ref(), len)
     }
+
+    #[inline]
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        self.as_ref().written_bit_size_sized(len)
+    }
 }
 
 impl<T: BitWriteSized<E>, E: Endianness> BitWriteSized<E> for Arc<T> {
@@ -365,6 +557,11 @@ impl<T: BitWriteSized<E>, E: Endianness> BitWriteSized<E> for Arc<T> {
     fn write_sized(&self, stream: &mut BitWriteStream<E>, len: usize) -> Result<()> {
         stream.write_sized(self.as_ref(), len)
     }
+
+    #[inline]
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        self.as_ref().written_bit_size_sized(len)
+    }
 }
 
 impl<T: BitWriteSized<E>, E: Endianness> BitWriteSized<E> for Option<T> {
@@ -376,6 +573,13 @@ impl<T: BitWriteSized<E>, E: Endianness> BitWriteSized<E> for Option<T> {
         }
         Ok(())
     }
+
+    #[inline]
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        1 + self
+            .as_ref()
+            .map_or(0, |val| val.written_bit_size_sized(len))
+    }
 }
 
 impl<'a, T: BitWriteSized<E> + ToOwned + ?Sized, E: Endianness> BitWriteSized<E> for Cow<'a, T> {
@@ -383,4 +587,9 @@ impl<'a, T: BitWriteSized<E> + ToOwned + ?Sized, E: Endianness> BitWriteSized<E>
     fn write_sized(&self, stream: &mut BitWriteStream<E>, len: usize) -> Result<()> {
         self.as_ref().write_sized(stream, len)
     }
+
+    #[inline]
+    fn written_bit_size_sized(&self, len: usize) -> usize {
+        self.as_ref().written_bit_size_sized(len)
+    }
 }
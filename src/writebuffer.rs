@@ -1,11 +1,23 @@
-use crate::Endianness;
+use crate::{BitError, Endianness, Result};
 use std::cmp::min;
-use std::marker::PhantomData;
+use std::io::Write;
 use std::ops::{Index, IndexMut, Range};
 
+/// Number of buffered bytes after which the complete bytes are written to the underlying writer
+const FLUSH_THRESHOLD: usize = 8 * 1024;
+
 enum WriteData<'a> {
     Vec(&'a mut Vec<u8>),
-    Slice { data: &'a mut [u8], length: usize },
+    Slice {
+        data: &'a mut [u8],
+        length: usize,
+    },
+    Writer {
+        writer: &'a mut dyn Write,
+        buffer: Vec<u8>,
+        // number of bytes already written to the writer
+        flushed: usize,
+    },
 }
 
 impl<'a> WriteData<'a> {
@@ -16,6 +28,7 @@ impl<'a> WriteData<'a> {
                 *length -= 1;
                 Some(data[*length])
             }
+            WriteData::Writer { buffer, .. } => buffer.pop(),
             _ => None,
         }
     }
@@ -29,6 +42,7 @@ impl<'a> WriteData<'a> {
                 target.copy_from_slice(other);
                 *length += other.len();
             }
+            WriteData::Writer { buffer, .. } => buffer.extend_from_slice(other),
         }
     }
 
@@ -39,6 +53,7 @@ impl<'a> WriteData<'a> {
                 data[*length] = byte;
                 *length += 1;
             }
+            WriteData::Writer { buffer, .. } => buffer.push(byte),
         }
     }
 
@@ -46,9 +61,57 @@ impl<'a> WriteData<'a> {
         match self {
             WriteData::Vec(vec) => vec.last_mut(),
             WriteData::Slice { data, length } if *length > 0 => Some(&mut data[*length - 1]),
+            WriteData::Writer { buffer, .. } => buffer.last_mut(),
             _ => None,
         }
     }
+
+    /// Drop all bytes after the first `len` bytes
+    fn truncate(&mut self, len: usize) {
+        match self {
+            WriteData::Vec(vec) => vec.truncate(len),
+            WriteData::Slice { data, length } if len < *length => {
+                data[len..*length].fill(0);
+                *length = len;
+            }
+            WriteData::Slice { .. } => {}
+            WriteData::Writer {
+                buffer, flushed, ..
+            } => buffer.truncate(len - *flushed),
+        }
+    }
+
+    /// The number of bytes that are buffered but not written to the writer yet
+    fn buffered_len(&self) -> usize {
+        match self {
+            WriteData::Writer { buffer, .. } => buffer.len(),
+            _ => 0,
+        }
+    }
+
+    /// Write the buffered bytes to the writer, keeping the last `keep` bytes buffered
+    fn write_out(&mut self, keep: usize) -> Result<()> {
+        if let WriteData::Writer {
+            writer,
+            buffer,
+            flushed,
+        } = self
+        {
+            let count = buffer.len().saturating_sub(keep);
+            writer.write_all(&buffer[0..count])?;
+            buffer.drain(0..count);
+            *flushed += count;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.write_out(0)?;
+        if let WriteData::Writer { writer, .. } = self {
+            writer.flush()?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a> Index<usize> for WriteData<'a> {
@@ -58,6 +121,9 @@ impl<'a> Index<usize> for WriteData<'a> {
         match self {
             WriteData::Vec(vec) => &vec[index],
             WriteData::Slice { data, .. } => &data[index],
+            WriteData::Writer {
+                buffer, flushed, ..
+            } => &buffer[index - *flushed],
         }
     }
 }
@@ -67,6 +133,9 @@ impl<'a> IndexMut<usize> for WriteData<'a> {
         match self {
             WriteData::Vec(vec) => &mut vec[index],
             WriteData::Slice { data, .. } => &mut data[index],
+            WriteData::Writer {
+                buffer, flushed, ..
+            } => &mut buffer[index - *flushed],
         }
     }
 }
@@ -78,6 +147,9 @@ impl<'a> Index<Range<usize>> for WriteData<'a> {
         match self {
             WriteData::Vec(vec) => &vec[index],
             WriteData::Slice { data, .. } => &data[index],
+            WriteData::Writer {
+                buffer, flushed, ..
+            } => &buffer[index.start - *flushed..index.end - *flushed],
         }
     }
 }
@@ -87,6 +159,9 @@ impl<'a> IndexMut<Range<usize>> for WriteData<'a> {
         match self {
             WriteData::Vec(vec) => &mut vec[index],
             WriteData::Slice { data, .. } => &mut data[index],
+            WriteData::Writer {
+                buffer, flushed, ..
+            } => &mut buffer[index.start - *flushed..index.end - *flushed],
         }
     }
 }
@@ -94,25 +169,69 @@ impl<'a> IndexMut<Range<usize>> for WriteData<'a> {
 pub struct WriteBuffer<'a, E: Endianness> {
     bit_len: usize,
     bytes: WriteData<'a>,
-    endianness: PhantomData<E>,
+    endianness: E,
+    // while a section is reserved the bytes before it can still be modified and can't be written out
+    flush_holds: usize,
 }
 
 impl<'a, E: Endianness> WriteBuffer<'a, E> {
-    pub fn new(bytes: &'a mut Vec<u8>, _endianness: E) -> Self {
+    pub fn new(bytes: &'a mut Vec<u8>, endianness: E) -> Self {
         WriteBuffer {
             bit_len: 0,
             bytes: WriteData::Vec(bytes),
-            endianness: PhantomData,
+            endianness,
+            flush_holds: 0,
         }
     }
-    pub fn for_slice(bytes: &'a mut [u8], _endianness: E) -> Self {
+    pub fn for_slice(bytes: &'a mut [u8], endianness: E) -> Self {
         WriteBuffer {
             bit_len: 0,
             bytes: WriteData::Slice {
                 data: bytes,
                 length: 0,
             },
-            endianness: PhantomData,
+            endianness,
+            flush_holds: 0,
+        }
+    }
+    pub fn for_writer(writer: &'a mut dyn Write, endianness: E) -> Self {
+        WriteBuffer {
+            bit_len: 0,
+            bytes: WriteData::Writer {
+                writer,
+                buffer: Vec::new(),
+                flushed: 0,
+            },
+            endianness,
+            flush_holds: 0,
+        }
+    }
+    /// Create a buffer for the writer that starts `bit_offset` bits in, the bits before are never written out
+    pub fn for_writer_at(writer: &'a mut dyn Write, endianness: E, bit_offset: usize) -> Self {
+        let pending = if bit_offset % 8 == 0 { vec![] } else { vec![0] };
+        WriteBuffer {
+            bit_len: bit_offset,
+            bytes: WriteData::Writer {
+                writer,
+                buffer: pending,
+                flushed: bit_offset / 8,
+            },
+            endianness,
+            flush_holds: 0,
+        }
+    }
+
+    pub fn endianness(&self) -> E {
+        self.endianness
+    }
+
+    /// Convert the buffer to a buffer writing the same data with a different endianness
+    pub fn with_endianness<E2: Endianness>(self, endianness: E2) -> WriteBuffer<'a, E2> {
+        WriteBuffer {
+            bit_len: self.bit_len,
+            bytes: self.bytes,
+            endianness,
+            flush_holds: self.flush_holds,
         }
     }
 
@@ -121,6 +240,72 @@ impl<'a, E: Endianness> WriteBuffer<'a, E> {
         self.bit_len
     }
 
+    /// Make room for `bits` more bits, failing if they don't fit in a slice
+    pub fn reserve_bits(&mut self, bits: usize) -> Result<()> {
+        let len = (self.bit_len + bits).div_ceil(8);
+        match &mut self.bytes {
+            WriteData::Vec(vec) => vec.reserve(len.saturating_sub(vec.len())),
+            WriteData::Slice { data, .. } if len > data.len() => {
+                return Err(BitError::NotEnoughSpace {
+                    requested: bits,
+                    bits_left: data.len() * 8 - self.bit_len,
+                });
+            }
+            // complete bytes are written out while writing, so the buffer doesn't need to hold all data
+            WriteData::Slice { .. } | WriteData::Writer { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Write the complete bytes to the writer once enough data is buffered
+    pub fn flush_complete(&mut self) -> Result<()> {
+        if self.flush_holds == 0 && self.bytes.buffered_len() >= FLUSH_THRESHOLD {
+            let partial = (self.bit_len & 7 != 0) as usize;
+            self.bytes.write_out(partial)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Write all buffered data, including the trailing partial byte, to the writer
+    ///
+    /// Any data that couldn't be written is discarded
+    pub fn finish(&mut self) -> Result<()> {
+        let result = self.bytes.flush();
+        if let WriteData::Writer { buffer, .. } = &mut self.bytes {
+            buffer.clear();
+        }
+        result
+    }
+
+    /// Drop all bits written after `bit_len`
+    ///
+    /// Any data that has already been written to a writer can't be truncated, so flushing should be held
+    pub fn truncate(&mut self, bit_len: usize) {
+        debug_assert!(bit_len <= self.bit_len);
+        self.bytes.truncate(bit_len.div_ceil(8));
+        let bit_offset = bit_len & 7;
+        if bit_offset > 0 {
+            let mask = if self.endianness.bit_order().is_lsb_first() {
+                !(u8::MAX << bit_offset)
+            } else {
+                !(u8::MAX >> bit_offset)
+            };
+            if let Some(last) = self.bytes.last_mut() {
+                *last &= mask;
+            }
+        }
+        self.bit_len = bit_len;
+    }
+
+    pub fn hold_flush(&mut self) {
+        self.flush_holds += 1;
+    }
+
+    pub fn release_flush(&mut self) {
+        self.flush_holds -= 1;
+    }
+
     pub fn push_non_fit_bits<I>(&mut self, bits: I, count: usize)
     where
         I: ExactSizeIterator,
@@ -130,6 +315,12 @@ impl<'a, E: Endianness> WriteBuffer<'a, E> {
         for (chunk, chunk_size) in bits {
             if remaining > 0 {
                 let bits = min(remaining, chunk_size as usize);
+                // for msb first the bits we want are at the top of a partially written chunk
+                let chunk = if self.endianness.bit_order().is_lsb_first() {
+                    chunk
+                } else {
+                    chunk >> (chunk_size as usize - bits)
+                };
                 self.push_bits(chunk, bits);
                 remaining -= bits
             }
@@ -156,7 +347,7 @@ impl<'a, E: Endianness> WriteBuffer<'a, E> {
         };
         let merged_byte_count = (count + bit_offset + 7) / 8;
 
-        if E::is_le() {
+        if self.endianness.bit_order().is_lsb_first() {
             let merged = last_written_byte as usize | bits << bit_offset;
             self.bytes
                 .extend_from_slice(&merged.to_le_bytes()[0..merged_byte_count]);
@@ -179,9 +370,14 @@ impl<'a, E: Endianness> WriteBuffer<'a, E> {
         let mut old = [0; 8];
         old[0..byte_count].copy_from_slice(&self.bytes[byte_pos..byte_pos + byte_count]);
 
-        let old = u64::from_le_bytes(old);
-        let merged = old | (bits << bit_offset);
-        let merged = merged.to_le_bytes();
+        let merged = if self.endianness.bit_order().is_lsb_first() {
+            let old = u64::from_le_bytes(old);
+            (old | (bits << bit_offset)).to_le_bytes()
+        } else {
+            // msb first fills the bytes from the most significant bit
+            let old = u64::from_be_bytes(old);
+            (old | (bits << (64 - bit_offset - count))).to_be_bytes()
+        };
         self.bytes[byte_pos..byte_pos + byte_count].copy_from_slice(&merged[0..byte_count]);
     }
 
@@ -194,7 +390,7 @@ impl<'a, E: Endianness> WriteBuffer<'a, E> {
     pub fn push_bool(&mut self, val: bool) {
         let val = val as u8;
         let bit_offset = self.bit_len() % 8;
-        let shift = if E::is_le() {
+        let shift = if self.endianness.bit_order().is_lsb_first() {
             bit_offset
         } else {
             7 - bit_offset
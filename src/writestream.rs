@@ -1,17 +1,34 @@
 use num_traits::{Float, PrimInt};
 use std::cmp::min;
 use std::mem::size_of;
-use std::ops::{BitOrAssign, BitXor};
+use std::ops::{BitOrAssign, BitXor, Deref, DerefMut};
 
-use crate::endianness::Endianness;
+use crate::endianness::{has_mixed_order, swap_bytes, Endianness};
 use crate::num_traits::{IsSigned, SplitFitUsize, UncheckedPrimitiveFloat, UncheckedPrimitiveInt};
+use crate::varint::zigzag_encode;
+use crate::write::measure_bit_size_at;
 use crate::writebuffer::WriteBuffer;
-use crate::{BitError, BitReadStream, BitWrite, BitWriteSized, Result};
+use crate::{BitError, BitReadStream, BitWrite, BitWriteSized, Padding, Result};
 use std::fmt::Debug;
+use std::io::Write;
 
 const USIZE_SIZE: usize = size_of::<usize>();
 const USIZE_BITS: usize = USIZE_SIZE * 8;
 
+/// Check if the value can be written with `count` bits, as two's complement for signed types
+fn fits_in_bits<T: PrimInt + IsSigned>(value: T, count: usize) -> bool {
+    if count >= size_of::<T>() * 8 {
+        true
+    } else if count == 0 {
+        value == T::zero()
+    } else if T::is_signed() {
+        let high = value.signed_shr(count as u32 - 1);
+        high == T::zero() || high == !T::zero()
+    } else {
+        value.unsigned_shr(count as u32) == T::zero()
+    }
+}
+
 /// Stream that provides an a way to write non bit aligned adata
 ///
 /// # Examples
@@ -67,6 +84,62 @@ where
             buffer: WriteBuffer::for_slice(data, endianness),
         }
     }
+
+    /// Create a new write stream that writes the data to a [`Write`] target
+    ///
+    /// Complete bytes are written to the target as they are produced, [`finish`] should be called
+    /// after writing to write any remaining data, including the zero padded trailing partial byte,
+    /// and flush the target.
+    ///
+    /// Dropping the stream without calling [`finish`], for example when returning early on an error,
+    /// discards the data that wasn't written out yet, including the trailing partial byte.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bitbuffer::{BitWriteStream, BigEndian};
+    /// # use bitbuffer::Result;
+    ///
+    /// # fn main() -> Result<()> {
+    /// let mut file = Vec::new(); // or any other `std::io::Write`
+    /// let mut stream = BitWriteStream::from_writer(&mut file, BigEndian);
+    /// stream.write_int(0b101u8, 3)?;
+    /// stream.write(&0xABCDu16)?;
+    /// stream.finish()?;
+    /// assert_eq!(file, [0b1011_0101, 0b0111_1001, 0b1010_0000]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`finish`]: BitWriteStream::finish
+    pub fn from_writer<W: Write>(writer: &'a mut W, endianness: E) -> Self {
+        BitWriteStream {
+            buffer: WriteBuffer::for_writer(writer, endianness),
+        }
+    }
+
+    /// Create a new write stream for the writer that starts `bit_offset` bits in, as if that many zero bits
+    /// were already written
+    pub(crate) fn from_writer_at<W: Write>(
+        writer: &'a mut W,
+        endianness: E,
+        bit_offset: usize,
+    ) -> Self {
+        BitWriteStream {
+            buffer: WriteBuffer::for_writer_at(writer, endianness, bit_offset),
+        }
+    }
+
+    /// Write any remaining data to the [`Write`] target, padding the last byte with zeros, and flush it
+    ///
+    /// For streams that write to a `Vec` or slice this does nothing.
+    ///
+    /// # Errors
+    ///
+    /// - [`BitError::Io`]: writing to the target failed
+    pub fn finish(mut self) -> Result<()> {
+        self.buffer.finish()
+    }
 }
 
 impl<'a, E> BitWriteStream<'a, E>
@@ -83,6 +156,11 @@ where
         (self.buffer.bit_len() + 7) / 8
     }
 
+    /// The endianness of the stream
+    pub fn endianness(&self) -> E {
+        self.buffer.endianness()
+    }
+
     fn push_non_fit_bits<I>(&mut self, bits: I, count: usize)
     where
         I: ExactSizeIterator,
@@ -118,26 +196,35 @@ where
     #[inline]
     pub fn write_bool(&mut self, value: bool) -> Result<()> {
         self.buffer.push_bool(value);
-        Ok(())
+        self.buffer.flush_complete()
     }
 
-    /// Write an integer into the buffer
+    /// Write the lowest `count` bits of an integer into the buffer
+    ///
+    /// Any higher bits of the value are discarded without error, use [`write_int_checked`] to
+    /// check that the value fits in `count` bits.
+    ///
+    /// # Errors
+    ///
+    /// - [`BitError::TooManyBits`]: to many bits requested for the chosen integer type
     ///
     /// # Examples
     ///
     /// ```
-    /// # use bitbuffer::{BitReadBuffer, LittleEndian, Result};
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
     /// #
     /// # fn main() -> Result<()> {
-    /// # use bitbuffer::{BitWriteStream, LittleEndian};
-    ///
     /// let mut data = Vec::new();
-    /// let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
     /// stream.write_int(123u16, 15)?;
+    /// stream.write_int(0x1234u16, 8)?;
+    /// assert_eq!(data, [0, 246, 104]);
     /// #
     /// #     Ok(())
     /// # }
     /// ```
+    ///
+    /// [`write_int_checked`]: BitWriteStream::write_int_checked
     #[inline]
     pub fn write_int<T>(&mut self, value: T, count: usize) -> Result<()>
     where
@@ -158,13 +245,219 @@ where
             });
         }
 
+        let value = if has_mixed_order(self.buffer.endianness()) && count > 8 {
+            swap_bytes(value, count, false)
+        } else {
+            value
+        };
+
         if type_bit_size < USIZE_BITS || count <= (USIZE_BITS - (self.bit_len() % 8)) {
             self.push_bits(value.into_usize_unchecked(), count);
         } else {
-            self.push_non_fit_bits(value.split_fit_usize::<E>(), count)
+            // the chunks are pushed from the start, for msb first the top bits are pushed first
+            // so we need to move the bits we want to write to the top
+            let value = if self.buffer.endianness().bit_order().is_lsb_first() {
+                value
+            } else {
+                value << (type_bit_size - count)
+            };
+            self.push_non_fit_bits(value.split_fit_usize(self.buffer.endianness()), count)
         }
 
-        Ok(())
+        self.buffer.flush_complete()
+    }
+
+    /// Write an integer into the buffer, checking that the value fits in `count` bits
+    ///
+    /// Signed values are checked to fit as `count` bit two's complement integer.
+    ///
+    /// # Errors
+    ///
+    /// - [`BitError::TooManyBits`]: to many bits requested for the chosen integer type
+    /// - [`BitError::ValueTooLarge`]: the value doesn't fit in the requested number of bits, nothing is written
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitError, BitWriteStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    /// stream.write_int_checked(123u16, 15)?;
+    /// stream.write_int_checked(-4i8, 3)?;
+    /// assert!(matches!(
+    ///     stream.write_int_checked(4i8, 3),
+    ///     Err(BitError::ValueTooLarge { bits: 3, .. })
+    /// ));
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn write_int_checked<T>(&mut self, value: T, count: usize) -> Result<()>
+    where
+        T: PrimInt
+            + BitOrAssign
+            + IsSigned
+            + UncheckedPrimitiveInt
+            + BitXor
+            + Debug
+            + SplitFitUsize,
+    {
+        if !fits_in_bits(value, count) {
+            return Err(BitError::ValueTooLarge {
+                value: format!("{:?}", value),
+                bits: count,
+            });
+        }
+        self.write_int(value, count)
+    }
+
+    /// Write an integer into the buffer, using the provided endianness instead of the endianness of the stream
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    /// stream.write_int(0x1234u16, 16)?;
+    /// stream.write_int_endian(0x1234u16, 16, BigEndian)?;
+    /// assert_eq!(data, [0x34, 0x12, 0x12, 0x34]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn write_int_endian<T, E2: Endianness>(
+        &mut self,
+        value: T,
+        count: usize,
+        endianness: E2,
+    ) -> Result<()>
+    where
+        T: PrimInt
+            + BitOrAssign
+            + IsSigned
+            + UncheckedPrimitiveInt
+            + BitXor
+            + Debug
+            + SplitFitUsize,
+    {
+        self.with_endianness(endianness, |stream| stream.write_int(value, count))
+    }
+
+    /// Write to the stream using a different endianness
+    ///
+    /// The closure is called with a stream writing to the same data with the provided endianness.
+    ///
+    /// Since the endianness also determines the order of the bits within a byte, switching endianness
+    /// is only meaningful at byte boundaries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    /// stream.with_endianness(BigEndian, |stream| stream.write(&0x12345678u32))?;
+    /// assert_eq!(data, [0x12, 0x34, 0x56, 0x78]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn with_endianness<E2, T, F>(&mut self, endianness: E2, write: F) -> T
+    where
+        E2: Endianness,
+        F: FnOnce(&mut BitWriteStream<'a, E2>) -> T,
+    {
+        write(&mut EndiannessSwap::new(self, endianness).stream)
+    }
+
+    /// Write a variable length integer into the buffer
+    ///
+    /// The integer is written as LEB128, signed integers are zigzag encoded first,
+    /// see [`BitReadStream::read_varint`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// # use bitbuffer::{BitWriteStream, LittleEndian};
+    ///
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    /// stream.write_varint(300u32)?;
+    /// stream.write_varint(-2i32)?;
+    /// assert_eq!(data, [0b1010_1100, 0b0000_0010, 0b0000_0011]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn write_varint<T>(&mut self, value: T) -> Result<()>
+    where
+        T: PrimInt + IsSigned + UncheckedPrimitiveInt,
+    {
+        let mut raw = if T::is_signed() {
+            zigzag_encode(value)
+        } else {
+            value
+        };
+        loop {
+            let byte = (raw & T::from_unchecked(0x7Fu8)).into_u8_unchecked();
+            raw = raw.unsigned_shr(7);
+            if raw.is_zero() {
+                self.push_bits(byte as usize, 8);
+                return self.buffer.flush_complete();
+            }
+            self.push_bits((byte | 0x80) as usize, 8);
+        }
+    }
+
+    /// Write a prefix encoded variable length integer into the buffer
+    ///
+    /// See [`BitReadStream::read_ubit_var`] for details about the encoding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitReadBuffer, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// # use bitbuffer::{BitWriteStream, LittleEndian};
+    ///
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    /// stream.write_ubit_var(0b1011_1010)?;
+    /// assert_eq!(stream.bit_len(), 10);
+    /// assert_eq!(data, [0b1101_1010, 0b0000_0010]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn write_ubit_var(&mut self, value: u32) -> Result<()> {
+        let (prefix, extra_bits) = match value {
+            0..=0xF => (0b00_0000, 0),
+            0x10..=0xFF => (0b01_0000, 4),
+            0x100..=0xFFF => (0b10_0000, 8),
+            _ => (0b11_0000, 28),
+        };
+        if extra_bits == 0 {
+            self.push_bits(value as usize, 6);
+            self.buffer.flush_complete()
+        } else {
+            self.push_bits((value & 0b1111 | prefix) as usize, 6);
+            self.write_int(value >> 4, extra_bits)
+        }
     }
 
     /// Write a float into the buffer
@@ -190,13 +483,13 @@ where
         T: Float + UncheckedPrimitiveFloat,
     {
         if self.buffer.bit_len() & 7 == 0 {
-            let bytes = value.to_bytes::<E>();
+            let bytes = value.to_bytes(self.buffer.endianness());
             self.buffer.extends_from_slice(bytes.as_ref());
         } else {
             self.write_int(value.to_int(), size_of::<T>() * 8)?;
         }
 
-        Ok(())
+        self.buffer.flush_complete()
     }
 
     /// Write a number of bytes into the buffer
@@ -226,7 +519,7 @@ where
                 .copied()
                 .for_each(|chunk| self.push_bits(chunk as usize, 8));
         }
-        Ok(())
+        self.buffer.flush_complete()
     }
 
     /// Write bits from a read stream into the buffer
@@ -242,15 +535,15 @@ where
 
         while bits.bits_left() > 32 {
             let chunk = bits.read::<u32>()?;
-            self.push_bits(chunk as usize, 32);
+            self.write_int(chunk, 32)?;
         }
 
         if bits.bits_left() > 0 {
             let end_bits = bits.bits_left();
             let end = bits.read_int::<u32>(end_bits)?;
-            self.push_bits(end as usize, end_bits);
+            self.write_int(end, end_bits)?;
         }
-        Ok(())
+        self.buffer.flush_complete()
     }
 
     /// Write a string into the buffer
@@ -290,7 +583,125 @@ where
                 self.push_bits(0, 8)
             }
         }
-        Ok(())
+        self.buffer.flush_complete()
+    }
+
+    /// Run a series of writes, removing any data written by the closure if it returns an error
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BitError, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_int(0b101u8, 3)?;
+    /// let result = stream.try_write_with(|stream| {
+    ///     stream.write_int(0b111u8, 3)?;
+    ///     stream.write_string("too long", Some(4))
+    /// });
+    /// assert!(matches!(result, Err(BitError::StringToLong { .. })));
+    /// assert_eq!(stream.bit_len(), 3);
+    /// stream.write_int(0b01u8, 2)?;
+    /// assert_eq!(data, [0b1010_1000]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn try_write_with<T, Err, F>(&mut self, write: F) -> Result<T, Err>
+    where
+        F: FnOnce(&mut Self) -> Result<T, Err>,
+        Err: From<BitError>,
+    {
+        let start = self.bit_len();
+        // data written by the closure can't be written out until we know it doesn't need to be removed
+        let result = write(&mut FlushHold::new(self));
+        if result.is_err() {
+            self.buffer.truncate(start);
+        }
+        self.buffer.flush_complete()?;
+        result
+    }
+
+    /// Check if the number of written bits is a multiple of 8
+    ///
+    /// Like all alignment methods, this is relative to the start of the stream, any data that was already
+    /// in the `Vec` the stream was created with isn't counted. This matches [`BitReadStream::is_aligned`],
+    /// so data aligned while writing is aligned the same way when read from a stream that starts at the
+    /// same position.
+    pub fn is_aligned(&self) -> bool {
+        self.bit_len().is_multiple_of(8)
+    }
+
+    /// Pad the stream with zero bits until the number of written bits is a multiple of `bits`,
+    /// returning the number of padding bits written
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_int(0b101u8, 3)?;
+    /// assert_eq!(stream.align(16)?, 13);
+    /// assert_eq!(stream.bit_len(), 16);
+    /// assert_eq!(data, [0b1010_0000, 0]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn align(&mut self, bits: usize) -> Result<usize> {
+        self.align_with(bits, Padding::Zeros)
+    }
+
+    /// Pad the stream with zero bits to the next byte boundary, returning the number of padding bits written
+    pub fn align_to_byte(&mut self) -> Result<usize> {
+        self.align(8)
+    }
+
+    /// Pad the stream with the provided padding until the number of written bits is a multiple of `bits`,
+    /// returning the number of padding bits written
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Padding, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_int(0b101u8, 3)?;
+    /// assert_eq!(stream.align_with(8, Padding::Ones)?, 5);
+    /// assert_eq!(data, [0b1011_1111]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn align_with(&mut self, bits: usize, padding: Padding) -> Result<usize> {
+        let count = if bits == 0 {
+            0
+        } else {
+            (bits - self.bit_len() % bits) % bits
+        };
+        self.write_padding(count, padding)?;
+        Ok(count)
+    }
+
+    fn write_padding(&mut self, count: usize, padding: Padding) -> Result<()> {
+        let chunk = match padding {
+            Padding::Zeros => 0,
+            Padding::Ones => usize::MAX,
+        };
+        let mut remaining = count;
+        while remaining > 0 {
+            let bits = min(remaining, 32);
+            self.push_bits(chunk, bits);
+            remaining -= bits;
+        }
+        self.buffer.flush_complete()
     }
 
     /// Write the type to stream
@@ -305,6 +716,137 @@ where
         value.write_sized(self, length)
     }
 
+    /// Write the type to stream, making room for all bits of the value at once
+    ///
+    /// The size of the value is measured by first writing it to a stream that discards the data, starting at the
+    /// same offset as this stream so the padding of aligned values matches.
+    /// When writing to a `Vec` the `Vec` is grown at most once.
+    ///
+    /// # Errors
+    ///
+    /// - [`BitError::NotEnoughSpace`]: the value doesn't fit in the slice the stream is writing to, nothing is written
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitError, BitWriteStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = [0; 4];
+    /// let mut stream = BitWriteStream::from_slice(&mut data, LittleEndian);
+    /// stream.write_prealloc(&0x1234u16)?;
+    /// let result = stream.write_prealloc(&"foo".to_string());
+    /// assert!(matches!(
+    ///     result,
+    ///     Err(BitError::NotEnoughSpace {
+    ///         requested: 32,
+    ///         bits_left: 16
+    ///     })
+    /// ));
+    /// assert_eq!(stream.bit_len(), 16);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_prealloc<T: BitWrite<E> + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let bits = measure_bit_size_at(self.bit_len(), |stream| value.write(stream));
+        self.buffer.reserve_bits(bits)?;
+        value.write(self)
+    }
+
+    /// Write all values to the stream, making room for all bits of the values at once
+    ///
+    /// See [`write_prealloc`] for details.
+    ///
+    /// # Errors
+    ///
+    /// - [`BitError::NotEnoughSpace`]: the values don't fit in the slice the stream is writing to, nothing is written
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_all_prealloc(&[1u16, 2, 3])?;
+    /// assert_eq!(data, [0, 1, 0, 2, 0, 3]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`write_prealloc`]: BitWriteStream::write_prealloc
+    pub fn write_all_prealloc<T: BitWrite<E>>(&mut self, values: &[T]) -> Result<()> {
+        let bits = measure_bit_size_at(self.bit_len(), |stream| {
+            values.iter().try_for_each(|value| value.write(stream))
+        });
+        self.buffer.reserve_bits(bits)?;
+        for value in values {
+            value.write(self)?;
+        }
+        Ok(())
+    }
+
+    /// Write the items followed by the `terminator`
+    ///
+    /// # Errors
+    ///
+    /// - [`BitError::TerminatorInItems`]: one of the items is equal to the terminator, the list would end at that item when reading
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, LittleEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    /// stream.write_until(&[1u8, 2, 3], &0)?;
+    /// assert_eq!(data, [1, 2, 3, 0]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_until<T: BitWrite<E> + PartialEq>(
+        &mut self,
+        items: &[T],
+        terminator: &T,
+    ) -> Result<()> {
+        if let Some(index) = items.iter().position(|item| item == terminator) {
+            return Err(BitError::TerminatorInItems { index });
+        }
+        for item in items {
+            item.write(self)?;
+        }
+        terminator.write(self)
+    }
+
+    /// Write the items each preceded by a set continuation bit, followed by an unset bit
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bitbuffer::{BitWriteStream, BigEndian, Result};
+    /// #
+    /// # fn main() -> Result<()> {
+    /// let mut data = Vec::new();
+    /// let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    /// stream.write_continued(&[false, true, false, true])?;
+    /// assert_eq!(data, [0b1011_1011, 0b0000_0000]);
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn write_continued<T: BitWrite<E>>(&mut self, items: &[T]) -> Result<()> {
+        for item in items {
+            self.write_bool(true)?;
+            item.write(self)?;
+        }
+        self.write_bool(false)
+    }
+
     /// Write the length of a section before the section
     pub fn reserve_length<Err: From<BitError>, F: Fn(&mut BitWriteStream<E>) -> Result<(), Err>>(
         &mut self,
@@ -335,7 +877,7 @@ where
             let bit_len = end - start;
 
             let pad_len = (8 - (bit_len & 7)) & 7;
-            stream.push_bits(0, pad_len);
+            stream.write_padding(pad_len, Padding::Zeros)?;
 
             let byte_len = (bit_len + pad_len) / 8;
             Ok(byte_len as u64)
@@ -343,17 +885,115 @@ where
     }
 
     /// Reserve the length to write an integer
+    ///
+    /// # Errors
+    ///
+    /// - [`BitError::TooManyBits`]: the reserved integer is 56 bits or larger
+    /// - [`BitError::ValueTooLarge`]: the integer returned by `body_fn` doesn't fit in the reserved bits
     pub fn reserve_int<Err: From<BitError>, F: Fn(&mut BitWriteStream<E>) -> Result<u64, Err>>(
         &mut self,
         count: usize,
         body_fn: F,
     ) -> Result<(), Err> {
+        // the reserved bits are filled in using `set_at`, which is limited to less than 56 bits
+        if count >= 56 {
+            return Err(BitError::TooManyBits {
+                requested: count,
+                max: 55,
+            }
+            .into());
+        }
         let start = self.bit_len();
-        self.write_int(0u64, count)?;
 
-        let head_int = body_fn(self)?;
+        // the reserved bits are only filled in after the body is written, so they can't be written out before that
+        let head_int = {
+            let mut stream = FlushHold::new(self);
+            stream
+                .write_int(0u64, count)
+                .map_err(Err::from)
+                .and_then(|_| body_fn(&mut stream))?
+        };
+        if !fits_in_bits(head_int, count) {
+            // the reserved bits can't be filled in, so the section is dropped like in `try_write_with`
+            self.buffer.truncate(start);
+            return Err(BitError::ValueTooLarge {
+                value: head_int.to_string(),
+                bits: count,
+            }
+            .into());
+        }
+        let head_int = if has_mixed_order(self.buffer.endianness()) && count > 8 {
+            swap_bytes(head_int, count, false)
+        } else {
+            head_int
+        };
         self.buffer.set_at(start, head_int, count);
 
+        self.buffer.flush_complete()?;
         Ok(())
     }
 }
+
+/// Prevents the stream from writing data out to the writer for as long as the guard exists
+///
+/// Releases the hold when dropped, so the stream stays usable even if writing panics
+struct FlushHold<'s, 'a, E: Endianness> {
+    stream: &'s mut BitWriteStream<'a, E>,
+}
+
+impl<'s, 'a, E: Endianness> FlushHold<'s, 'a, E> {
+    fn new(stream: &'s mut BitWriteStream<'a, E>) -> Self {
+        stream.buffer.hold_flush();
+        FlushHold { stream }
+    }
+}
+
+impl<'s, 'a, E: Endianness> Deref for FlushHold<'s, 'a, E> {
+    type Target = BitWriteStream<'a, E>;
+
+    fn deref(&self) -> &Self::Target {
+        self.stream
+    }
+}
+
+impl<'s, 'a, E: Endianness> DerefMut for FlushHold<'s, 'a, E> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.stream
+    }
+}
+
+impl<'s, 'a, E: Endianness> Drop for FlushHold<'s, 'a, E> {
+    fn drop(&mut self) {
+        self.stream.buffer.release_flush();
+    }
+}
+
+/// Moves the buffer of a stream into a stream with a different endianness
+///
+/// The buffer is moved back when dropped, so the original stream keeps its data even if writing panics
+struct EndiannessSwap<'s, 'a, E: Endianness, E2: Endianness> {
+    original: &'s mut BitWriteStream<'a, E>,
+    stream: BitWriteStream<'a, E2>,
+}
+
+impl<'s, 'a, E: Endianness, E2: Endianness> EndiannessSwap<'s, 'a, E, E2> {
+    fn new(original: &'s mut BitWriteStream<'a, E>, endianness: E2) -> Self {
+        // the buffer is temporarily replaced by an empty one while the other endianness is used
+        let empty = WriteBuffer::for_slice(&mut [], original.buffer.endianness());
+        let buffer = std::mem::replace(&mut original.buffer, empty);
+        EndiannessSwap {
+            original,
+            stream: BitWriteStream {
+                buffer: buffer.with_endianness(endianness),
+            },
+        }
+    }
+}
+
+impl<'s, 'a, E: Endianness, E2: Endianness> Drop for EndiannessSwap<'s, 'a, E, E2> {
+    fn drop(&mut self) {
+        let empty = WriteBuffer::for_slice(&mut [], self.stream.buffer.endianness());
+        let buffer = std::mem::replace(&mut self.stream.buffer, empty);
+        self.original.buffer = buffer.with_endianness(self.original.buffer.endianness());
+    }
+}
@@ -496,3 +496,639 @@ fn test_invalid_utf8() {
 
     assert_eq!(stream.pos(), 6 * 8);
 }
+
+#[test]
+fn test_read_varint_overflow() {
+    let bytes = vec![0xFF, 0xFF, 0x07, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    assert!(matches!(
+        stream.read_varint::<u16>(),
+        Err(BitError::VarIntOverflow { max: 16 })
+    ));
+    assert_eq!(0, stream.pos());
+    assert_eq!(0x1FFFF, stream.read_varint::<u32>().unwrap());
+
+    // too many continuation bytes
+    assert!(matches!(
+        stream.read_varint::<u32>(),
+        Err(BitError::VarIntOverflow { max: 32 })
+    ));
+    assert_eq!(24, stream.pos());
+    assert!(matches!(
+        stream.read_varint::<u64>(),
+        Err(BitError::NotEnoughData { .. })
+    ));
+    assert_eq!(24, stream.pos());
+}
+
+#[test]
+fn test_read_varint_unaligned() {
+    let bytes = vec![0b0101_1001, 0b0000_0101, 0b0000_0000];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    assert_eq!(true, stream.read_bool().unwrap());
+    assert_eq!(300, stream.read_varint::<u16>().unwrap());
+    assert_eq!(17, stream.pos());
+}
+
+#[test]
+fn test_read_unary_long() {
+    let mut bytes = vec![0; 32];
+    bytes[20] = 0b0001_0000;
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+    stream.skip_bits(3).unwrap();
+    assert_eq!(20 * 8 + 3 - 3, stream.read_unary().unwrap());
+    assert_eq!(20 * 8 + 4, stream.pos());
+    assert!(matches!(
+        stream.read_unary(),
+        Err(BitError::NotEnoughData { .. })
+    ));
+    assert_eq!(20 * 8 + 4, stream.pos());
+
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    stream.skip_bits(3).unwrap();
+    assert_eq!(20 * 8 + 4 - 3, stream.read_unary().unwrap());
+    assert_eq!(20 * 8 + 5, stream.pos());
+}
+
+#[test]
+fn test_read_exp_golomb_overflow() {
+    let bytes = vec![0, 0, 0, 0, 0, 0, 0, 0, 0b0100_0000, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+    assert!(matches!(
+        stream.read_exp_golomb(0),
+        Err(BitError::VarIntOverflow { .. })
+    ));
+    assert_eq!(0, stream.pos());
+    assert!(matches!(
+        stream.read_elias_gamma(),
+        Err(BitError::VarIntOverflow { .. })
+    ));
+    assert_eq!(0, stream.pos());
+}
+
+#[test]
+fn test_read_rice_too_many_bits() {
+    let mut bytes = vec![0; 32];
+    bytes[0] = 0b1000_0000;
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+    assert!(matches!(
+        stream.read_rice(128),
+        Err(BitError::TooManyBits {
+            requested: 128,
+            max: 64
+        })
+    ));
+    assert!(matches!(
+        stream.read_rice(65),
+        Err(BitError::TooManyBits {
+            requested: 65,
+            max: 64
+        })
+    ));
+    assert_eq!(0, stream.pos());
+    assert_eq!(0, stream.read_rice(64).unwrap());
+    assert_eq!(65, stream.pos());
+}
+
+#[test]
+fn test_owned_send_sync() {
+    use bitbuffer::{LazyBitRead, LazyBitReadSized};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<BitReadBuffer<'static, LittleEndian>>();
+    assert_send_sync::<BitReadStream<'static, BigEndian>>();
+    assert_send_sync::<LazyBitRead<'static, u32, LittleEndian>>();
+    assert_send_sync::<LazyBitReadSized<'static, String, LittleEndian>>();
+
+    let buffer = BitReadBuffer::new_owned(BYTES.to_vec(), LittleEndian);
+    let mut stream = BitReadStream::new(buffer);
+    let lazy: LazyBitRead<u32, _> = stream.read().unwrap();
+    let worker_stream = stream.clone();
+    let (value, rest) = std::thread::spawn(move || {
+        let mut worker_stream = worker_stream;
+        (
+            lazy.read().unwrap(),
+            worker_stream.read_int::<u16>(16).unwrap(),
+        )
+    })
+    .join()
+    .unwrap();
+    assert_eq!(
+        value,
+        BitReadBuffer::new(BYTES, LittleEndian)
+            .read_int::<u32>(0, 32)
+            .unwrap()
+    );
+    assert_eq!(rest, stream.read_int::<u16>(16).unwrap());
+}
+
+/// Reader that only returns a single byte for every read call
+struct ByteReader<'a>(&'a [u8]);
+
+impl std::io::Read for ByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match (self.0.split_first(), buf.first_mut()) {
+            (Some((byte, rest)), Some(target)) => {
+                *target = *byte;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn test_read_io_stream() {
+    use bitbuffer::BitReadIoStream;
+
+    #[derive(BitRead, Debug, PartialEq)]
+    struct Entry {
+        #[size = 3]
+        kind: u8,
+        name: String,
+        value: u32,
+        #[size = 3]
+        list: Vec<u16>,
+    }
+
+    let mut bytes: Vec<u8> = (0..200u8).map(|i| b'a' + i % 26).collect();
+    bytes[20] = 0;
+    bytes[100] = 0;
+
+    for chunk_size in [1, 3, 64, 8192] {
+        let mut io_stream =
+            BitReadIoStream::with_chunk_size(ByteReader(&bytes), chunk_size, BigEndian);
+        let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+
+        assert_eq!(
+            stream.read_int::<u8>(5).unwrap(),
+            io_stream.read_int::<u8>(5).unwrap()
+        );
+        assert_eq!(
+            stream.read::<Entry>().unwrap(),
+            io_stream.read::<Entry>().unwrap()
+        );
+        assert_eq!(stream.read_bool().unwrap(), io_stream.read_bool().unwrap());
+        stream.skip_bits(84).unwrap();
+        io_stream.skip_bits(84).unwrap();
+        assert_eq!(stream.pos(), io_stream.pos());
+        assert_eq!(
+            stream.read::<Entry>().unwrap(),
+            io_stream.read::<Entry>().unwrap()
+        );
+        assert_eq!(
+            stream.read_float::<f64>().unwrap(),
+            io_stream.read_float::<f64>().unwrap()
+        );
+        assert_eq!(
+            stream.read_bytes(20).unwrap().as_ref(),
+            io_stream.read_bytes(20).unwrap().as_slice()
+        );
+        assert_eq!(stream.pos(), io_stream.pos());
+
+        let bits_left = stream.bits_left();
+        assert!(!io_stream.is_at_end().unwrap());
+        assert!(matches!(
+            io_stream.read_bytes(bits_left / 8 + 1),
+            Err(BitError::NotEnoughData { .. })
+        ));
+        assert_eq!(stream.pos(), io_stream.pos());
+        io_stream.skip_bits(bits_left).unwrap();
+        assert!(io_stream.is_at_end().unwrap());
+        assert!(matches!(
+            io_stream.read_bool(),
+            Err(BitError::NotEnoughData { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_read_io_stream_chunk_end() {
+    use bitbuffer::BitReadIoStream;
+
+    #[derive(BitRead, Debug, PartialEq)]
+    struct Rest {
+        a: u8,
+        #[until_eof]
+        rest: Vec<u8>,
+    }
+
+    let bytes: Vec<u8> = (1..=10).collect();
+
+    for chunk_size in [1, 3, 4, 64] {
+        let mut io_stream =
+            BitReadIoStream::with_chunk_size(ByteReader(&bytes), chunk_size, LittleEndian);
+        let rest = io_stream.read::<Rest>().unwrap();
+        assert_eq!(rest.a, 1);
+        assert_eq!(rest.rest, &bytes[1..]);
+        assert!(io_stream.is_at_end().unwrap());
+
+        // no null terminator in the first chunk
+        let mut io_stream =
+            BitReadIoStream::with_chunk_size(ByteReader(b"foobar"), chunk_size, LittleEndian);
+        assert_eq!(io_stream.read_string(None).unwrap(), "foobar");
+        assert_eq!(io_stream.pos(), 48);
+    }
+}
+
+#[test]
+fn test_read_io_stream_error() {
+    use bitbuffer::BitReadIoStream;
+    use std::io::{Error, Read};
+
+    let reader = [1u8, 2].chain(FailingReader);
+    let mut stream = BitReadIoStream::with_chunk_size(reader, 1, LittleEndian);
+    assert_eq!(stream.read_int::<u16>(16).unwrap(), 0x0201);
+    assert!(matches!(stream.read_bool(), Err(BitError::Io(_))));
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(Error::other("broken pipe"))
+        }
+    }
+}
+
+#[test]
+fn test_read_io_stream_sub_stream_error() {
+    use bitbuffer::{BitReadIoStream, Endianness};
+    use std::cell::Cell;
+    use std::io::Read;
+    use std::rc::Rc;
+
+    /// Record with a body that is too short for the value inside it
+    #[derive(Debug)]
+    struct Record;
+
+    impl<'a, E: Endianness> BitRead<'a, E> for Record {
+        fn read(stream: &mut BitReadStream<'a, E>) -> bitbuffer::Result<Self> {
+            let mut body = stream.read_bits(8)?;
+            body.read_int::<u16>(16)?;
+            Ok(Record)
+        }
+    }
+
+    struct CountingReader(Rc<Cell<usize>>);
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.set(self.0.get() + buf.len());
+            buf.fill(0);
+            Ok(buf.len())
+        }
+    }
+
+    let read = Rc::new(Cell::new(0));
+    let mut stream = BitReadIoStream::with_chunk_size(CountingReader(read.clone()), 16, BigEndian);
+    let err = stream.read::<Record>().unwrap_err();
+    assert!(matches!(err.root(), BitError::NotEnoughData { .. }));
+    // the error is unrelated to the end of the input, so no more data is pulled from the reader
+    assert_eq!(16, read.get());
+    assert_eq!(0, stream.pos());
+}
+
+#[test]
+fn test_align() {
+    use bitbuffer::Padding;
+
+    let bytes = vec![
+        0b1110_0000,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFE,
+    ];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+    assert!(stream.is_aligned());
+    stream.skip_bits(3).unwrap();
+    assert!(!stream.is_aligned());
+    assert_eq!(5, stream.align_checked(8, Padding::Zeros).unwrap());
+    assert!(stream.is_aligned());
+
+    // padding longer than 64 bits
+    assert!(matches!(
+        stream.align_checked(80, Padding::Ones),
+        Err(BitError::InvalidPadding { pos: 8, bits: 72 })
+    ));
+    assert_eq!(8, stream.pos());
+    assert_eq!(71, stream.align_checked(79, Padding::Ones).unwrap());
+    assert!(matches!(
+        stream.align(128),
+        Err(BitError::NotEnoughData { .. })
+    ));
+    assert_eq!(79, stream.pos());
+
+    // alignment is relative to the start of the stream
+    stream.set_pos(4).unwrap();
+    let mut sub = stream.read_bits(16).unwrap();
+    sub.skip_bits(1).unwrap();
+    assert_eq!(7, sub.align_to_byte().unwrap());
+    assert_eq!(8, sub.pos());
+}
+
+#[test]
+fn test_peek_and_rewind() {
+    let bytes = vec![0b1011_0101, 0b0110_1010, 0b1010_1100];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    assert!(stream.peek_bool().unwrap());
+    assert_eq!(0b101, stream.peek_int::<u8>(3).unwrap());
+    assert_eq!(0b0101, stream.peek::<u8>().unwrap() & 0xF);
+    assert_eq!(0, stream.pos());
+
+    stream.skip_bits(20).unwrap();
+    assert!(matches!(
+        stream.peek_int::<u8>(8),
+        Err(BitError::NotEnoughData { .. })
+    ));
+    assert_eq!(20, stream.pos());
+
+    let result: Result<(), BitError> = stream.try_read_with(|stream| {
+        stream.read_int::<u8>(2)?;
+        stream.read_int::<u8>(8)?;
+        Ok(())
+    });
+    assert!(result.is_err());
+    assert_eq!(20, stream.pos());
+
+    let value = stream.try_read_with(|stream| stream.read_int::<u8>(4));
+    assert_eq!(0b1010, value.unwrap());
+    assert_eq!(24, stream.pos());
+}
+
+#[test]
+fn test_read_limits() {
+    use bitbuffer::{BitReadIoStream, LimitKind, ReadLimits};
+
+    let bytes = vec![b'a'; 64];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    stream.set_limits(ReadLimits {
+        max_collection_length: 16,
+        max_string_length: 8,
+        max_allocation: 32,
+    });
+
+    assert!(matches!(
+        stream.read_sized::<Vec<u8>>(17),
+        Err(BitError::LimitExceeded {
+            limit: LimitKind::CollectionLength,
+            requested: 17,
+            max: 16
+        })
+    ));
+    // claimed length doesn't fit in the remaining data
+    assert!(matches!(
+        stream.read_sized::<Vec<u64>>(9),
+        Err(BitError::NotEnoughData {
+            requested: 576,
+            bits_left: 512
+        })
+    ));
+    assert!(matches!(
+        stream.read_sized::<String>(9),
+        Err(BitError::LimitExceeded {
+            limit: LimitKind::StringLength,
+            requested: 9,
+            max: 8
+        })
+    ));
+    // no null terminator, so the string is read until the end of the stream
+    assert!(matches!(
+        stream.read::<String>(),
+        Err(BitError::LimitExceeded {
+            limit: LimitKind::StringLength,
+            requested: 64,
+            max: 8
+        })
+    ));
+    assert_eq!(0, stream.pos());
+
+    // allocations add up over multiple reads
+    assert_eq!(16, stream.read_sized::<Vec<u8>>(16).unwrap().len());
+    assert_eq!("aaaaaaaa", stream.read_sized::<String>(8).unwrap());
+    assert!(matches!(
+        stream.read_sized::<HashMap<u8, u8>>(5),
+        Err(BitError::LimitExceeded {
+            limit: LimitKind::Allocation,
+            requested: 34,
+            max: 32
+        })
+    ));
+
+    // sub streams and clones share the allocation budget with the stream they're created from
+    let mut sub = stream.read_bits(64).unwrap();
+    assert_eq!(stream.limits(), sub.limits());
+    assert!(sub.read_sized::<Vec<u16>>(2).is_ok());
+    let mut clone = stream.clone();
+    assert!(clone.read_sized::<Vec<u8>>(4).is_ok());
+    assert!(sub.read_sized::<Vec<u8>>(1).is_err());
+    assert!(stream.read_sized::<Vec<u8>>(1).is_err());
+
+    // limits are checked on the size before reading a derived type
+    #[derive(BitRead, Debug)]
+    #[allow(dead_code)]
+    struct Items {
+        count: u8,
+        #[size = "count"]
+        items: Vec<u8>,
+    }
+    let bytes = vec![200, 0, 0];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    stream.set_limits(ReadLimits {
+        max_collection_length: 100,
+        ..ReadLimits::default()
+    });
+    let err = stream.read::<Items>().unwrap_err();
+    assert!(matches!(
+        err.root(),
+        BitError::LimitExceeded {
+            limit: LimitKind::CollectionLength,
+            ..
+        }
+    ));
+
+    let input: &[u8] = &[b'a'; 64];
+    let mut stream = BitReadIoStream::with_chunk_size(input, 4, LittleEndian);
+    stream.set_limits(ReadLimits {
+        max_allocation: 16,
+        ..ReadLimits::default()
+    });
+    assert!(stream.read_sized::<Vec<u8>>(10).is_ok());
+    assert!(matches!(
+        stream.read_sized::<Vec<u8>>(10),
+        Err(BitError::LimitExceeded {
+            limit: LimitKind::Allocation,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_read_terminated_limits() {
+    use bitbuffer::{LimitKind, ReadLimits};
+
+    let bytes = vec![1; 64];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    stream.set_limits(ReadLimits {
+        max_collection_length: 16,
+        ..ReadLimits::default()
+    });
+
+    assert!(matches!(
+        stream.read_until::<u8>(&0),
+        Err(BitError::LimitExceeded {
+            limit: LimitKind::CollectionLength,
+            requested: 17,
+            max: 16
+        })
+    ));
+    assert!(matches!(
+        stream.read_until_eof::<u16>(),
+        Err(BitError::LimitExceeded {
+            limit: LimitKind::CollectionLength,
+            requested: 17,
+            max: 16
+        })
+    ));
+}
+
+#[test]
+fn test_read_mixed_bit_order() {
+    use bitbuffer::{BigEndianLsbFirst, BitOrder, Endianness, LittleEndianMsbFirst};
+
+    assert!(LittleEndianMsbFirst.is_le());
+    assert_eq!(LittleEndianMsbFirst.bit_order(), BitOrder::MsbFirst);
+    assert!(BigEndianLsbFirst.is_be());
+    assert_eq!(BigEndianLsbFirst.bit_order(), BitOrder::LsbFirst);
+    assert_eq!(LittleEndian.bit_order(), BitOrder::LsbFirst);
+    assert_eq!(BigEndian.bit_order(), BitOrder::MsbFirst);
+
+    let bytes = [0b1011_0010, 0x34, 0x12, 0x00, 0x80];
+    let buffer = BitReadBuffer::new(&bytes, LittleEndianMsbFirst);
+    assert!(buffer.read_bool(0).unwrap());
+    assert!(!buffer.read_bool(1).unwrap());
+    assert_eq!(buffer.read_int::<u8>(0, 3).unwrap(), 0b101);
+    assert_eq!(buffer.read_int::<u16>(8, 16).unwrap(), 0x1234);
+    assert_eq!(buffer.read_int::<i16>(24, 16).unwrap(), i16::MIN);
+    assert_eq!(buffer.read_int::<u16>(8, 12).unwrap(), 0x134);
+    assert_eq!(buffer.read_int::<u16>(4, 16).unwrap(), 0x4123);
+    assert_eq!(buffer.read_int::<u32>(4, 32).unwrap(), 0x0820_4123);
+    assert_eq!(buffer.read_int::<u64>(4, 36).unwrap(), 0x0_0820_4123);
+    assert_eq!(buffer.read_bytes(4, 2).unwrap().to_vec(), [0x23, 0x41]);
+    assert_eq!(
+        buffer.read_float::<f32>(8).unwrap(),
+        f32::from_le_bytes([0x34, 0x12, 0x00, 0x80])
+    );
+    assert_eq!(
+        buffer.read_float::<f32>(4).unwrap(),
+        f32::from_bits(0x0820_4123)
+    );
+
+    let bytes = [0b0100_1101, 0x12, 0x34, 0x80, 0x00];
+    let buffer = BitReadBuffer::new(&bytes, BigEndianLsbFirst);
+    assert!(buffer.read_bool(0).unwrap());
+    assert!(!buffer.read_bool(1).unwrap());
+    assert_eq!(buffer.read_int::<u8>(0, 3).unwrap(), 0b101);
+    assert_eq!(buffer.read_int::<u16>(8, 16).unwrap(), 0x1234);
+    assert_eq!(buffer.read_int::<i16>(24, 16).unwrap(), i16::MIN);
+    assert_eq!(buffer.read_int::<u16>(8, 12).unwrap(), 0x241);
+    assert_eq!(buffer.read_int::<u16>(4, 16).unwrap(), 0x2441);
+    assert_eq!(buffer.read_bytes(4, 2).unwrap().to_vec(), [0x24, 0x41]);
+    assert_eq!(
+        buffer.read_float::<f32>(8).unwrap(),
+        f32::from_be_bytes([0x12, 0x34, 0x80, 0x00])
+    );
+
+    let bytes = [0b0001_0000, 0b0000_1000];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndianMsbFirst));
+    assert_eq!(stream.read_unary().unwrap(), 3);
+    assert_eq!(stream.read_unary().unwrap(), 8);
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndianLsbFirst));
+    assert_eq!(stream.read_unary().unwrap(), 4);
+    assert_eq!(stream.read_unary().unwrap(), 6);
+
+    // the suffixes of codes aren't reordered, they continue in the bit order of the prefix
+    let bytes = [0x00, 0x7D, 0x27, 0x15, 0x33, 0b1000_0000];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndianMsbFirst));
+    assert_eq!(stream.read_exp_golomb(0).unwrap(), 1000);
+    assert_eq!(stream.read_rice(2).unwrap(), 11);
+    assert_eq!(stream.read_elias_delta().unwrap().get(), 0x333);
+    assert_eq!(stream.read_golomb(3).unwrap(), 0);
+    assert_eq!(stream.pos(), 42);
+
+    let bytes = [0x00, 0xA6, 0xE7, 0b1010_1000, 0b1001_1001, 0b0000_0001];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndianLsbFirst));
+    assert_eq!(stream.read_exp_golomb(0).unwrap(), 1000);
+    assert_eq!(stream.read_rice(2).unwrap(), 11);
+    assert_eq!(stream.read_elias_delta().unwrap().get(), 0x333);
+    assert_eq!(stream.read_golomb(3).unwrap(), 0);
+    assert_eq!(stream.pos(), 42);
+}
+
+#[test]
+fn test_read_bit_int() {
+    use bitbuffer::{SInt, UInt};
+
+    let bytes = [
+        0b1011_1110,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        0b1000_0000,
+    ];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+    assert_eq!(UInt::<3>::new(5).unwrap(), stream.read().unwrap());
+    assert_eq!(SInt::<5>::new(-2).unwrap(), stream.read().unwrap());
+    assert_eq!(UInt::<64>::MAX, stream.read().unwrap());
+    assert_eq!(SInt::<1>::MIN, stream.read().unwrap());
+    assert_eq!(73, stream.pos());
+    assert!(matches!(
+        stream.read::<SInt<8>>(),
+        Err(BitError::NotEnoughData {
+            requested: 8,
+            bits_left: 7
+        })
+    ));
+
+    let bytes = [0x0F; 17];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, LittleEndian));
+    assert_eq!(
+        UInt::<128>::new(0x0F0F_0F0F_0F0F_0F0F_0F0F_0F0F_0F0F_0F0F).unwrap(),
+        stream.read().unwrap()
+    );
+    assert_eq!(SInt::<4>::new(-1).unwrap(), stream.read().unwrap());
+    assert_eq!(SInt::<4>::new(0).unwrap(), stream.read().unwrap());
+    assert_eq!(Some(128), bitbuffer::bit_size_of::<UInt<128>>());
+
+    // conversions with the primitive integers
+    let bytes = [0b1011_1110, 0b1000_0000];
+    let mut stream = BitReadStream::new(BitReadBuffer::new(&bytes, BigEndian));
+    let raw: u16 = stream.read_int(12).unwrap();
+    let value = UInt::<12>::try_from(raw).unwrap();
+    assert_eq!(0xBE8, u16::try_from(value).unwrap());
+    assert!(u8::try_from(value).is_err());
+    assert_eq!(UInt::<3>::new(7), UInt::<3>::try_from(7u8).ok());
+    assert!(matches!(
+        UInt::<3>::try_from(8usize),
+        Err(BitError::ValueTooLarge { bits: 3, .. })
+    ));
+    assert_eq!(SInt::<4>::new(-8), SInt::<4>::try_from(-8i64).ok());
+    assert!(matches!(
+        SInt::<4>::try_from(8i8),
+        Err(BitError::ValueTooLarge { bits: 4, .. })
+    ));
+    assert_eq!(-2i8, i8::try_from(SInt::<5>::new(-2).unwrap()).unwrap());
+    assert!(i32::try_from(SInt::<40>::MIN).is_err());
+    assert_eq!(i128::from(SInt::<40>::MIN), -(1 << 39));
+}
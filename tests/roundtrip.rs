@@ -1,41 +1,42 @@
 use bitbuffer::{
-    BigEndian, BitRead, BitReadBuffer, BitReadStream, BitWrite, BitWriteStream, LittleEndian,
+    BigEndian, BitRead, BitReadBuffer, BitReadStream, BitWrite, BitWriteStream, DynamicEndian,
+    Endianness, LittleEndian,
 };
 use std::fmt::Debug;
 
+#[track_caller]
+fn roundtrip_endianness<T: BitRead<'static, E> + BitWrite<E> + Debug + PartialEq, E: Endianness>(
+    val: &T,
+    endianness: E,
+) {
+    let mut data = Vec::new();
+    let size = {
+        let mut stream = BitWriteStream::new(&mut data, endianness);
+        stream.write(val).unwrap();
+        stream.bit_len()
+    };
+    let mut read = BitReadStream::new(BitReadBuffer::new_owned(data, endianness));
+    assert_eq!(val, &read.read::<T>().unwrap());
+    assert_eq!(size, read.pos());
+}
+
 #[track_caller]
 fn roundtrip<
     T: BitRead<'static, BigEndian>
         + BitWrite<BigEndian>
         + BitRead<'static, LittleEndian>
         + BitWrite<LittleEndian>
+        + BitRead<'static, DynamicEndian>
+        + BitWrite<DynamicEndian>
         + Debug
         + PartialEq,
 >(
     val: T,
 ) {
-    {
-        let mut data = Vec::new();
-        let size = {
-            let mut stream = BitWriteStream::new(&mut data, LittleEndian);
-            stream.write(&val).unwrap();
-            stream.bit_len()
-        };
-        let mut read = BitReadStream::new(BitReadBuffer::new_owned(data, LittleEndian));
-        assert_eq!(val, read.read().unwrap());
-        assert_eq!(size, read.pos());
-    }
-    {
-        let mut data = Vec::new();
-        let size = {
-            let mut stream = BitWriteStream::new(&mut data, BigEndian);
-            stream.write(&val).unwrap();
-            stream.bit_len()
-        };
-        let mut read = BitReadStream::new(BitReadBuffer::new_owned(data, BigEndian));
-        assert_eq!(val, read.read().unwrap());
-        assert_eq!(size, read.pos());
-    }
+    roundtrip_endianness(&val, LittleEndian);
+    roundtrip_endianness(&val, BigEndian);
+    roundtrip_endianness(&val, DynamicEndian::Little);
+    roundtrip_endianness(&val, DynamicEndian::Big);
 }
 
 #[test]
@@ -112,3 +113,116 @@ fn test_tuple() {
     roundtrip((1, false));
     roundtrip((1, 10.12, String::from("asd")));
 }
+
+#[test]
+fn test_varint() {
+    use bitbuffer::{UBitVar, VarInt};
+
+    roundtrip(VarInt(0u8));
+    roundtrip(VarInt(u8::MAX));
+    roundtrip(VarInt(300u16));
+    roundtrip(VarInt(u32::MAX));
+    roundtrip(VarInt(u64::MAX));
+    roundtrip(VarInt(u128::MAX));
+    roundtrip(VarInt(-1i8));
+    roundtrip(VarInt(i16::MIN));
+    roundtrip(VarInt(i32::MAX));
+    roundtrip(VarInt(i64::MIN));
+    roundtrip(VarInt(i128::MIN));
+    roundtrip((true, VarInt(12345u32), VarInt(-12345i64)));
+    roundtrip(UBitVar(0xF));
+    roundtrip(UBitVar(0xFF));
+    roundtrip(UBitVar(0xFFF));
+    roundtrip(UBitVar(u32::MAX));
+    roundtrip((false, UBitVar(0x123), UBitVar(0x12345)));
+}
+
+#[test]
+fn test_varint_struct() {
+    use bitbuffer::VarInt;
+
+    #[derive(Debug, PartialEq, BitRead, BitWrite)]
+    struct Message {
+        flag: bool,
+        id: VarInt<u64>,
+        delta: VarInt<i32>,
+    }
+    roundtrip(Message {
+        flag: true,
+        id: VarInt(1 << 40),
+        delta: VarInt(-300),
+    });
+}
+
+#[test]
+fn test_codes() {
+    use bitbuffer::codes::{
+        EliasDelta, EliasGamma, ExpGolomb, Golomb, Rice, SignedExpGolomb, Unary,
+    };
+    use std::num::NonZeroU64;
+
+    for value in [0, 1, 2, 3, 7, 8, 100, 1000] {
+        roundtrip(Unary(value));
+        roundtrip((true, Unary(value)));
+        roundtrip(Rice::<0>(value));
+        roundtrip(Rice::<3>(value));
+        roundtrip((false, Golomb::<1>(value)));
+        roundtrip(Golomb::<3>(value));
+        roundtrip(Golomb::<10>(value));
+    }
+    for value in [0, 1, 2, 3, 1000, u32::MAX as u64, u64::MAX - 1, u64::MAX] {
+        roundtrip(ExpGolomb::<0>(value));
+        roundtrip((true, ExpGolomb::<5>(value)));
+        roundtrip(ExpGolomb::<64>(value));
+        roundtrip(Rice::<60>(value));
+    }
+    for value in [0, 1, -1, 2, -2, 1000, -1000, i64::MAX, i64::MIN] {
+        roundtrip(SignedExpGolomb::<0>(value));
+        roundtrip((false, SignedExpGolomb::<3>(value)));
+    }
+    for value in [1, 2, 3, 5, 17, 1000, u32::MAX as u64, u64::MAX] {
+        let value = NonZeroU64::new(value).unwrap();
+        roundtrip(EliasGamma(value));
+        roundtrip((true, EliasGamma(value)));
+        roundtrip(EliasDelta(value));
+        roundtrip((false, EliasDelta(value)));
+    }
+}
+
+#[test]
+fn test_dynamic_endian_matches_static() {
+    #[derive(Debug, PartialEq, BitRead, BitWrite)]
+    struct Header {
+        #[size = 3]
+        kind: u8,
+        length: u32,
+        offset: i64,
+        scale: f32,
+    }
+    let header = Header {
+        kind: 5,
+        length: 0x1234_5678,
+        offset: -0x1234_5678_9ABC,
+        scale: 1.5,
+    };
+
+    let mut little = Vec::new();
+    BitWriteStream::new(&mut little, LittleEndian)
+        .write(&header)
+        .unwrap();
+    let mut dynamic = Vec::new();
+    BitWriteStream::new(&mut dynamic, DynamicEndian::Little)
+        .write(&header)
+        .unwrap();
+    assert_eq!(little, dynamic);
+
+    let mut big = Vec::new();
+    BitWriteStream::new(&mut big, BigEndian)
+        .write(&header)
+        .unwrap();
+    let mut dynamic = Vec::new();
+    BitWriteStream::new(&mut dynamic, DynamicEndian::Big)
+        .write(&header)
+        .unwrap();
+    assert_eq!(big, dynamic);
+}
@@ -243,3 +243,810 @@ fn test_write_last_slice() {
     assert_eq!(0b1000, read.read_int::<u8>(4).unwrap());
     assert_eq!(true, read.read_bool().unwrap());
 }
+
+#[test]
+fn test_write_wide_unaligned_be() {
+    let mut data = Vec::new();
+    {
+        let mut stream = BitWriteStream::new(&mut data, BigEndian);
+
+        stream.write_int(0u8, 5).unwrap();
+        stream.write_int(0x0ABC_DEF0_1234_5678u64, 62).unwrap();
+        stream
+            .write_int(0x1234_5678_9ABC_DEF0_1234_5678u128, 125)
+            .unwrap();
+    }
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&data, BigEndian));
+
+    assert_eq!(0, read.read_int::<u8>(5).unwrap());
+    assert_eq!(0x0ABC_DEF0_1234_5678u64, read.read_int::<u64>(62).unwrap());
+    assert_eq!(
+        0x1234_5678_9ABC_DEF0_1234_5678u128,
+        read.read_int::<u128>(125).unwrap()
+    );
+}
+
+fn write_test_data<E: bitbuffer::Endianness>(stream: &mut BitWriteStream<E>) {
+    for i in 0..5000u32 {
+        stream.write_bool(i % 3 == 0).unwrap();
+        stream.write_int(i, 17).unwrap();
+        if i % 1000 == 0 {
+            stream
+                .reserve_length(16, |stream| {
+                    for j in 0..2000u16 {
+                        stream.write_int(j, 13)?;
+                    }
+                    Ok::<(), bitbuffer::BitError>(())
+                })
+                .unwrap();
+        }
+        stream.write_string("foo", None).unwrap();
+    }
+    stream.write_bool(true).unwrap();
+}
+
+#[test]
+fn test_write_to_writer() {
+    let mut expected = Vec::new();
+    write_test_data(&mut BitWriteStream::new(&mut expected, LittleEndian));
+
+    let mut written = Vec::new();
+    let mut stream = BitWriteStream::from_writer(&mut written, LittleEndian);
+    write_test_data(&mut stream);
+    let bit_len = stream.bit_len();
+    stream.finish().unwrap();
+
+    assert_eq!(expected.len(), bit_len.div_ceil(8));
+    assert_eq!(expected, written);
+
+    let mut written = Vec::new();
+    let mut stream = BitWriteStream::from_writer(&mut written, BigEndian);
+    stream.write_int(0b101u8, 3).unwrap();
+    stream.finish().unwrap();
+    assert_eq!(written, [0b1010_0000]);
+}
+
+#[test]
+fn test_write_to_writer_drop() {
+    use bitbuffer::BitError;
+
+    let mut written = Vec::new();
+    let mut stream = BitWriteStream::from_writer(&mut written, BigEndian);
+    stream.write_int(0b101u8, 3).unwrap();
+    assert!(matches!(
+        stream.write_int_checked(300u16, 4),
+        Err(BitError::ValueTooLarge { .. })
+    ));
+    drop(stream);
+    assert!(written.is_empty());
+}
+
+#[test]
+fn test_write_to_writer_varint_flush() {
+    use std::cell::Cell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    struct CountingWriter(Rc<Cell<usize>>);
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.set(self.0.get() + buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let written = Rc::new(Cell::new(0));
+    let mut writer = CountingWriter(written.clone());
+    let mut stream = BitWriteStream::from_writer(&mut writer, LittleEndian);
+    for i in 0..10_000u32 {
+        stream.write_varint(i).unwrap();
+        stream.write_ubit_var(i & 0xF).unwrap();
+    }
+    // complete bytes are written out while writing instead of being buffered until the end
+    assert!(written.get() > 0);
+    let bit_len = stream.bit_len();
+    stream.finish().unwrap();
+    assert_eq!(written.get(), bit_len.div_ceil(8));
+}
+
+#[test]
+fn test_write_to_writer_error() {
+    use std::io::{Error, Write};
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut writer = FailingWriter;
+    let mut stream = BitWriteStream::from_writer(&mut writer, LittleEndian);
+    stream.write_int(1u8, 3).unwrap();
+    assert!(matches!(stream.finish(), Err(bitbuffer::BitError::Io(_))));
+
+    let mut stream = BitWriteStream::from_writer(&mut writer, LittleEndian);
+    let result = (0..10_000).try_for_each(|i| stream.write_int(i as u32, 32));
+    assert!(matches!(result, Err(bitbuffer::BitError::Io(_))));
+}
+
+#[test]
+fn test_try_write_with() {
+    fn write_failing<E: bitbuffer::Endianness>(
+        stream: &mut BitWriteStream<E>,
+    ) -> bitbuffer::Result<()> {
+        stream.try_write_with(|stream| {
+            stream.write_int(0xFFFFu16, 13)?;
+            stream.write_int(u8::MAX, 9)
+        })
+    }
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    stream.write_int(0b101u8, 3).unwrap();
+    assert!(write_failing(&mut stream).is_err());
+    assert_eq!(3, stream.bit_len());
+    stream.write_int(0b11u8, 2).unwrap();
+    assert_eq!(data, [0b1_1101]);
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write_int(0b101u8, 3).unwrap();
+    assert!(write_failing(&mut stream).is_err());
+    stream.write_int(0b11u8, 2).unwrap();
+    assert_eq!(data, [0b1011_1000]);
+
+    let mut data = [0; 4];
+    let mut stream = BitWriteStream::from_slice(&mut data, BigEndian);
+    stream.write_int(0b101u8, 3).unwrap();
+    assert!(write_failing(&mut stream).is_err());
+    stream
+        .try_write_with(|stream| stream.write_int(0b11u8, 2))
+        .unwrap();
+    assert_eq!(5, stream.bit_len());
+    assert_eq!(data, [0b1011_1000, 0, 0, 0]);
+
+    // nothing written by a failed closure reaches the writer, even when it would normally be flushed
+    let mut expected = Vec::new();
+    let mut expected_stream = BitWriteStream::new(&mut expected, LittleEndian);
+    let mut written = Vec::new();
+    let mut stream = BitWriteStream::from_writer(&mut written, LittleEndian);
+    for stream in [&mut expected_stream, &mut stream] {
+        stream.write_int(0b101u8, 3).unwrap();
+    }
+    let result = stream.try_write_with(|stream| {
+        for i in 0..10_000u32 {
+            stream.write_int(i, 32)?;
+        }
+        stream.write_int(u8::MAX, 9)
+    });
+    assert!(result.is_err());
+    for stream in [&mut expected_stream, &mut stream] {
+        stream.write_int(0xABCDu16, 16).unwrap();
+    }
+    stream.finish().unwrap();
+    assert_eq!(expected, written);
+}
+
+#[test]
+fn test_try_write_with_panic() {
+    use std::cell::Cell;
+    use std::io::Write;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    struct CountingWriter(Rc<Cell<usize>>);
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.set(self.0.get() + buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let written = Rc::new(Cell::new(0));
+    let mut writer = CountingWriter(written.clone());
+    let mut stream = BitWriteStream::from_writer(&mut writer, LittleEndian);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        stream.try_write_with::<(), bitbuffer::BitError, _>(|_| panic!("failed to write"))
+    }));
+    assert!(result.is_err());
+
+    // writing data out isn't blocked after the panic
+    for i in 0..10_000u32 {
+        stream.write_int(i, 32).unwrap();
+    }
+    assert!(written.get() > 0);
+    stream.finish().unwrap();
+    assert_eq!(written.get(), 40_000);
+}
+
+#[test]
+fn test_reserve_length_big_endian() {
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write_int(0b101u8, 3).unwrap();
+    stream
+        .reserve_length(10, |stream| stream.write_int(0u16, 12))
+        .unwrap();
+    stream
+        .reserve_byte_length(8, |stream| stream.write_int(0xFFu8, 8))
+        .unwrap();
+
+    let mut read = BitReadStream::from(BitReadBuffer::new(&data, BigEndian));
+    assert_eq!(0b101, read.read_int::<u8>(3).unwrap());
+    assert_eq!(12, read.read_int::<u16>(10).unwrap());
+    assert_eq!(0, read.read_int::<u16>(12).unwrap());
+    assert_eq!(1, read.read_int::<u8>(8).unwrap());
+    assert_eq!(0xFF, read.read_int::<u8>(8).unwrap());
+}
+
+#[test]
+fn test_write_sized_vec() {
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    stream.write_sized(&vec![1u8, 2, 3], 3).unwrap();
+    assert!(matches!(
+        stream.write_sized(&vec![1u8, 2], 3),
+        Err(bitbuffer::BitError::LengthMismatch {
+            length: 2,
+            requested_length: 3
+        })
+    ));
+    assert_eq!(data, [1, 2, 3]);
+}
+
+#[test]
+fn test_write_with_endianness() {
+    let mut written = Vec::new();
+    let mut stream = BitWriteStream::from_writer(&mut written, LittleEndian);
+    stream.write_int(0x1234u16, 16).unwrap();
+    stream.write_int_endian(0x1234u16, 16, BigEndian).unwrap();
+    stream.write_int(0x1234u16, 16).unwrap();
+    assert_eq!(48, stream.bit_len());
+    stream.finish().unwrap();
+    assert_eq!(written, [0x34, 0x12, 0x12, 0x34, 0x34, 0x12]);
+
+    let mut data = [0; 4];
+    let mut stream = BitWriteStream::from_slice(&mut data, BigEndian);
+    stream
+        .with_endianness(LittleEndian, |stream| stream.write_int(0x1234u16, 16))
+        .unwrap();
+    stream.write_int(0x1234u16, 16).unwrap();
+    assert_eq!(data, [0x34, 0x12, 0x12, 0x34]);
+}
+
+#[test]
+fn test_write_with_endianness_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write_int(0x12u8, 8).unwrap();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        stream.with_endianness(LittleEndian, |stream| {
+            stream.write_int(0x34u8, 8).unwrap();
+            panic!("failed to write")
+        })
+    }));
+    assert!(result.is_err());
+
+    // the stream still writes to the original data after the panic
+    assert_eq!(16, stream.bit_len());
+    stream.write_int(0x56u8, 8).unwrap();
+    assert_eq!(data, [0x12, 0x34, 0x56]);
+}
+
+#[test]
+fn test_write_prealloc() {
+    use bitbuffer::{BitError, BitWrite};
+
+    assert_eq!(BitWrite::<LittleEndian>::written_bit_size(&[1u8, 2, 3]), 24);
+    assert_eq!(BitWrite::<LittleEndian>::written_bit_size(&(1u8, true)), 9);
+    assert_eq!(BitWrite::<LittleEndian>::written_bit_size(&None::<u32>), 1);
+    assert_eq!(
+        BitWrite::<LittleEndian>::written_bit_size(&vec!["ab".to_string()]),
+        24
+    );
+    assert_eq!(
+        BitWrite::<LittleEndian>::written_bit_size(&bitbuffer::VarInt(300u32)),
+        16
+    );
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    stream.write_all_prealloc(&[1u32, 2, 3]).unwrap();
+    assert_eq!(96, stream.bit_len());
+    assert!(data.capacity() >= 12);
+    assert_eq!(data, [1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0]);
+
+    let mut data = [0; 3];
+    let mut stream = BitWriteStream::from_slice(&mut data, BigEndian);
+    stream.write_bool(true).unwrap();
+    stream.write_prealloc(&0x7Fu8).unwrap();
+    assert!(matches!(
+        stream.write_all_prealloc(&[1u8, 2]),
+        Err(BitError::NotEnoughSpace {
+            requested: 16,
+            bits_left: 15
+        })
+    ));
+    assert_eq!(9, stream.bit_len());
+    stream.write_prealloc(&0x7Fu8).unwrap();
+    assert_eq!(data, [0b1011_1111, 0b1011_1111, 0b1000_0000]);
+}
+
+#[test]
+fn test_write_bit_int() {
+    use bitbuffer::{SInt, UInt};
+
+    assert_eq!(UInt::<12>::MAX.get(), 0xFFF);
+    assert_eq!(UInt::<12>::new(0x1000), None);
+    assert_eq!(UInt::<128>::MAX.get(), u128::MAX);
+    assert_eq!(SInt::<1>::MIN.get(), -1);
+    assert_eq!(SInt::<1>::MAX.get(), 0);
+    assert_eq!(SInt::<12>::new(-2049), None);
+    assert_eq!(SInt::<128>::MIN.get(), i128::MIN);
+    assert_eq!(UInt::<4>::new_truncated(0x1F).get(), 0xF);
+    assert_eq!(SInt::<4>::new_truncated(0xF).get(), -1);
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write(&UInt::<3>::new(5).unwrap()).unwrap();
+    stream.write(&SInt::<5>::new(-2).unwrap()).unwrap();
+    stream.write(&SInt::<128>::MIN).unwrap();
+    stream.write(&UInt::<1>::MAX).unwrap();
+    assert_eq!(137, stream.bit_len());
+    assert_eq!(data[0], 0b1011_1110);
+
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, BigEndian));
+    assert_eq!(UInt::<3>::new(5).unwrap(), read.read().unwrap());
+    assert_eq!(SInt::<5>::new(-2).unwrap(), read.read().unwrap());
+    assert_eq!(SInt::<128>::MIN, read.read().unwrap());
+    assert_eq!(UInt::<1>::MAX, read.read().unwrap());
+}
+
+#[test]
+fn test_write_int_checked() {
+    use bitbuffer::BitError;
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    stream.write_int_checked(7u8, 3).unwrap();
+    stream.write_int_checked(-4i8, 3).unwrap();
+    stream.write_int_checked(3i8, 3).unwrap();
+    stream.write_int_checked(0u8, 0).unwrap();
+    stream.write_int_checked(u128::MAX, 128).unwrap();
+    stream.write_int_checked(i64::MIN, 64).unwrap();
+    assert_eq!(201, stream.bit_len());
+
+    let result = stream.write_int_checked(8u8, 3);
+    assert!(matches!(
+        result,
+        Err(BitError::ValueTooLarge { ref value, bits: 3 }) if value == "8"
+    ));
+    let result = stream.write_int_checked(-5i8, 3);
+    assert!(matches!(
+        result,
+        Err(BitError::ValueTooLarge { ref value, bits: 3 }) if value == "-5"
+    ));
+    assert!(stream.write_int_checked(4i8, 3).is_err());
+    assert!(stream.write_int_checked(1u8, 0).is_err());
+    assert!(stream.write_sized(&300u16, 8).is_err());
+    assert_eq!(201, stream.bit_len());
+
+    stream.write_int(0x1FFu16, 8).unwrap();
+    stream.write_int(-1i8, 2).unwrap();
+    assert_eq!(211, stream.bit_len());
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    let result = stream.reserve_length(4, |stream| stream.write_int(0u16, 16));
+    assert!(matches!(
+        result,
+        Err(BitError::ValueTooLarge { ref value, bits: 4 }) if value == "16"
+    ));
+    // the section is dropped when the length doesn't fit
+    assert_eq!(0, stream.bit_len());
+
+    let result = stream.reserve_length(56, |stream| stream.write_int(0u16, 16));
+    assert!(matches!(
+        result,
+        Err(BitError::TooManyBits {
+            requested: 56,
+            max: 55
+        })
+    ));
+}
+
+#[test]
+fn test_write_minifloat() {
+    use bitbuffer::minifloat::{MiniFloat, BF16, F16};
+
+    // every value of the formats survives a round trip through f32, including NaN payloads
+    for bits in 0..=u16::MAX as u32 {
+        assert_eq!(bits, F16::from_f32(F16::from_bits(bits).to_f32()).to_bits());
+        assert_eq!(
+            bits,
+            BF16::from_f32(BF16::from_bits(bits).to_f32()).to_bits()
+        );
+    }
+    for bits in 0..=u8::MAX as u32 {
+        type E4M3 = MiniFloat<4, 3>;
+        assert_eq!(
+            bits,
+            E4M3::from_f32(E4M3::from_bits(bits).to_f32()).to_bits()
+        );
+    }
+
+    assert_eq!(F16::from_bits(0x7BFF).to_f32(), 65504.0);
+    assert_eq!(F16::from_bits(0x0001).to_f32(), 2f32.powi(-24));
+    assert_eq!(F16::from_bits(0x03FF).to_f32(), 1023.0 * 2f32.powi(-24));
+    assert_eq!(
+        F16::from_bits(0x8000).to_f32().to_bits(),
+        (-0.0f32).to_bits()
+    );
+    assert_eq!(F16::from_bits(0xFC00).to_f32(), f32::NEG_INFINITY);
+    assert!(F16::from_bits(0x7E00).to_f32().is_nan());
+    assert_eq!(
+        BF16::from_bits(0x0001).to_f32(),
+        f32::from_bits(0x0001_0000)
+    );
+
+    // ties round to an even mantissa
+    assert_eq!(F16::from_f32(2049.0).to_f32(), 2048.0);
+    assert_eq!(F16::from_f32(2051.0).to_f32(), 2052.0);
+    assert_eq!(F16::from_f32(2f32.powi(-25)).to_bits(), 0);
+    assert_eq!(F16::from_f32(3.0 * 2f32.powi(-26)).to_bits(), 1);
+    assert_eq!(F16::from_f32(1023.5 * 2f32.powi(-24)).to_bits(), 0x0400);
+    // values that are too large become infinite
+    assert_eq!(F16::from_f32(65519.0).to_f32(), 65504.0);
+    assert_eq!(F16::from_f32(65520.0).to_f32(), f32::INFINITY);
+    assert_eq!(F16::from_f32(-1e10).to_f32(), f32::NEG_INFINITY);
+    assert_eq!(F16::from_f32(f32::MIN_POSITIVE / 4.0).to_bits(), 0);
+    // NaN payloads are truncated without turning the value into an infinity
+    assert_eq!(F16::from_f32(f32::from_bits(0x7FC0_0001)).to_bits(), 0x7E00);
+    assert_eq!(F16::from_f32(f32::from_bits(0x7F80_0001)).to_bits(), 0x7E00);
+    assert_eq!(F16::from_f32(f32::from_bits(0xFFA0_2000)).to_bits(), 0xFD01);
+
+    // bfloat16 rounds the top half of the f32 bits
+    for bits in (0..u32::MAX).step_by(0x1234_567) {
+        let value = f32::from_bits(bits);
+        if !value.is_nan() {
+            let expected = (bits + 0x7FFF + ((bits >> 16) & 1)) >> 16;
+            assert_eq!(BF16::from_f32(value).to_bits(), expected);
+        }
+    }
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    stream.write_bool(true).unwrap();
+    stream.write_f16(1.0).unwrap();
+    stream.write_bf16(-2.0).unwrap();
+    stream.write_minifloat(0.25, 4, 3).unwrap();
+    stream.write(&F16::from_f32(f32::NAN)).unwrap();
+    assert_eq!(57, stream.bit_len());
+
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    assert!(read.read_bool().unwrap());
+    assert_eq!(1.0, read.read_f16().unwrap());
+    assert_eq!(-2.0, read.read_bf16().unwrap());
+    assert_eq!(0.25, read.read_minifloat(4, 3).unwrap());
+    assert_eq!(F16::from_f32(f32::NAN), read.read::<F16>().unwrap());
+}
+
+#[test]
+fn test_write_quantized() {
+    use bitbuffer::quantized::{BitCoord, BitNormal, Fixed, UFixed};
+    use bitbuffer::BitWrite;
+
+    // every representable value is written back as the same raw value
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    for raw in 0..1024u32 {
+        stream.write_int(raw, 10).unwrap();
+    }
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    for _ in 0..1024 {
+        let value = read.read_quantized_float(10, -10.0, 10.0).unwrap();
+        stream
+            .write_quantized_float(value, 10, -10.0, 10.0)
+            .unwrap();
+    }
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    for raw in 0..1024u32 {
+        assert_eq!(raw, read.read_int::<u32>(10).unwrap());
+    }
+
+    // values are rounded to the nearest step and clamped to the range
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndian);
+    for value in [0.3, 0.5, 0.8, -5.0, 5.0, f32::NAN] {
+        stream.write_quantized_float(value, 2, 0.0, 3.0).unwrap();
+    }
+    assert_eq!(data, [0b0001_0100, 0b1100_0000]);
+
+    assert_eq!(Fixed::<8, 0>::from_f64(2.5).to_bits(), 3);
+    assert_eq!(Fixed::<8, 0>::from_f64(-2.5).to_bits(), -3);
+    assert_eq!(Fixed::<8, 0>::from_f64(f64::NAN).to_bits(), 0);
+    assert_eq!(Fixed::<4, 4>::from_f64(-9.0), Fixed::<4, 4>::MIN);
+    assert_eq!(Fixed::<4, 4>::from_bits(0xFF).to_bits(), -1);
+    assert_eq!(UFixed::<4, 4>::from_bits(0x1FF).to_bits(), 0xFF);
+    assert_eq!(UFixed::<0, 64>::MAX.to_bits(), u64::MAX);
+    assert_eq!(Fixed::<64, 0>::from_f64(1e30), Fixed::<64, 0>::MAX);
+    for raw in -2048..2048 {
+        let value = Fixed::<4, 8>::from_bits(raw);
+        assert_eq!(value, Fixed::<4, 8>::from_f64(value.to_f64()));
+    }
+
+    assert_eq!(
+        BitWrite::<LittleEndian>::written_bit_size(&BitCoord(0.0)),
+        2
+    );
+    assert_eq!(
+        BitWrite::<LittleEndian>::written_bit_size(&BitCoord(0.5)),
+        8
+    );
+    assert_eq!(
+        BitWrite::<LittleEndian>::written_bit_size(&BitCoord(-1.0)),
+        17
+    );
+    assert_eq!(
+        BitWrite::<LittleEndian>::written_bit_size(&BitCoord(1.5)),
+        22
+    );
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndian);
+    for value in [1.01, 1.02, -0.01, -1e6, 1234.5] {
+        stream.write_bit_coord(value).unwrap();
+    }
+    for value in [0.5, -2.0, 0.0001, -0.0001] {
+        stream.write(&BitNormal(value)).unwrap();
+    }
+    stream.write_bit_vec3_coord([0.0, 1.5, -0.01]).unwrap();
+    stream.write_bit_vec3_normal([0.6, 0.0, -0.8]).unwrap();
+
+    let mut read = BitReadStream::new(BitReadBuffer::new(&data, LittleEndian));
+    assert_eq!(1.0, read.read_bit_coord().unwrap());
+    assert_eq!(1.03125, read.read_bit_coord().unwrap());
+    assert_eq!(0.0, read.read_bit_coord().unwrap());
+    assert_eq!(-(16384.0 + 31.0 / 32.0), read.read_bit_coord().unwrap());
+    assert_eq!(BitCoord(1234.5), read.read().unwrap());
+    assert_eq!(1024.0 / 2047.0, read.read_bit_normal().unwrap());
+    assert_eq!(-1.0, read.read_bit_normal().unwrap());
+    assert_eq!(0.0, read.read_bit_normal().unwrap());
+    assert_eq!(BitNormal(0.0), read.read().unwrap());
+    assert_eq!([0.0, 1.5, 0.0], read.read_bit_vec3_coord().unwrap());
+    let [x, y, z] = read.read_bit_vec3_normal().unwrap();
+    assert_eq!(x, 1228.0 / 2047.0);
+    assert_eq!(y, 0.0);
+    assert_eq!(z, -(1.0 - x * x).sqrt());
+    assert!(read.bits_left() < 8);
+}
+
+#[test]
+fn test_write_bit_order() {
+    use bitbuffer::{BigEndianLsbFirst, BitOrder, Endianness, LittleEndianMsbFirst};
+
+    // integers are split into bytes from the least significant bit, with the last byte holding the remaining bits,
+    // the byte order decides the order of the bytes and the bit order the order of the bits within each byte
+    fn reference_bits<E: Endianness>(endianness: E, value: u128, count: usize) -> Vec<bool> {
+        let mut bytes: Vec<(u128, usize)> = (0..count)
+            .step_by(8)
+            .map(|pos| {
+                let size = (count - pos).min(8);
+                ((value >> pos) & ((1 << size) - 1), size)
+            })
+            .collect();
+        if endianness.is_be() {
+            bytes.reverse();
+        }
+        bytes
+            .into_iter()
+            .flat_map(|(byte, size)| {
+                (0..size).map(move |i| match endianness.bit_order() {
+                    BitOrder::LsbFirst => byte >> i & 1 == 1,
+                    BitOrder::MsbFirst => byte >> (size - 1 - i) & 1 == 1,
+                })
+            })
+            .collect()
+    }
+
+    fn pack<E: Endianness>(endianness: E, bits: &[bool]) -> Vec<u8> {
+        bits.chunks(8)
+            .map(|chunk| {
+                chunk.iter().enumerate().fold(0, |byte, (i, bit)| {
+                    let shift = match endianness.bit_order() {
+                        BitOrder::LsbFirst => i,
+                        BitOrder::MsbFirst => 7 - i,
+                    };
+                    byte | (*bit as u8) << shift
+                })
+            })
+            .collect()
+    }
+
+    fn check<E: Endianness>(endianness: E) {
+        let mut seed = 0x2545_f491_4f6c_dd1d_u128;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(0x2d99_7879_2689_8a4e_f3a2_dd36_8a72_6e6b)
+                .wrapping_add(1);
+            seed ^ seed >> 64
+        };
+        let ints: Vec<(u128, usize)> = (0..400)
+            .map(|_| {
+                let count = (next() % 128) as usize + 1;
+                (next() & (u128::MAX >> (128 - count)), count)
+            })
+            .collect();
+
+        let mut data = Vec::new();
+        let mut stream = BitWriteStream::new(&mut data, endianness);
+        let mut bits = Vec::new();
+        for &(value, count) in &ints {
+            match count {
+                0..=8 => stream.write_int(value as u8, count),
+                9..=16 => stream.write_int(value as u16, count),
+                17..=32 => stream.write_int(value as u32, count),
+                33..=64 => stream.write_int(value as u64, count),
+                _ => stream.write_int(value, count),
+            }
+            .unwrap();
+            stream.write_bool(count % 3 == 0).unwrap();
+            bits.extend(reference_bits(endianness, value, count));
+            bits.push(count % 3 == 0);
+        }
+        assert_eq!(bits.len(), stream.bit_len());
+        assert_eq!(pack(endianness, &bits), data);
+
+        let mut read = BitReadStream::new(BitReadBuffer::new(&data, endianness));
+        for &(value, count) in &ints {
+            let read_value = match count {
+                0..=8 => read.read_int::<u8>(count).map(u128::from),
+                9..=16 => read.read_int::<u16>(count).map(u128::from),
+                17..=32 => read.read_int::<u32>(count).map(u128::from),
+                33..=64 => read.read_int::<u64>(count).map(u128::from),
+                _ => read.read_int::<u128>(count),
+            }
+            .unwrap();
+            assert_eq!(value, read_value, "{} bits", count);
+            assert_eq!(count % 3 == 0, read.read_bool().unwrap());
+        }
+
+        // signed, float, byte and string values survive a round trip at any offset
+        let mut data = Vec::new();
+        let mut stream = BitWriteStream::new(&mut data, endianness);
+        for offset in 0..8 {
+            stream.write_int(0, offset).unwrap();
+            stream.write_int(-1234i16, 13).unwrap();
+            stream.write_int(i64::MIN, 64).unwrap();
+            stream.write_float(-1.5f32).unwrap();
+            stream.write_float(std::f64::consts::PI).unwrap();
+            stream.write_bytes(&[1, 2, 3, 250]).unwrap();
+            stream.write_string("hello", None).unwrap();
+            stream.write_ubit_var(0x1234_5678).unwrap();
+            stream
+                .reserve_length(24, |stream| stream.write_int(0xABu8, 8))
+                .unwrap();
+        }
+        let mut read = BitReadStream::new(BitReadBuffer::new(&data, endianness));
+        for offset in 0..8 {
+            read.skip_bits(offset).unwrap();
+            assert_eq!(-1234, read.read_int::<i16>(13).unwrap());
+            assert_eq!(i64::MIN, read.read_int::<i64>(64).unwrap());
+            assert_eq!(-1.5, read.read_float::<f32>().unwrap());
+            assert_eq!(std::f64::consts::PI, read.read_float::<f64>().unwrap());
+            assert_eq!([1, 2, 3, 250], *read.read_bytes(4).unwrap());
+            assert_eq!("hello", read.read_string(None).unwrap());
+            assert_eq!(0x1234_5678, read.read_ubit_var().unwrap());
+            assert_eq!(8, read.read_int::<u32>(24).unwrap());
+            assert_eq!(0xAB, read.read_int::<u8>(8).unwrap());
+        }
+
+        // copying bits from a read stream keeps the bits in order
+        let read = BitReadStream::new(BitReadBuffer::new(&data, endianness));
+        let mut copy = Vec::new();
+        let mut stream = BitWriteStream::new(&mut copy, endianness);
+        stream.write_int(0u8, 3).unwrap();
+        stream.write_bits(&read).unwrap();
+        let mut read = BitReadStream::new(BitReadBuffer::new(&copy, endianness));
+        read.skip_bits(3).unwrap();
+        assert_eq!(
+            BitReadStream::new(BitReadBuffer::new(&data, endianness))
+                .read_bytes(data.len())
+                .unwrap(),
+            read.read_bytes(data.len()).unwrap()
+        );
+    }
+
+    check(LittleEndian);
+    check(BigEndian);
+    check(LittleEndianMsbFirst);
+    check(BigEndianLsbFirst);
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndianMsbFirst);
+    stream.write_int(0b101u8, 3).unwrap();
+    stream.write_int(0x1234u16, 16).unwrap();
+    assert_eq!(data, [0b1010_0110, 0b1000_0010, 0b0100_0000]);
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndianLsbFirst);
+    stream.write_int(0b101u8, 3).unwrap();
+    stream.write_int(0x1234u16, 16).unwrap();
+    assert_eq!(data, [0b1001_0101, 0b1010_0000, 0b0000_0001]);
+}
+
+#[test]
+fn test_write_codes_mixed_bit_order() {
+    use bitbuffer::{BigEndianLsbFirst, Endianness, LittleEndianMsbFirst};
+    use std::num::NonZeroU64;
+
+    fn write_codes<E: Endianness>(endianness: E) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut stream = BitWriteStream::new(&mut data, endianness);
+        for value in [0, 1, 5, 1000, 0x1234_5678, u64::MAX] {
+            stream.write_exp_golomb(value, 0).unwrap();
+            stream.write_exp_golomb(value, 3).unwrap();
+            stream.write_signed_exp_golomb(value as i64, 2).unwrap();
+            stream.write_rice(value % 100_000, 12).unwrap();
+            stream.write_golomb(value % 100_000, 1000).unwrap();
+            let value = NonZeroU64::new(value.max(1)).unwrap();
+            stream.write_elias_gamma(value).unwrap();
+            stream.write_elias_delta(value).unwrap();
+        }
+        data
+    }
+
+    fn read_codes<E: Endianness>(data: &[u8], endianness: E) {
+        let mut stream = BitReadStream::new(BitReadBuffer::new(data, endianness));
+        for value in [0, 1, 5, 1000, 0x1234_5678, u64::MAX] {
+            assert_eq!(value, stream.read_exp_golomb(0).unwrap());
+            assert_eq!(value, stream.read_exp_golomb(3).unwrap());
+            assert_eq!(value as i64, stream.read_signed_exp_golomb(2).unwrap());
+            assert_eq!(value % 100_000, stream.read_rice(12).unwrap());
+            assert_eq!(value % 100_000, stream.read_golomb(1000).unwrap());
+            assert_eq!(value.max(1), stream.read_elias_gamma().unwrap().get());
+            assert_eq!(value.max(1), stream.read_elias_delta().unwrap().get());
+        }
+    }
+
+    // codes are written bit by bit, so only the bit order matters and not the byte order
+    let data = write_codes(LittleEndianMsbFirst);
+    assert_eq!(write_codes(BigEndian), data);
+    read_codes(&data, LittleEndianMsbFirst);
+
+    let data = write_codes(BigEndianLsbFirst);
+    assert_eq!(write_codes(LittleEndian), data);
+    read_codes(&data, BigEndianLsbFirst);
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, LittleEndianMsbFirst);
+    stream.write_exp_golomb(1000, 0).unwrap();
+    stream.write_rice(11, 2).unwrap();
+    assert_eq!(data, [0x00, 0x7D, 0x27]);
+
+    let mut data = Vec::new();
+    let mut stream = BitWriteStream::new(&mut data, BigEndianLsbFirst);
+    stream.write_exp_golomb(1000, 0).unwrap();
+    stream.write_rice(11, 2).unwrap();
+    assert_eq!(data, [0x00, 0xA6, 0xE7]);
+}